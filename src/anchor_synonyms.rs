@@ -0,0 +1,135 @@
+//! `--anchor-synonyms`: Old anchors that are accepted in place of their new name while links are
+//! being migrated, like after renaming a heading.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Error};
+use bumpalo::collections::String as BumpString;
+
+use crate::html::{push_and_canonicalize, try_percent_decode, try_percent_decode_path, AnchorCase};
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AnchorSynonyms {
+    /// New anchors by old anchor, for all pages. Anchors are stored without `#`.
+    global: BTreeMap<String, String>,
+    /// New anchors by canonical href of the page and old anchor.
+    per_page: BTreeMap<(String, String), String>,
+}
+
+impl AnchorSynonyms {
+    pub fn read(
+        path: &Path,
+        collapse_index_html: bool,
+        strict_trailing_slash: bool,
+        anchor_case: AnchorCase,
+    ) -> Result<Self, Error> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read anchor synonyms {}", path.display()))?;
+        Self::parse(
+            &contents,
+            collapse_index_html,
+            strict_trailing_slash,
+            anchor_case,
+        )
+        .with_context(|| format!("Failed to parse anchor synonyms {}", path.display()))
+    }
+
+    /// Parse one synonym per line, like `#old = #new` for all pages or `/guide.html#old = #new`
+    /// for one page. Empty lines and lines starting with `# ` are ignored.
+    pub fn parse(
+        contents: &str,
+        collapse_index_html: bool,
+        strict_trailing_slash: bool,
+        anchor_case: AnchorCase,
+    ) -> Result<Self, Error> {
+        let arena = bumpalo::Bump::new();
+        let mut synonyms = AnchorSynonyms::default();
+
+        for (i, line) in contents.lines().map(str::trim).enumerate() {
+            if line.is_empty() || line.starts_with("# ") {
+                continue;
+            }
+
+            let invalid = || anyhow!("line {}: expected OLD = #NEW, got {:?}", i + 1, line);
+            let (old, new) = line.split_once('=').ok_or_else(invalid)?;
+            let (page, old_anchor) = old.trim().split_once('#').ok_or_else(invalid)?;
+            let new_anchor = new.trim().strip_prefix('#').ok_or_else(invalid)?;
+            if old_anchor.is_empty() || new_anchor.is_empty() {
+                return Err(invalid());
+            }
+
+            let old_anchor = anchor_case
+                .fold(&try_percent_decode(old_anchor))
+                .into_owned();
+            let new_anchor = anchor_case
+                .fold(&try_percent_decode(new_anchor))
+                .into_owned();
+
+            if page.is_empty() {
+                synonyms.global.insert(old_anchor, new_anchor);
+            } else {
+                let mut href = BumpString::new_in(&arena);
+                push_and_canonicalize(
+                    &mut href,
+                    &try_percent_decode_path(page),
+                    collapse_index_html,
+                    strict_trailing_slash,
+                );
+                synonyms
+                    .per_page
+                    .insert((href.to_string(), old_anchor), new_anchor);
+            }
+        }
+
+        Ok(synonyms)
+    }
+
+    /// The canonical href that `href`, like `guide.html#old`, is a synonym of.
+    pub fn resolve(&self, href: &str) -> Option<String> {
+        let (page, anchor) = href.split_once('#')?;
+        let new_anchor = self
+            .per_page
+            .get(&(page.to_owned(), anchor.to_owned()))
+            .or_else(|| self.global.get(anchor))?;
+        Some(format!("{page}#{new_anchor}"))
+    }
+}
+
+#[test]
+fn test_anchor_synonyms() {
+    let synonyms = AnchorSynonyms::parse(
+        "
+        # renamed in v2
+        #install = #installation
+        /docs/guide.html#old-name = #new-name
+        docs/index.html#Setup = #getting-started
+        ",
+        true,
+        false,
+        AnchorCase::Ascii,
+    )
+    .unwrap();
+
+    assert_eq!(
+        synonyms.resolve("about.html#install").as_deref(),
+        Some("about.html#installation")
+    );
+    assert_eq!(
+        synonyms.resolve("docs/guide.html#old-name").as_deref(),
+        Some("docs/guide.html#new-name")
+    );
+    assert_eq!(
+        synonyms.resolve("docs#setup").as_deref(),
+        Some("docs#getting-started")
+    );
+    assert_eq!(synonyms.resolve("about.html#old-name"), None);
+    assert_eq!(synonyms.resolve("docs/guide.html"), None);
+
+    let err = AnchorSynonyms::parse("#old #new", true, false, AnchorCase::Sensitive).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "line 1: expected OLD = #NEW, got \"#old #new\""
+    );
+}
@@ -0,0 +1,96 @@
+//! `--from-tar`: Check a site from a tar archive, without extracting it to disk first.
+
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Error};
+
+/// All regular files of a tar archive, read into memory.
+pub struct TarFiles {
+    files: Vec<(PathBuf, Arc<[u8]>)>,
+}
+
+impl TarFiles {
+    pub fn read(read: impl Read) -> Result<Self, Error> {
+        let mut archive = tar::Archive::new(read);
+        let mut files = Vec::new();
+
+        for entry in archive.entries().context("Failed to read tar archive")? {
+            let mut entry = entry.context("Failed to read tar archive")?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+
+            // Archives created with `tar -C public .` have paths like `./index.html`.
+            let path: PathBuf = entry
+                .path()?
+                .components()
+                .filter(|component| matches!(component, Component::Normal(_)))
+                .collect();
+
+            let mut contents = Vec::new();
+            entry
+                .read_to_end(&mut contents)
+                .with_context(|| format!("Failed to read {} from tar archive", path.display()))?;
+            files.push((path, contents.into()));
+        }
+
+        Ok(TarFiles { files })
+    }
+
+    /// All files inside of `root_path`.
+    pub fn files_in<'a>(
+        &'a self,
+        root_path: &'a Path,
+    ) -> impl Iterator<Item = (&'a PathBuf, &'a Arc<[u8]>)> {
+        self.files
+            .iter()
+            .filter(move |(path, _)| path.starts_with(root_path))
+            .map(|(path, contents)| (path, contents))
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&[u8]> {
+        self.files
+            .iter()
+            .find(|(file_path, _)| file_path == path)
+            .map(|(_, contents)| &contents[..])
+    }
+}
+
+#[test]
+fn test_tar_files() {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, contents) in [
+        ("./public/index.html", "<a href=foo.html>"),
+        ("./public/foo.html", ""),
+        ("./README.md", "# readme"),
+    ] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, path, contents.as_bytes())
+            .unwrap();
+    }
+    let archive = builder.into_inner().unwrap();
+
+    let files = TarFiles::read(&archive[..]).unwrap();
+    let paths: Vec<_> = files
+        .files_in(Path::new("public"))
+        .map(|(path, _)| path.clone())
+        .collect();
+    assert_eq!(
+        paths,
+        &[
+            PathBuf::from("public/index.html"),
+            PathBuf::from("public/foo.html")
+        ]
+    );
+    assert_eq!(
+        files.get(Path::new("public/index.html")),
+        Some(&b"<a href=foo.html>"[..])
+    );
+    assert_eq!(files.get(Path::new("index.html")), None);
+}
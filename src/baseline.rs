@@ -0,0 +1,172 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Error};
+
+static HEADER: &str =
+    "# hyperlink baseline: one known broken link per line, tab-separated <kind> <path> <href>";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum BaselineKind {
+    Link,
+    Anchor,
+}
+
+impl BaselineKind {
+    pub fn new(hard_404: bool) -> Self {
+        if hard_404 {
+            BaselineKind::Link
+        } else {
+            BaselineKind::Anchor
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BaselineKind::Link => "link",
+            BaselineKind::Anchor => "anchor",
+        }
+    }
+}
+
+/// A known broken link. `path` is the path of the document containing the link, relative to the
+/// base path and always using forward slashes, so that baselines can be shared across machines.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct BaselineEntry {
+    pub path: String,
+    pub href: String,
+    pub kind: BaselineKind,
+}
+
+impl BaselineEntry {
+    pub fn new(base_path: &Path, path: &Path, href: &str, hard_404: bool) -> Self {
+        let path = path.strip_prefix(base_path).unwrap_or(path);
+        let mut path = path.to_string_lossy().into_owned();
+
+        if cfg!(windows) {
+            path = path.replace('\\', "/");
+        }
+
+        BaselineEntry {
+            path,
+            href: href.to_owned(),
+            kind: BaselineKind::new(hard_404),
+        }
+    }
+}
+
+/// A set of broken links that should not fail the build, loaded from the file passed via
+/// `--baseline`.
+pub struct Baseline {
+    entries: BTreeSet<BaselineEntry>,
+}
+
+impl Baseline {
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let file = fs::File::open(path)
+            .with_context(|| format!("Failed to read baseline {}", path.display()))?;
+        Self::parse(path, BufReader::new(file))
+    }
+
+    /// Parse the lines of a baseline. `path` is only used for error messages.
+    fn parse(path: &Path, reader: impl BufRead) -> Result<Self, Error> {
+        let mut entries = BTreeSet::new();
+
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            // Baselines edited on Windows may use CRLF line endings.
+            let line = line.strip_suffix('\r').unwrap_or(&line);
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.splitn(3, '\t');
+            let kind = match parts.next() {
+                Some("link") => BaselineKind::Link,
+                Some("anchor") => BaselineKind::Anchor,
+                _ => {
+                    return Err(anyhow!(
+                        "Invalid baseline {}, unknown kind at line {}",
+                        path.display(),
+                        i + 1
+                    ))
+                }
+            };
+
+            match (parts.next(), parts.next()) {
+                (Some(path), Some(href)) => {
+                    entries.insert(BaselineEntry {
+                        path: path.to_owned(),
+                        href: href.to_owned(),
+                        kind,
+                    });
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "Invalid baseline {}, missing fields at line {}",
+                        path.display(),
+                        i + 1
+                    ))
+                }
+            }
+        }
+
+        Ok(Baseline { entries })
+    }
+
+    pub fn write(path: &Path, entries: &BTreeSet<BaselineEntry>) -> Result<(), Error> {
+        let file = fs::File::create(path)
+            .with_context(|| format!("Failed to write baseline {}", path.display()))?;
+        let mut file = io::BufWriter::new(file);
+
+        writeln!(file, "{HEADER}")?;
+        for entry in entries {
+            writeln!(
+                file,
+                "{}\t{}\t{}",
+                entry.kind.as_str(),
+                entry.path,
+                entry.href
+            )?;
+        }
+
+        file.flush()?;
+        Ok(())
+    }
+
+    pub fn contains(&self, entry: &BaselineEntry) -> bool {
+        self.entries.contains(entry)
+    }
+
+    /// Entries of the baseline that were not part of `still_broken`, i.e. links that have been
+    /// fixed since the baseline was written.
+    pub fn stale_entries<'a>(
+        &'a self,
+        still_broken: &'a BTreeSet<BaselineEntry>,
+    ) -> impl Iterator<Item = &'a BaselineEntry> {
+        self.entries.difference(still_broken)
+    }
+}
+
+#[test]
+fn test_parse() {
+    let baseline =
+        format!("{HEADER}\r\nlink\tindex.html\tbar.html\r\n\r\nanchor\tindex.html\t#intro\n");
+    let baseline = Baseline::parse(Path::new("baseline.txt"), baseline.as_bytes()).unwrap();
+
+    assert!(baseline.contains(&BaselineEntry {
+        path: "index.html".to_owned(),
+        href: "bar.html".to_owned(),
+        kind: BaselineKind::Link,
+    }));
+    assert!(baseline.contains(&BaselineEntry {
+        path: "index.html".to_owned(),
+        href: "#intro".to_owned(),
+        kind: BaselineKind::Anchor,
+    }));
+    assert_eq!(baseline.entries.len(), 2);
+
+    assert!(Baseline::parse(Path::new("baseline.txt"), "link\tindex.html\n".as_bytes()).is_err());
+}
@@ -5,7 +5,13 @@ use std::sync::Arc;
 use bumpalo::collections::String as BumpString;
 use bumpalo::Bump;
 
-use crate::html::{push_and_canonicalize, try_percent_decode, Href, Link, UsedLink};
+use crate::anchor_synonyms::AnchorSynonyms;
+use crate::html::{
+    push_and_canonicalize, try_percent_decode, DirectoryIndex, Document, Href, Link, ParserOptions,
+    Position, UsedLink, Warning,
+};
+use crate::redirects::Redirects;
+use crate::rewrite_map::RewriteMap;
 use crate::urls::is_external_link;
 
 pub trait LinkCollector<P>: Send {
@@ -17,8 +23,14 @@ pub trait LinkCollector<P>: Send {
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub struct OwnedUsedLink<P> {
     pub href: String,
+    pub raw_href: String,
     pub path: Arc<PathBuf>,
     pub paragraph: Option<P>,
+    pub element: &'static str,
+    pub media: Option<String>,
+    pub referrer: Option<&'static str>,
+    pub absolute: bool,
+    pub position: Option<Position>,
 }
 
 /// Collects only used links for match-all-paragraphs command. Discards defined links.
@@ -37,8 +49,14 @@ impl<P: Send> LinkCollector<P> for UsedLinkCollector<P> {
         if let Link::Uses(used_link) = link {
             self.used_links.push(OwnedUsedLink {
                 href: used_link.href.0.to_owned(),
+                raw_href: used_link.raw_href.to_owned(),
                 path: used_link.path.to_owned(),
                 paragraph: used_link.paragraph,
+                element: used_link.element,
+                media: used_link.media.map(str::to_owned),
+                referrer: used_link.referrer,
+                absolute: used_link.absolute,
+                position: used_link.position,
             });
         }
     }
@@ -48,28 +66,59 @@ impl<P: Send> LinkCollector<P> for UsedLinkCollector<P> {
     }
 }
 
+/// Where a link is used: the document, paragraph, element, its media condition, what references
+/// the id in the href, the href as written and its position.
+type LinkUsage<P> = (
+    Arc<PathBuf>,
+    Option<P>,
+    &'static str,
+    Option<String>,
+    Option<&'static str>,
+    String,
+    Option<Position>,
+);
+
 #[derive(Debug)]
 enum LinkState<P> {
-    /// We have observed a DefinedLink for this href
-    Defined,
+    /// We have observed a DefinedLink for this href. `used` tracks whether we have also observed a
+    /// UsedLink pointing to it.
+    Defined { used: bool },
     /// We have not *yet* observed a DefinedLink and therefore need to keep track of all link
     /// usages for potential error reporting.
-    Undefined(Vec<(Arc<PathBuf>, Option<P>)>),
+    Undefined(Vec<LinkUsage<P>>),
 }
 
 impl<P: Copy> LinkState<P> {
     fn add_usage(&mut self, link: &UsedLink<P>) {
-        if let LinkState::Undefined(ref mut links) = self {
-            links.push((link.path.clone(), link.paragraph));
+        match self {
+            LinkState::Defined { used } => *used = true,
+            LinkState::Undefined(ref mut links) => {
+                links.push((
+                    link.path.clone(),
+                    link.paragraph,
+                    link.element,
+                    link.media.map(str::to_owned),
+                    link.referrer,
+                    link.raw_href.to_owned(),
+                    link.position,
+                ));
+            }
         }
     }
 
+    fn define(&mut self) {
+        // An Undefined state always contains at least one usage.
+        let used = !matches!(self, LinkState::Defined { used: false });
+        *self = LinkState::Defined { used };
+    }
+
     fn update(&mut self, other: Self) {
-        match self {
-            LinkState::Defined => (),
-            LinkState::Undefined(links) => match other {
-                LinkState::Defined => *self = LinkState::Defined,
-                LinkState::Undefined(links2) => links.extend(links2),
+        match other {
+            LinkState::Defined { used: false } => self.define(),
+            LinkState::Defined { used: true } => *self = LinkState::Defined { used: true },
+            LinkState::Undefined(links2) => match self {
+                LinkState::Defined { used } => *used = true,
+                LinkState::Undefined(links) => links.extend(links2),
             },
         }
     }
@@ -98,6 +147,8 @@ pub fn canonicalize_local_link<'a, P>(arena: &Bump, mut link: Link<'a, P>) -> Op
         push_and_canonicalize(
             &mut href,
             &try_percent_decode(&used_link.href.0[..qs_start]),
+            true,
+            false,
         );
     }
 
@@ -123,17 +174,114 @@ impl<P, C: LinkCollector<P>> LinkCollector<P> for LocalLinksOnly<C> {
     }
 }
 
+/// Collects all used links, and which of them are broken, for the emit-inventory command.
+pub struct InventoryCollector<P> {
+    pub used_links: UsedLinkCollector<P>,
+    pub local_links: LocalLinksOnly<BrokenLinkCollector<P>>,
+}
+
+impl<P: Send + Copy> LinkCollector<P> for InventoryCollector<P> {
+    fn new() -> Self {
+        InventoryCollector {
+            used_links: UsedLinkCollector::new(),
+            local_links: LocalLinksOnly::new(),
+        }
+    }
+
+    fn ingest(&mut self, link: Link<'_, P>) {
+        if let Link::Uses(_) = link {
+            self.used_links.ingest(link.clone());
+        }
+        self.local_links.ingest(link);
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.used_links.merge(other.used_links);
+        self.local_links.merge(other.local_links);
+    }
+}
+
 /// Link collector used for actual link checking. Keeps track of broken links only.
 pub struct BrokenLinkCollector<P> {
     links: BTreeMap<String, LinkState<P>>,
+    warnings: Vec<Warning>,
     used_link_count: usize,
+    /// Number of used links per document. A document's links are ingested in one go, so we only
+    /// need to compare against the last entry.
+    used_links_per_page: Vec<(Arc<PathBuf>, usize)>,
+    /// Anchors on elements that `--anchor-element-policy` does not expect, with the document and
+    /// tag name.
+    unexpected_anchor_elements: Vec<(String, Arc<PathBuf>, String)>,
+    /// Targets of documents that redirect with `<meta http-equiv=refresh>`, written like targets
+    /// in `_redirects`. See `--follow-meta-refresh-chain`.
+    meta_refreshes: BTreeMap<String, String>,
+    /// Anchors as written in the document that defines them, see
+    /// `--warn-anchor-encoding-mismatch`.
+    raw_anchors: BTreeMap<String, String>,
+    /// Links whose anchor is written with percent-encoding while the id it points to is not, or
+    /// the other way around. Contains the document, the link as written and the id as written.
+    encoding_mismatches: Vec<(Arc<PathBuf>, String, String)>,
+    /// Links starting with a single slash, which are broken whatever they point to. See
+    /// `--filesystem-mode`.
+    absolute_links: Vec<OwnedUsedLink<P>>,
+}
+
+/// `href` with `--anchor-prefix` removed from its anchor, or added if the anchor does not have it.
+fn prefixed_anchor(href: &str, options: &CheckOptions) -> Option<String> {
+    let prefix = options.anchor_prefix.as_deref()?;
+    let (page, anchor) = href.split_at(href.find('#')?);
+    let anchor = &anchor[1..];
+    match anchor.strip_prefix(prefix) {
+        Some(unprefixed) => Some(format!("{page}#{unprefixed}")),
+        None => Some(format!("{page}#{prefix}{anchor}")),
+    }
+}
+
+/// Whether `anchor` contains percent-encoded characters.
+pub fn is_percent_encoded(anchor: &str) -> bool {
+    try_percent_decode(anchor) != anchor
+}
+
+/// Whether a link written as `raw_href` uses a different encoding than the anchor it points to,
+/// which is written as `raw_anchor`.
+fn is_encoding_mismatch(raw_href: &str, raw_anchor: &str) -> bool {
+    match raw_href.split_once('#') {
+        Some((_, anchor)) => is_percent_encoded(anchor) != is_percent_encoded(raw_anchor),
+        None => false,
+    }
+}
+
+impl<P> BrokenLinkCollector<P> {
+    /// Record how the anchor `href` is written, and compare it with the links to it seen so far.
+    fn add_raw_anchor(&mut self, href: String, raw_anchor: String) {
+        if let Some(LinkState::Undefined(links)) = self.links.get(&href) {
+            for (path, _, _, _, _, raw_href, _) in links {
+                if is_encoding_mismatch(raw_href, &raw_anchor) {
+                    self.encoding_mismatches.push((
+                        path.clone(),
+                        raw_href.clone(),
+                        raw_anchor.clone(),
+                    ));
+                }
+            }
+        }
+
+        self.raw_anchors.insert(href, raw_anchor);
+    }
 }
 
 impl<P: Send + Copy> LinkCollector<P> for BrokenLinkCollector<P> {
     fn new() -> Self {
         BrokenLinkCollector {
             links: BTreeMap::new(),
+            warnings: Vec::new(),
             used_link_count: 0,
+            used_links_per_page: Vec::new(),
+            unexpected_anchor_elements: Vec::new(),
+            meta_refreshes: BTreeMap::new(),
+            raw_anchors: BTreeMap::new(),
+            encoding_mismatches: Vec::new(),
+            absolute_links: Vec::new(),
         }
     }
 
@@ -142,6 +290,36 @@ impl<P: Send + Copy> LinkCollector<P> for BrokenLinkCollector<P> {
             Link::Uses(used_link) => {
                 self.used_link_count += 1;
 
+                match self.used_links_per_page.last_mut() {
+                    Some((path, count)) if Arc::ptr_eq(path, &used_link.path) => *count += 1,
+                    _ => self.used_links_per_page.push((used_link.path.clone(), 1)),
+                }
+
+                if used_link.absolute {
+                    self.absolute_links.push(OwnedUsedLink {
+                        href: used_link.href.0.to_owned(),
+                        raw_href: used_link.raw_href.to_owned(),
+                        path: used_link.path,
+                        paragraph: used_link.paragraph,
+                        element: used_link.element,
+                        media: used_link.media.map(str::to_owned),
+                        referrer: used_link.referrer,
+                        absolute: true,
+                        position: used_link.position,
+                    });
+                    return;
+                }
+
+                if let Some(raw_anchor) = self.raw_anchors.get(used_link.href.0) {
+                    if is_encoding_mismatch(used_link.raw_href, raw_anchor) {
+                        self.encoding_mismatches.push((
+                            used_link.path.clone(),
+                            used_link.raw_href.to_owned(),
+                            raw_anchor.clone(),
+                        ));
+                    }
+                }
+
                 self.links
                     .entry(used_link.href.0.to_owned())
                     .and_modify(|state| state.add_usage(&used_link))
@@ -152,14 +330,62 @@ impl<P: Send + Copy> LinkCollector<P> for BrokenLinkCollector<P> {
                     });
             }
             Link::Defines(defined_link) => {
+                if let Some((path, element)) = defined_link.unexpected_element {
+                    self.unexpected_anchor_elements.push((
+                        defined_link.href.0.to_owned(),
+                        path,
+                        element.to_owned(),
+                    ));
+                }
+
+                if let Some(raw_anchor) = defined_link.raw_anchor {
+                    self.add_raw_anchor(defined_link.href.0.to_owned(), raw_anchor.to_owned());
+                }
+
                 self.links
-                    .insert(defined_link.href.0.to_owned(), LinkState::Defined);
+                    .entry(defined_link.href.0.to_owned())
+                    .and_modify(LinkState::define)
+                    .or_insert(LinkState::Defined { used: false });
+            }
+            Link::Warns(warning) => self.warnings.push(warning),
+            Link::Redirects(redirect) => {
+                let to = if is_external_link(redirect.to.0.as_bytes()) {
+                    redirect.to.0.to_owned()
+                } else {
+                    format!("/{}", redirect.to.0)
+                };
+                self.meta_refreshes.insert(redirect.from.0.to_owned(), to);
             }
         }
     }
 
     fn merge(&mut self, other: Self) {
         self.used_link_count += other.used_link_count;
+        self.used_links_per_page.extend(other.used_links_per_page);
+        self.warnings.extend(other.warnings);
+        self.unexpected_anchor_elements
+            .extend(other.unexpected_anchor_elements);
+        self.meta_refreshes.extend(other.meta_refreshes);
+        self.encoding_mismatches.extend(other.encoding_mismatches);
+        self.absolute_links.extend(other.absolute_links);
+
+        // Links of one collector may point to anchors of the other.
+        for (href, raw_anchor) in &self.raw_anchors {
+            if let Some(LinkState::Undefined(links)) = other.links.get(href) {
+                for (path, _, _, _, _, raw_href, _) in links {
+                    if is_encoding_mismatch(raw_href, raw_anchor) {
+                        self.encoding_mismatches.push((
+                            path.clone(),
+                            raw_href.clone(),
+                            raw_anchor.clone(),
+                        ));
+                    }
+                }
+            }
+        }
+        for (href, raw_anchor) in other.raw_anchors {
+            self.add_raw_anchor(href, raw_anchor);
+        }
 
         for (href, other_state) in other.links {
             if let Some(state) = self.links.get_mut(&href) {
@@ -171,6 +397,25 @@ impl<P: Send + Copy> LinkCollector<P> for BrokenLinkCollector<P> {
     }
 }
 
+/// Options that control which links are considered broken.
+#[derive(Clone, Debug, Default)]
+pub struct CheckOptions {
+    pub check_anchors: bool,
+    pub directory_index: DirectoryIndex,
+    /// Extension to try appending to links without one, see `--assume-extension`.
+    pub assume_extension: Option<String>,
+    /// Rules from the site's `_redirects` file.
+    pub redirects: Redirects,
+    /// Old anchors that are accepted in place of new ones, see `--anchor-synonyms`.
+    pub anchor_synonyms: Option<AnchorSynonyms>,
+    /// Prefix that anchors are accepted with and without, see `--anchor-prefix`.
+    pub anchor_prefix: Option<String>,
+    /// Clean URLs served from files with other names, see `--rewrite-map`.
+    pub rewrite_map: Option<RewriteMap>,
+    /// Only return the first broken link, preferring hard 404s, see `--fail-fast`.
+    pub fail_fast: bool,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub struct BrokenLink<P> {
     pub hard_404: bool,
@@ -178,37 +423,848 @@ pub struct BrokenLink<P> {
 }
 
 impl<P: Copy + PartialEq> BrokenLinkCollector<P> {
-    pub fn get_broken_links(&self, check_anchors: bool) -> impl Iterator<Item = BrokenLink<P>> {
+    fn is_defined_exactly(&self, href: &str) -> bool {
+        matches!(self.links.get(href), Some(LinkState::Defined { .. }))
+    }
+
+    /// Whether `href` is defined, taking into account the fallbacks configured in `options` and
+    /// `_redirects`.
+    fn is_defined(&self, href: &str, options: &CheckOptions) -> bool {
+        let page = Href(href).without_anchor().0;
+        self.is_defined_without_redirects(href, options)
+            || options.redirects.resolve(page).is_some()
+            || self.meta_refreshes.contains_key(page)
+    }
+
+    /// The URL that `href` is redirected to by `_redirects` or `<meta http-equiv=refresh>`.
+    fn redirect_target(&self, href: &str, options: &CheckOptions) -> Option<String> {
+        options
+            .redirects
+            .redirect_target(href)
+            .or_else(|| self.meta_refreshes.get(href).cloned())
+    }
+
+    fn is_defined_without_redirects(&self, href: &str, options: &CheckOptions) -> bool {
+        self.is_defined_with_fallbacks(href, options)
+            || prefixed_anchor(href, options)
+                .is_some_and(|href| self.is_defined_with_fallbacks(&href, options))
+    }
+
+    fn is_defined_with_fallbacks(&self, href: &str, options: &CheckOptions) -> bool {
+        if self.is_defined_exactly(href) {
+            return true;
+        }
+
+        let (page, anchor) = href.split_at(href.find('#').unwrap_or(href.len()));
+        if page.is_empty() {
+            return false;
+        }
+
+        if let Some(file) = options
+            .rewrite_map
+            .as_ref()
+            .and_then(|rewrite_map| rewrite_map.resolve(page))
+        {
+            if self.is_defined_exactly(&format!("{file}{anchor}")) {
+                return true;
+            }
+        }
+
+        if options.directory_index == DirectoryIndex::SameNameHtml
+            && self.is_defined_exactly(&format!("{page}.html{anchor}"))
+        {
+            return true;
+        }
+
+        if let Some(ref extension) = options.assume_extension {
+            let file_name = &page[page.rfind('/').map_or(0, |i| i + 1)..];
+            if !file_name.contains('.')
+                && self.is_defined_exactly(&format!("{page}.{extension}{anchor}"))
+            {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// The href that `href` is a synonym of, if that one is defined.
+    fn defined_synonym(&self, href: &str, options: &CheckOptions) -> Option<String> {
+        let synonym = options.anchor_synonyms.as_ref()?.resolve(href)?;
+        if self.is_defined(&synonym, options) {
+            Some(synonym)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_broken_links(&self, options: &CheckOptions) -> impl Iterator<Item = BrokenLink<P>> {
         let mut broken_links = Vec::new();
 
+        if options.fail_fast {
+            if let Some(link) = self.absolute_links.first() {
+                broken_links.push(BrokenLink {
+                    hard_404: true,
+                    link: link.clone(),
+                });
+                return broken_links.into_iter();
+            }
+        }
+
         for (href, state) in self.links.iter() {
             if let LinkState::Undefined(links) = state {
-                let hard_404 = if check_anchors {
-                    !matches!(
-                        self.links.get(Href(href).without_anchor().0),
-                        Some(&LinkState::Defined)
-                    )
+                if self.is_defined(href, options) || self.defined_synonym(href, options).is_some() {
+                    continue;
+                }
+
+                let hard_404 = if options.check_anchors {
+                    !self.is_defined(Href(href).without_anchor().0, options)
                 } else {
                     true
                 };
 
-                for (path, paragraph) in links.iter() {
-                    broken_links.push(BrokenLink {
+                for (path, paragraph, element, media, referrer, raw_href, position) in links.iter()
+                {
+                    let broken_link = BrokenLink {
                         hard_404,
                         link: OwnedUsedLink {
                             path: path.clone(),
                             paragraph: *paragraph,
                             href: href.clone(),
+                            raw_href: raw_href.clone(),
+                            element,
+                            media: media.clone(),
+                            referrer: *referrer,
+                            absolute: false,
+                            position: *position,
                         },
-                    });
+                    };
+
+                    if options.fail_fast {
+                        // A hard 404 determines the exit code, so only a broken anchor makes us
+                        // keep looking.
+                        broken_links.clear();
+                        broken_links.push(broken_link);
+                        break;
+                    }
+
+                    broken_links.push(broken_link);
+                }
+
+                if options.fail_fast && hard_404 {
+                    return broken_links.into_iter();
                 }
             }
         }
 
+        for link in &self.absolute_links {
+            broken_links.push(BrokenLink {
+                hard_404: true,
+                link: link.clone(),
+            });
+        }
+
         broken_links.into_iter()
     }
 
+    /// Links that only work because `_redirects` redirects them, together with the target of the
+    /// redirect.
+    pub fn get_redirected_links<'a>(
+        &'a self,
+        options: &'a CheckOptions,
+    ) -> impl Iterator<Item = (OwnedUsedLink<P>, &'a str)> {
+        let mut redirected_links = Vec::new();
+
+        for (href, state) in self.links.iter() {
+            if let LinkState::Undefined(links) = state {
+                if self.is_defined_without_redirects(href, options) {
+                    continue;
+                }
+
+                let page = Href(href).without_anchor().0;
+                let to = match options.redirects.resolve(page) {
+                    Some(redirect) if redirect.is_redirect => redirect.to,
+                    Some(_) => continue,
+                    None => match self.meta_refreshes.get(page) {
+                        Some(to) => to.as_str(),
+                        None => continue,
+                    },
+                };
+
+                for (path, paragraph, element, media, referrer, raw_href, position) in links.iter()
+                {
+                    redirected_links.push((
+                        OwnedUsedLink {
+                            path: path.clone(),
+                            paragraph: *paragraph,
+                            href: href.clone(),
+                            raw_href: raw_href.clone(),
+                            element,
+                            media: media.clone(),
+                            referrer: *referrer,
+                            absolute: false,
+                            position: *position,
+                        },
+                        to,
+                    ));
+                }
+            }
+        }
+
+        redirected_links.into_iter()
+    }
+
+    /// Links whose anchor is written with percent-encoding while the id is not, or the other way
+    /// around, as the document, the link as written and the id as written.
+    pub fn get_anchor_encoding_mismatches(
+        &self,
+    ) -> impl Iterator<Item = &(Arc<PathBuf>, String, String)> {
+        self.encoding_mismatches.iter()
+    }
+
+    /// Links to old anchors that only work because of `--anchor-synonyms`, together with the href
+    /// they should link to instead.
+    pub fn get_anchor_synonym_links(
+        &self,
+        options: &CheckOptions,
+    ) -> impl Iterator<Item = (OwnedUsedLink<P>, String)> {
+        let mut synonym_links = Vec::new();
+
+        for (href, state) in self.links.iter() {
+            if let LinkState::Undefined(links) = state {
+                if self.is_defined(href, options) {
+                    continue;
+                }
+
+                let synonym = match self.defined_synonym(href, options) {
+                    Some(synonym) => synonym,
+                    None => continue,
+                };
+
+                for (path, paragraph, element, media, referrer, raw_href, position) in links.iter()
+                {
+                    synonym_links.push((
+                        OwnedUsedLink {
+                            path: path.clone(),
+                            paragraph: *paragraph,
+                            href: href.clone(),
+                            raw_href: raw_href.clone(),
+                            element,
+                            media: media.clone(),
+                            referrer: *referrer,
+                            absolute: false,
+                            position: *position,
+                        },
+                        synonym.clone(),
+                    ));
+                }
+            }
+        }
+
+        synonym_links.into_iter()
+    }
+
+    /// Rules in `_redirects` whose target does not exist, as the source of the rule, its target
+    /// as written and the target resolved like a link from `root`. See
+    /// `--warn-on-redirect-to-404`.
+    pub fn get_redirects_to_missing_files<'a>(
+        &self,
+        options: &'a CheckOptions,
+        root: &Document,
+        parser_options: &ParserOptions,
+    ) -> impl Iterator<Item = (String, &'a str, String)> {
+        let arena = Bump::new();
+        let mut missing = Vec::new();
+
+        for (from, to) in options.redirects.local_targets() {
+            let target = &to[..to.find(&['?', '#'][..]).unwrap_or(to.len())];
+            let href = root.join(&arena, parser_options, target);
+            if !self.is_defined(href.0, options) {
+                missing.push((from, to, href.0.to_owned()));
+            }
+        }
+
+        missing.into_iter()
+    }
+
+    /// Links that only work because of `_redirects`, together with every URL they are redirected
+    /// to until a file or external URL is reached. Redirect loops end the chain.
+    pub fn get_redirect_chains(
+        &self,
+        options: &CheckOptions,
+    ) -> impl Iterator<Item = (OwnedUsedLink<P>, Vec<String>)> {
+        let mut redirect_chains = Vec::new();
+
+        for (href, state) in self.links.iter() {
+            if let LinkState::Undefined(links) = state {
+                if self.is_defined_without_redirects(href, options) {
+                    continue;
+                }
+
+                let mut chain: Vec<String> = Vec::new();
+                let mut current = Href(href).without_anchor().0.to_owned();
+                while let Some(target) = self.redirect_target(&current, options) {
+                    let next = target[..target.find(&['?', '#'][..]).unwrap_or(target.len())]
+                        .trim_matches('/')
+                        .to_owned();
+                    let is_loop = next == Href(href).without_anchor().0
+                        || chain.iter().any(|hop| hop.trim_matches('/') == next);
+                    let is_final = is_external_link(target.as_bytes())
+                        || self.is_defined_without_redirects(&next, options);
+                    chain.push(target);
+                    if is_loop || is_final {
+                        break;
+                    }
+                    current = next;
+                }
+
+                if chain.is_empty() {
+                    continue;
+                }
+
+                for (path, paragraph, element, media, referrer, raw_href, position) in links.iter()
+                {
+                    redirect_chains.push((
+                        OwnedUsedLink {
+                            path: path.clone(),
+                            paragraph: *paragraph,
+                            href: href.clone(),
+                            raw_href: raw_href.clone(),
+                            element,
+                            media: media.clone(),
+                            referrer: *referrer,
+                            absolute: false,
+                            position: *position,
+                        },
+                        chain.clone(),
+                    ));
+                }
+            }
+        }
+
+        redirect_chains.into_iter()
+    }
+
+    /// Anchors that are defined somewhere, but never linked to.
+    pub fn get_unused_anchors(&self) -> impl Iterator<Item = &str> {
+        self.links.iter().filter_map(|(href, state)| match state {
+            LinkState::Defined { used: false } if href.contains('#') => Some(href.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Anchors that are linked to, but defined on an element that `--anchor-element-policy` does
+    /// not expect, with the document and tag name.
+    pub fn get_unexpected_anchor_elements(
+        &self,
+    ) -> impl Iterator<Item = (&str, &Arc<PathBuf>, &str)> {
+        self.unexpected_anchor_elements
+            .iter()
+            .filter(move |(href, _, _)| {
+                matches!(
+                    self.links.get(href),
+                    Some(LinkState::Defined { used: true })
+                )
+            })
+            .map(|(href, path, element)| (href.as_str(), path, element.as_str()))
+    }
+
+    pub fn get_warnings(&self) -> impl Iterator<Item = &Warning> {
+        self.warnings.iter()
+    }
+
     pub fn used_links_count(&self) -> usize {
         self.used_link_count
     }
+
+    pub fn used_links_per_page(&self) -> impl Iterator<Item = (&Arc<PathBuf>, usize)> {
+        self.used_links_per_page
+            .iter()
+            .map(|(path, count)| (path, *count))
+    }
+}
+
+/// A link to `href` from the document at `path`, written as `raw_href`.
+#[cfg(test)]
+fn uses<'a>(path: &Arc<PathBuf>, href: &'a str, raw_href: &'a str) -> Link<'a, ()> {
+    Link::Uses(UsedLink {
+        href: Href(href),
+        raw_href,
+        path: path.clone(),
+        paragraph: None,
+        element: "<a href>",
+        media: None,
+        referrer: None,
+        absolute: false,
+        position: None,
+    })
+}
+
+/// A definition of the document or anchor `href`.
+#[cfg(test)]
+fn defines(href: &str) -> Link<'_, ()> {
+    Link::Defines(crate::html::DefinedLink {
+        href: Href(href),
+        unexpected_element: None,
+        raw_anchor: None,
+    })
+}
+
+#[test]
+fn test_assume_extension() {
+    let mut collector = BrokenLinkCollector::<()>::new();
+    let path = Arc::new(PathBuf::from("public/index.html"));
+
+    for href in ["about.html", "about.html#team", "contact.htm"] {
+        collector.ingest(defines(href));
+    }
+
+    for href in ["about", "about#team", "about#missing", "contact", "v1.0"] {
+        collector.ingest(uses(&path, href, href));
+    }
+
+    let options = CheckOptions {
+        check_anchors: true,
+        assume_extension: Some("html".to_owned()),
+        ..Default::default()
+    };
+
+    let broken: Vec<_> = collector
+        .get_broken_links(&options)
+        .map(|broken_link| (broken_link.link.href, broken_link.hard_404))
+        .collect();
+
+    assert_eq!(
+        broken,
+        &[
+            ("about#missing".to_owned(), false),
+            ("contact".to_owned(), true),
+            ("v1.0".to_owned(), true),
+        ]
+    );
+}
+
+#[test]
+fn test_fail_fast() {
+    let mut collector = BrokenLinkCollector::<()>::new();
+    let path = Arc::new(PathBuf::from("public/index.html"));
+
+    collector.ingest(defines("about"));
+
+    for href in ["about#missing", "contact", "contact", "docs"] {
+        collector.ingest(uses(&path, href, href));
+    }
+
+    let mut options = CheckOptions {
+        check_anchors: true,
+        ..Default::default()
+    };
+
+    let broken = |collector: &BrokenLinkCollector<()>, options: &CheckOptions| -> Vec<_> {
+        collector
+            .get_broken_links(options)
+            .map(|broken_link| (broken_link.link.href, broken_link.hard_404))
+            .collect()
+    };
+
+    assert_eq!(broken(&collector, &options).len(), 4);
+
+    options.fail_fast = true;
+    assert_eq!(
+        broken(&collector, &options),
+        &[("contact".to_owned(), true)]
+    );
+
+    options.assume_extension = Some("html".to_owned());
+    collector.ingest(defines("contact.html"));
+    collector.ingest(defines("docs.html"));
+    assert_eq!(
+        broken(&collector, &options),
+        &[("about#missing".to_owned(), false)]
+    );
+}
+
+#[test]
+fn test_anchor_prefix() {
+    let mut collector = BrokenLinkCollector::<()>::new();
+    let path = Arc::new(PathBuf::from("public/index.html"));
+
+    for href in [
+        "guide.html",
+        "guide.html#user-content-setup",
+        "guide.html#faq",
+    ] {
+        collector.ingest(defines(href));
+    }
+
+    for href in [
+        "guide.html#setup",
+        "guide.html#user-content-faq",
+        "guide.html#user-content-setup",
+        "guide.html#missing",
+    ] {
+        collector.ingest(uses(&path, href, href));
+    }
+
+    let mut options = CheckOptions {
+        check_anchors: true,
+        ..Default::default()
+    };
+
+    let broken = |options: &CheckOptions| -> Vec<_> {
+        collector
+            .get_broken_links(options)
+            .map(|broken_link| broken_link.link.href)
+            .collect()
+    };
+
+    assert_eq!(
+        broken(&options),
+        &[
+            "guide.html#missing",
+            "guide.html#setup",
+            "guide.html#user-content-faq"
+        ]
+    );
+
+    options.anchor_prefix = Some("user-content-".to_owned());
+    assert_eq!(broken(&options), &["guide.html#missing"]);
+}
+
+#[test]
+fn test_paths_are_case_sensitive() {
+    use crate::html::Document;
+    use std::path::Path;
+
+    let document = Document::new(Path::new("public/"), Path::new("public/Docs/about.html"));
+    assert_eq!(document.href(), Href("Docs/about.html"));
+
+    let mut collector = BrokenLinkCollector::<()>::new();
+    collector.ingest(defines(document.href().0));
+
+    for href in ["Docs/about.html", "docs/about.html", "Docs/About.html"] {
+        collector.ingest(uses(&document.path, href, href));
+    }
+
+    let broken: Vec<_> = collector
+        .get_broken_links(&CheckOptions::default())
+        .map(|broken_link| broken_link.link.href)
+        .collect();
+
+    assert_eq!(broken, &["Docs/About.html", "docs/about.html"]);
+}
+
+#[test]
+fn test_unexpected_anchor_elements() {
+    use crate::html::DefinedLink;
+
+    let mut collector = BrokenLinkCollector::<()>::new();
+    let path = Arc::new(PathBuf::from("public/page.html"));
+
+    for (href, unexpected_element) in [
+        ("page.html#intro", Some("span")),
+        ("page.html#unused", Some("span")),
+        ("page.html#setup", None),
+    ] {
+        collector.ingest(Link::Defines(DefinedLink {
+            href: Href(href),
+            unexpected_element: unexpected_element.map(|element| (path.clone(), element)),
+            raw_anchor: None,
+        }));
+    }
+
+    for href in ["page.html#intro", "page.html#setup"] {
+        collector.ingest(uses(&path, href, href));
+    }
+
+    let unexpected: Vec<_> = collector
+        .get_unexpected_anchor_elements()
+        .map(|(href, _, element)| (href, element))
+        .collect();
+    assert_eq!(unexpected, [("page.html#intro", "span")]);
+}
+
+#[test]
+fn test_redirect_chains() {
+    let mut collector = BrokenLinkCollector::<()>::new();
+    let path = Arc::new(PathBuf::from("public/index.html"));
+    collector.ingest(defines("final.html"));
+    for href in ["a", "b#top", "loop", "final.html", "external"] {
+        collector.ingest(uses(&path, href, href));
+    }
+
+    let options = CheckOptions {
+        redirects: Redirects::parse(
+            "
+            /a         /b
+            /b         /c/ 301
+            /c/        /final.html
+            /loop      /loop2
+            /loop2     /loop
+            /external  https://example.com/
+            ",
+        ),
+        ..Default::default()
+    };
+
+    let chains: Vec<_> = collector
+        .get_redirect_chains(&options)
+        .map(|(link, chain)| format!("{} -> {}", link.href, chain.join(" -> ")))
+        .collect();
+    assert_eq!(
+        chains,
+        [
+            "a -> /b -> /c/ -> /final.html",
+            "b#top -> /c/ -> /final.html",
+            "external -> https://example.com/",
+            "loop -> /loop2 -> /loop",
+        ]
+    );
+}
+
+#[test]
+fn test_redirects_to_missing_files() {
+    use std::path::Path;
+
+    let mut collector = BrokenLinkCollector::<()>::new();
+    for href in ["", "final.html", "docs", "café.html"] {
+        collector.ingest(defines(href));
+    }
+
+    let options = CheckOptions {
+        redirects: Redirects::parse(
+            "
+            /a         /final.html#top
+            /b         /a
+            /c         /
+            /d         /docs/index.html
+            /e         /docs/
+            /f         /caf%C3%A9.html
+            /broken    /missing-page.html
+            /blog/*    /news/:splat
+            ",
+        ),
+        ..Default::default()
+    };
+    let root = Document::new(Path::new("public/"), Path::new("public/index.html"));
+
+    assert_eq!(
+        collector
+            .get_redirects_to_missing_files(&options, &root, &ParserOptions::default())
+            .collect::<Vec<_>>(),
+        [(
+            "/broken".to_owned(),
+            "/missing-page.html",
+            "missing-page.html".to_owned()
+        )]
+    );
+
+    // With --strict-trailing-slash, directories are only defined with a trailing slash.
+    let mut collector = BrokenLinkCollector::<()>::new();
+    for href in ["", "docs/"] {
+        collector.ingest(defines(href));
+    }
+    let parser_options = ParserOptions {
+        strict_trailing_slash: true,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        collector
+            .get_redirects_to_missing_files(&options, &root, &parser_options)
+            .map(|(from, _, href)| (from, href))
+            .collect::<Vec<_>>(),
+        [
+            ("/a".to_owned(), "final.html".to_owned()),
+            ("/f".to_owned(), "café.html".to_owned()),
+            ("/broken".to_owned(), "missing-page.html".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn test_anchor_synonyms() {
+    use crate::html::AnchorCase;
+
+    let mut collector = BrokenLinkCollector::<()>::new();
+    let path = Arc::new(PathBuf::from("public/index.html"));
+
+    for href in ["guide.html", "guide.html#installation"] {
+        collector.ingest(defines(href));
+    }
+
+    for href in ["guide.html#install", "guide.html#setup", "guide.html#usage"] {
+        collector.ingest(uses(&path, href, href));
+    }
+
+    let options = CheckOptions {
+        check_anchors: true,
+        anchor_synonyms: Some(
+            AnchorSynonyms::parse(
+                "#install = #installation\n#setup = #getting-started",
+                true,
+                false,
+                AnchorCase::Sensitive,
+            )
+            .unwrap(),
+        ),
+        ..Default::default()
+    };
+
+    let broken: Vec<_> = collector
+        .get_broken_links(&options)
+        .map(|broken_link| broken_link.link.href)
+        .collect();
+    assert_eq!(broken, ["guide.html#setup", "guide.html#usage"]);
+
+    let synonym_links: Vec<_> = collector
+        .get_anchor_synonym_links(&options)
+        .map(|(link, synonym)| (link.href, synonym))
+        .collect();
+    assert_eq!(
+        synonym_links,
+        [(
+            "guide.html#install".to_owned(),
+            "guide.html#installation".to_owned()
+        )]
+    );
+}
+
+#[test]
+fn test_meta_refresh_chains() {
+    use crate::html::RedirectLink;
+
+    let mut collector = BrokenLinkCollector::<()>::new();
+    let path = Arc::new(PathBuf::from("public/index.html"));
+
+    collector.ingest(defines("c.html"));
+    for (from, to) in [
+        ("a.html", "b.html"),
+        ("b.html", "c.html"),
+        ("loop.html", "loop2.html"),
+        ("loop2.html", "loop.html"),
+        ("away.html", "https://example.com/"),
+    ] {
+        collector.ingest(Link::Redirects(RedirectLink {
+            from: Href(from),
+            to: Href(to),
+        }));
+    }
+    for href in ["a.html", "a.html#top", "c.html", "loop.html", "away.html"] {
+        collector.ingest(uses(&path, href, href));
+    }
+
+    let options = CheckOptions {
+        check_anchors: true,
+        ..Default::default()
+    };
+    assert_eq!(collector.get_broken_links(&options).count(), 0);
+
+    let redirected: Vec<_> = collector
+        .get_redirected_links(&options)
+        .map(|(link, to)| format!("{} -> {}", link.href, to))
+        .collect();
+    assert_eq!(
+        redirected,
+        [
+            "a.html -> /b.html",
+            "a.html#top -> /b.html",
+            "away.html -> https://example.com/",
+            "loop.html -> /loop2.html",
+        ]
+    );
+
+    let chains: Vec<_> = collector
+        .get_redirect_chains(&options)
+        .map(|(link, chain)| format!("{} -> {}", link.href, chain.join(" -> ")))
+        .collect();
+    assert_eq!(
+        chains,
+        [
+            "a.html -> /b.html -> /c.html",
+            "a.html#top -> /b.html -> /c.html",
+            "away.html -> https://example.com/",
+            "loop.html -> /loop2.html -> /loop.html",
+        ]
+    );
+}
+
+#[test]
+fn test_anchor_encoding_mismatches() {
+    use crate::html::DefinedLink;
+
+    let define = |collector: &mut BrokenLinkCollector<()>, href, raw_anchor| {
+        collector.ingest(Link::Defines(DefinedLink {
+            href: Href(href),
+            unexpected_element: None,
+            raw_anchor: Some(raw_anchor),
+        }));
+    };
+    let path = Arc::new(PathBuf::from("public/index.html"));
+    let use_link = |collector: &mut BrokenLinkCollector<()>, href, raw_href| {
+        collector.ingest(uses(&path, href, raw_href));
+    };
+
+    let mut collector = BrokenLinkCollector::<()>::new();
+    use_link(
+        &mut collector,
+        "guide.html#testmiljø",
+        "guide.html#testmilj%C3%B8",
+    );
+    define(&mut collector, "guide.html#testmiljø", "testmiljø");
+    use_link(
+        &mut collector,
+        "guide.html#testmiljø",
+        "guide.html#testmiljø",
+    );
+    define(&mut collector, "guide.html#café", "caf%C3%A9");
+
+    let mut other = BrokenLinkCollector::<()>::new();
+    use_link(&mut other, "guide.html#café", "guide.html#café");
+    use_link(&mut other, "guide.html#café", "guide.html#caf%C3%A9");
+    collector.merge(other);
+
+    let mismatches: Vec<_> = collector
+        .get_anchor_encoding_mismatches()
+        .map(|(_, raw_href, raw_anchor)| (raw_href.as_str(), raw_anchor.as_str()))
+        .collect();
+    assert_eq!(
+        mismatches,
+        [
+            ("guide.html#testmilj%C3%B8", "testmiljø"),
+            ("guide.html#café", "caf%C3%A9"),
+        ]
+    );
+}
+
+#[test]
+fn test_absolute_links() {
+    let mut collector = BrokenLinkCollector::<()>::new();
+    let path = Arc::new(PathBuf::from("public/docs/index.html"));
+
+    collector.ingest(defines("about.html"));
+
+    for (raw_href, absolute) in [("/about.html", true), ("../about.html", false)] {
+        let mut link = uses(&path, "about.html", raw_href);
+        if let Link::Uses(used_link) = &mut link {
+            used_link.absolute = absolute;
+        }
+        collector.ingest(link);
+    }
+
+    let broken: Vec<_> = collector
+        .get_broken_links(&CheckOptions::default())
+        .map(|broken_link| {
+            (
+                broken_link.link.href,
+                broken_link.link.raw_href,
+                broken_link.link.absolute,
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        broken,
+        &[("about.html".to_owned(), "/about.html".to_owned(), true)]
+    );
 }
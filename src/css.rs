@@ -0,0 +1,121 @@
+//! A minimal scanner for links in CSS files, used by `--check-css-imports`. This is not a CSS
+//! parser: it only knows enough about comments and strings to not report URLs that are not
+//! actually URLs.
+
+/// Return all URLs referenced by `@import` rules and `url()` functions in `css`, together with
+/// which of the two they were found in.
+pub fn css_urls(css: &str) -> Vec<(&str, &'static str)> {
+    let mut urls = Vec::new();
+    let mut i = 0;
+
+    while i < css.len() {
+        let rest = &css[i..];
+
+        if rest.starts_with("/*") {
+            i += rest.find("*/").map_or(rest.len(), |end| end + 2);
+        } else if starts_with_ignore_case(rest, "url(") {
+            let (url, len) = parse_url_function(&rest[4..]);
+            urls.push((url, "url()"));
+            i += 4 + len;
+        } else if starts_with_ignore_case(rest, "@import") {
+            let after_keyword = &rest[7..];
+            let args = after_keyword.trim_start();
+            i += 7 + after_keyword.len() - args.len();
+
+            // @import url(...) is handled by the next iteration
+            if let Some((url, len)) = parse_string(args) {
+                urls.push((url, "@import"));
+                i += len;
+            }
+        } else if let Some((_, len)) = parse_string(rest) {
+            // skip over strings so that content: "url(foo)" is not picked up
+            i += len;
+        } else {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+
+    urls.retain(|(url, _)| !url.is_empty());
+    urls
+}
+
+fn starts_with_ignore_case(s: &str, prefix: &str) -> bool {
+    s.get(..prefix.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+}
+
+/// Parse a quoted string at the start of `s`, returning its contents and the number of bytes
+/// consumed including the quotes.
+fn parse_string(s: &str) -> Option<(&str, usize)> {
+    let quote = s.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let mut escaped = false;
+
+    for (i, c) in s.char_indices().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            return Some((&s[1..i], i + 1));
+        }
+    }
+
+    Some((&s[1..], s.len()))
+}
+
+/// Parse the arguments of `url(`, returning the URL and the number of bytes consumed including
+/// the closing parenthesis.
+fn parse_url_function(s: &str) -> (&str, usize) {
+    let args = s.trim_start();
+    let mut consumed = s.len() - args.len();
+
+    let url = if let Some((url, len)) = parse_string(args) {
+        consumed += len;
+        url
+    } else {
+        let len = args.find(')').unwrap_or(args.len());
+        consumed += len;
+        args[..len].trim_end()
+    };
+
+    consumed += s[consumed..]
+        .find(')')
+        .map_or(s.len() - consumed, |end| end + 1);
+    (url, consumed)
+}
+
+#[test]
+fn test_css_urls() {
+    let css = r#"
+        @import "theme.css";
+        @import 'print.css' print;
+        @IMPORT url(fonts.css);
+        /* @import "commented.css"; background: url(commented.png) */
+        .logo { background: URL( "img/logo.png" ) no-repeat; }
+        .icon { background-image: url(../icons/ümlaut.svg), url('data:image/png;base64,AAAA'); }
+        .quote::before { content: "url(not-a-url.png)"; }
+        .empty { background: url(); }
+    "#;
+
+    assert_eq!(
+        css_urls(css),
+        vec![
+            ("theme.css", "@import"),
+            ("print.css", "@import"),
+            ("fonts.css", "url()"),
+            ("img/logo.png", "url()"),
+            ("../icons/ümlaut.svg", "url()"),
+            ("data:image/png;base64,AAAA", "url()"),
+        ]
+    );
+}
+
+#[test]
+fn test_css_urls_unterminated() {
+    assert_eq!(
+        css_urls("a { background: url(foo.png"),
+        vec![("foo.png", "url()")]
+    );
+    assert_eq!(css_urls("@import \"foo.css"), vec![("foo.css", "@import")]);
+    assert_eq!(css_urls("/* url(foo.png)"), vec![]);
+}
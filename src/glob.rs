@@ -0,0 +1,88 @@
+//! `--sources-glob`: Select source files by a pattern like `docs/**/*.md`.
+
+use std::path::Path;
+use std::str;
+
+/// A glob over `/`-separated relative paths. `*` and `?` match within one path component,
+/// `**` as a whole component matches any number of components.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Glob {
+    components: Vec<String>,
+}
+
+impl str::FromStr for Glob {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let components: Vec<String> = s
+            .trim_start_matches("./")
+            .split('/')
+            .filter(|component| !component.is_empty())
+            .map(str::to_owned)
+            .collect();
+
+        if components.is_empty() {
+            return Err(format!("empty glob {s:?}"));
+        }
+
+        Ok(Glob { components })
+    }
+}
+
+impl Glob {
+    /// Whether `path`, relative to the directory the glob applies to, matches.
+    pub fn matches(&self, path: &Path) -> bool {
+        let path: Vec<_> = path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect();
+        let path: Vec<&str> = path.iter().map(|component| &**component).collect();
+        match_components(&self.components, &path)
+    }
+}
+
+fn match_components(pattern: &[String], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=path.len()).any(|skip| match_components(rest, &path[skip..]))
+        }
+        Some((first, rest)) => match path.split_first() {
+            Some((component, path_rest)) => {
+                let pattern: Vec<char> = first.chars().collect();
+                let name: Vec<char> = component.chars().collect();
+                match_component(&pattern, &name) && match_components(rest, path_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+fn match_component(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| match_component(rest, &name[skip..])),
+        Some(('?', rest)) => !name.is_empty() && match_component(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && match_component(rest, &name[1..]),
+    }
+}
+
+#[test]
+fn test_glob() {
+    let glob: Glob = "docs/**/*.md".parse().unwrap();
+    assert!(glob.matches(Path::new("docs/intro.md")));
+    assert!(glob.matches(Path::new("docs/guide/setup.md")));
+    assert!(!glob.matches(Path::new("docs/guide/setup.mdx")));
+    assert!(!glob.matches(Path::new("blog/post.md")));
+    assert!(!glob.matches(Path::new("intro.md")));
+
+    let glob: Glob = "*.md".parse().unwrap();
+    assert!(glob.matches(Path::new("index.md")));
+    assert!(!glob.matches(Path::new("docs/index.md")));
+
+    let glob: Glob = "./page-?.md".parse().unwrap();
+    assert!(glob.matches(Path::new("page-1.md")));
+    assert!(!glob.matches(Path::new("page-10.md")));
+
+    assert!("".parse::<Glob>().is_err());
+}
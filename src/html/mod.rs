@@ -1,26 +1,40 @@
 mod parser;
 
 use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::BTreeSet;
 use std::fmt;
 use std::fs;
-use std::io::Read;
+use std::io::{self, Read};
 use std::path::{Path, PathBuf};
 use std::str;
-use std::sync::Arc;
+use std::sync::{Arc, Condvar, Mutex};
 
-use anyhow::Error;
+use anyhow::{Context, Error};
 use bumpalo::collections::String as BumpString;
 use bumpalo::collections::Vec as BumpVec;
 use html5gum::{IoReader, Tokenizer};
 
+use crate::css::css_urls;
 use crate::paragraph::ParagraphWalker;
+use crate::slug::AnchorStyle;
 use crate::urls::is_external_link;
 
 #[cfg(test)]
 use pretty_assertions::assert_eq;
 
+/// Canonicalize `path` relative to `base`, a canonicalized href. If `collapse_index_html` is set, a
+/// trailing `index.html` is removed, as it would be served for the directory as well.
+///
+/// If `keep_trailing_slash` is set, links to directories keep their trailing slash, so that `foo`
+/// and `foo/` are different hrefs. See `--strict-trailing-slash`.
 #[inline]
-pub fn push_and_canonicalize(base: &mut BumpString, path: &str) {
+pub fn push_and_canonicalize(
+    base: &mut BumpString,
+    path: &str,
+    collapse_index_html: bool,
+    keep_trailing_slash: bool,
+) {
     if is_external_link(path.as_bytes()) {
         base.clear();
         base.push_str(path);
@@ -28,7 +42,8 @@ pub fn push_and_canonicalize(base: &mut BumpString, path: &str) {
     } else if path.starts_with('/') {
         base.clear();
     } else if path.is_empty() {
-        if base.ends_with('/') {
+        // the site root never has a trailing slash
+        if base.ends_with('/') && !(keep_trailing_slash && base.len() > 1) {
             base.truncate(base.len() - 1);
         }
         return;
@@ -37,15 +52,18 @@ pub fn push_and_canonicalize(base: &mut BumpString, path: &str) {
     }
 
     let num_slashes = path.matches('/').count();
+    let mut is_directory = false;
 
     for (i, component) in path.split('/').enumerate() {
+        is_directory = true;
         match component {
-            "index.html" | "index.htm" if i == num_slashes => {}
+            "index.html" | "index.htm" if collapse_index_html && i == num_slashes => {}
             "" | "." => {}
             ".." => {
                 base.truncate(base.rfind('/').unwrap_or(0));
             }
             _ => {
+                is_directory = false;
                 if !base.is_empty() {
                     base.push('/');
                 }
@@ -53,6 +71,10 @@ pub fn push_and_canonicalize(base: &mut BumpString, path: &str) {
             }
         }
     }
+
+    if keep_trailing_slash && is_directory && !base.is_empty() {
+        base.push('/');
+    }
 }
 
 #[cfg(test)]
@@ -63,7 +85,7 @@ mod test_push_and_canonicalize {
     fn push_and_canonicalize(base: &mut String, path: &str) {
         let arena = bumpalo::Bump::new();
         let mut base2 = BumpString::from_str_in(&*base, &arena);
-        push_and_canonicalize_impl(&mut base2, path);
+        push_and_canonicalize_impl(&mut base2, path, true, false);
         *base = base2.as_str().to_owned();
     }
 
@@ -111,6 +133,35 @@ mod test_push_and_canonicalize {
         assert_eq!(base, "foo");
     }
 
+    #[test]
+    fn index_html_no_collapse() {
+        let arena = bumpalo::Bump::new();
+        let mut base = BumpString::from_str_in("foo/bar.html", &arena);
+        push_and_canonicalize_impl(&mut base, "index.html", false, false);
+        assert_eq!(base, "foo/index.html");
+    }
+
+    #[test]
+    fn keep_trailing_slash() {
+        let arena = bumpalo::Bump::new();
+        for (base, path, expected) in [
+            ("docs/intro.html", "guide/", "docs/guide/"),
+            ("docs/intro.html", "guide", "docs/guide"),
+            ("docs/intro.html", "guide/index.html", "docs/guide/"),
+            ("docs/guide/intro.html", "..", "docs/"),
+            ("docs/guide/intro.html", "../", "docs/"),
+            ("docs/intro.html", "..", ""),
+            ("docs/intro.html", "/", ""),
+            ("docs/", "", "docs/"),
+            ("/", "", ""),
+            ("docs/intro.html", "", "docs/intro.html"),
+        ] {
+            let mut href = BumpString::from_str_in(base, &arena);
+            push_and_canonicalize_impl(&mut href, path, true, true);
+            assert_eq!(href, expected, "{} + {}", base, path);
+        }
+    }
+
     #[test]
     fn index_html_middle() {
         let mut base = String::from("foo/bar.html");
@@ -159,14 +210,31 @@ pub fn try_percent_decode(input: &str) -> Cow<'_, str> {
         .unwrap_or(Cow::Borrowed(input))
 }
 
+/// Like `try_percent_decode`, but keeps `#` and `?` escaped, so that a path decoded from a link
+/// can't be mistaken for a path followed by an anchor or query string.
+#[inline]
+pub fn try_percent_decode_path(input: &str) -> Cow<'_, str> {
+    escape_path(try_percent_decode(input))
+}
+
+/// Escape `#` and `?` in a filesystem path that is used as (part of) an href.
+pub fn escape_path(path: Cow<'_, str>) -> Cow<'_, str> {
+    if path.contains(&['#', '?'][..]) {
+        Cow::Owned(path.replace('#', "%23").replace('?', "%3F"))
+    } else {
+        path
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub struct Href<'a>(pub &'a str);
 
-impl Href<'_> {
-    pub fn without_anchor(&self) -> Href<'_> {
+impl<'a> Href<'a> {
+    /// The href of the document, without anchor or `--valid-routes` query string.
+    pub fn without_anchor(&self) -> Href<'a> {
         let mut s = self.0;
 
-        if let Some(i) = s.find('#') {
+        if let Some(i) = s.find(&['?', '#'][..]) {
             s = &s[..i];
         }
 
@@ -183,32 +251,482 @@ impl fmt::Display for Href<'_> {
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub struct UsedLink<'a, P> {
     pub href: Href<'a>,
+    /// The href as written in the document, before it was resolved against the document's path.
+    pub raw_href: &'a str,
     pub path: Arc<PathBuf>,
     pub paragraph: Option<P>,
+    /// Where in the document the link was found, such as `<script src>`.
+    pub element: &'static str,
+    /// The `media` condition of the `<source>` element the link was found in, if any.
+    pub media: Option<&'a str>,
+    /// What references the id in `href`, for links from `--check-aria-references` and
+    /// `--check-label-for`, such as `aria-describedby` or `label`.
+    pub referrer: Option<&'static str>,
+    /// Whether the link starts with a single slash, which `--filesystem-mode` reports as broken.
+    pub absolute: bool,
+    /// Where the attribute containing the link is written, only recorded with
+    /// `ParserOptions::html_tab_size`.
+    pub position: Option<Position>,
+}
+
+/// A position in an HTML document, as counted while it is tokenized.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Position {
+    /// The byte offset from the start of the document.
+    pub offset: usize,
+    /// The 1-based line.
+    pub line: usize,
+    /// The 1-based column, with tabs advancing it to the next multiple of the tab size.
+    pub column: usize,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub struct DefinedLink<'a> {
     pub href: Href<'a>,
+    /// The document and tag name of an anchor that `--anchor-element-policy` does not expect on
+    /// this element.
+    pub unexpected_element: Option<(Arc<PathBuf>, &'a str)>,
+    /// The anchor as written in the document, without `#`. Only recorded for
+    /// `--warn-anchor-encoding-mismatch`.
+    pub raw_anchor: Option<&'a str>,
+}
+
+/// A document that redirects to another URL with `<meta http-equiv=refresh>`, see
+/// `--follow-meta-refresh-chain`.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub struct RedirectLink<'a> {
+    pub from: Href<'a>,
+    pub to: Href<'a>,
+}
+
+/// A problem with a document that is not a broken link, found by one of the opt-in lints.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Warning {
+    pub path: Arc<PathBuf>,
+    pub message: String,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub enum Link<'a, P> {
     Uses(UsedLink<'a, P>),
     Defines(DefinedLink<'a>),
+    Warns(Warning),
+    Redirects(RedirectLink<'a>),
 }
 
 impl<P> Link<'_, P> {
     pub fn into_paragraph(self) -> Option<P> {
         match self {
             Link::Uses(UsedLink { paragraph, .. }) => paragraph,
-            Link::Defines(_) => None,
+            Link::Defines(_) | Link::Warns(_) | Link::Redirects(_) => None,
+        }
+    }
+}
+
+/// How anchors are compared against each other, see `--ignore-anchor-case`.
+///
+/// Browsers match anchors case-sensitively, so anything but `Sensitive` is a convenience for sites
+/// that are sloppy about the casing of their anchors.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AnchorCase {
+    #[default]
+    Sensitive,
+    /// Only fold ASCII letters, so `#Intro` matches `#intro` but `#İ` does not match `#i`.
+    Ascii,
+    /// Lowercase anchors according to Unicode.
+    Unicode,
+}
+
+impl AnchorCase {
+    /// The form of `anchor` that is compared against other anchors.
+    pub fn fold<'a>(&self, anchor: &'a str) -> Cow<'a, str> {
+        match self {
+            AnchorCase::Sensitive => Cow::Borrowed(anchor),
+            AnchorCase::Ascii => Cow::Owned(anchor.to_ascii_lowercase()),
+            AnchorCase::Unicode => Cow::Owned(anchor.to_lowercase()),
+        }
+    }
+}
+
+/// How the server resolves links to directories, see `--directory-index-mode`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum DirectoryIndex {
+    /// `/foo/` is served from `foo/index.html`
+    #[default]
+    IndexHtml,
+    /// `/foo/` is served from `foo.html`
+    SameNameHtml,
+    /// `/foo/` is not served at all, links have to point to files.
+    None,
+}
+
+impl str::FromStr for DirectoryIndex {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "index-html" => Ok(DirectoryIndex::IndexHtml),
+            "same-name-html" => Ok(DirectoryIndex::SameNameHtml),
+            "none" => Ok(DirectoryIndex::None),
+            _ => Err(format!(
+                "unknown directory index mode {s:?}, expected index-html, same-name-html or none"
+            )),
+        }
+    }
+}
+
+/// An additional tag and attribute to extract links from, see `--link-attribute`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LinkAttribute {
+    pub tag: String,
+    pub attribute: String,
+    /// Where links were found in error messages, such as `<div data-href>`.
+    pub element: &'static str,
+}
+
+impl str::FromStr for LinkAttribute {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some((tag, attribute)) if !tag.is_empty() && !attribute.is_empty() => {
+                let tag = tag.to_ascii_lowercase();
+                let attribute = attribute.to_ascii_lowercase();
+                // Parsed once per command line argument, so leaking is fine.
+                let element = Box::leak(format!("<{tag} {attribute}>").into_boxed_str());
+                Ok(LinkAttribute {
+                    tag,
+                    attribute,
+                    element,
+                })
+            }
+            _ => Err(format!(
+                "expected TAG:ATTRIBUTE like div:data-href, got {s:?}"
+            )),
+        }
+    }
+}
+
+/// An element whose links are not extracted, together with the links of its descendants, see
+/// `--ignore-selector`. Only single tag, class and id selectors are supported.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum IgnoreSelector {
+    Tag(String),
+    Class(String),
+    Id(String),
+}
+
+impl IgnoreSelector {
+    pub fn matches_tag(&self, tag: &[u8]) -> bool {
+        matches!(self, IgnoreSelector::Tag(name) if name.as_bytes() == tag)
+    }
+
+    pub fn matches_attribute(&self, attribute: &[u8], value: &[u8]) -> bool {
+        match (self, attribute) {
+            (IgnoreSelector::Class(name), b"class") => value
+                .split(u8::is_ascii_whitespace)
+                .any(|class| class == name.as_bytes()),
+            (IgnoreSelector::Id(name), b"id") => value == name.as_bytes(),
+            _ => false,
+        }
+    }
+}
+
+impl str::FromStr for IgnoreSelector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, selector): (_, fn(String) -> Self) = if let Some(name) = s.strip_prefix('.') {
+            (name, IgnoreSelector::Class)
+        } else if let Some(name) = s.strip_prefix('#') {
+            (name, IgnoreSelector::Id)
+        } else {
+            (s, |name| IgnoreSelector::Tag(name.to_ascii_lowercase()))
+        };
+
+        let is_simple = !name.is_empty()
+            && !name.contains(|c: char| c.is_whitespace() || ".#[]:>+~,*()".contains(c));
+        if !is_simple {
+            return Err(format!(
+                "unsupported selector {s:?}, expected a tag, .class or #id"
+            ));
+        }
+
+        Ok(selector(name.to_owned()))
+    }
+}
+
+const HEADING_TAGS: &[&str] = &["h1", "h2", "h3", "h4", "h5", "h6"];
+const LANDMARK_TAGS: &[&str] = &[
+    "main", "nav", "aside", "header", "footer", "section", "article", "form",
+];
+
+/// The elements linked anchors are expected on, see `--anchor-element-policy`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AnchorElementPolicy {
+    tags: Vec<String>,
+}
+
+impl AnchorElementPolicy {
+    pub fn allows(&self, tag: &[u8]) -> bool {
+        self.tags.iter().any(|allowed| allowed.as_bytes() == tag)
+    }
+}
+
+impl str::FromStr for AnchorElementPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tags = Vec::new();
+        for tag in s.split(',').map(str::trim).filter(|tag| !tag.is_empty()) {
+            match tag.to_ascii_lowercase().as_str() {
+                "headings" => tags.extend(HEADING_TAGS.iter().map(|&tag| tag.to_owned())),
+                "landmarks" => tags.extend(LANDMARK_TAGS.iter().map(|&tag| tag.to_owned())),
+                tag => tags.push(tag.to_owned()),
+            }
+        }
+
+        if tags.is_empty() {
+            return Err(format!(
+                "expected tag names like headings,landmarks,div, got {s:?}"
+            ));
+        }
+
+        Ok(AnchorElementPolicy { tags })
+    }
+}
+
+#[test]
+fn test_anchor_element_policy() {
+    let policy: AnchorElementPolicy = "headings, landmarks,DT".parse().unwrap();
+    assert!(policy.allows(b"h2"));
+    assert!(policy.allows(b"section"));
+    assert!(policy.allows(b"dt"));
+    assert!(!policy.allows(b"span"));
+    assert!(!policy.allows(b"headings"));
+
+    assert!(" , ".parse::<AnchorElementPolicy>().is_err());
+}
+
+/// Routes of single-page apps that select a page by query string, like `/app?page=settings`, see
+/// `--valid-routes`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ValidRoutes {
+    /// Canonical hrefs of the documents that are routed by query string, like `app`.
+    pages: BTreeSet<String>,
+    /// Canonical hrefs of all routes, like `app?page=settings`.
+    routes: BTreeSet<String>,
+}
+
+impl ValidRoutes {
+    pub fn read(
+        path: &Path,
+        collapse_index_html: bool,
+        strict_trailing_slash: bool,
+    ) -> Result<Self, Error> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read routes {}", path.display()))?;
+        Ok(Self::parse(
+            &contents,
+            collapse_index_html,
+            strict_trailing_slash,
+        ))
+    }
+
+    /// Parse one route per line. Empty lines and lines starting with `#` are ignored.
+    pub fn parse(contents: &str, collapse_index_html: bool, strict_trailing_slash: bool) -> Self {
+        let arena = bumpalo::Bump::new();
+        let mut valid_routes = ValidRoutes::default();
+
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let line = &line[..line.find('#').unwrap_or(line.len())];
+            let (page, query) = line.split_at(line.find('?').unwrap_or(line.len()));
+            let mut href = BumpString::new_in(&arena);
+            push_and_canonicalize(
+                &mut href,
+                &try_percent_decode_path(page),
+                collapse_index_html,
+                strict_trailing_slash,
+            );
+
+            valid_routes.pages.insert(href.to_string());
+            if query.len() > 1 {
+                href.push_str(query);
+                valid_routes.routes.insert(href.to_string());
+            }
         }
+
+        valid_routes
+    }
+
+    /// Whether links to the document with the canonical href `page` keep their query string.
+    pub fn is_routed(&self, page: &str) -> bool {
+        self.pages.contains(page)
+    }
+
+    pub fn routes(&self) -> impl Iterator<Item = &str> {
+        self.routes.iter().map(String::as_str)
+    }
+}
+
+#[test]
+fn test_valid_routes() {
+    let routes = ValidRoutes::parse(
+        "
+        # settings
+        /app?page=settings
+        /app/index.html?page=profile#top
+        /docs/./search.html?
+        ",
+        true,
+        false,
+    );
+
+    assert!(routes.is_routed("app"));
+    assert!(routes.is_routed("docs/search.html"));
+    assert!(!routes.is_routed("index.html"));
+    assert_eq!(
+        routes.routes().collect::<Vec<_>>(),
+        ["app?page=profile", "app?page=settings"]
+    );
+}
+
+/// Options that control which links are extracted from a document, and how.
+#[derive(Clone, Debug, Default)]
+pub struct ParserOptions {
+    pub check_anchors: bool,
+    pub anchor_case: AnchorCase,
+    /// Warn about `<img srcset>` with width descriptors but no `sizes` attribute.
+    pub check_srcset_sizes: bool,
+    /// Warn about HTML syntax errors that may cause links to be missed.
+    pub report_parse_errors: bool,
+    pub directory_index: DirectoryIndex,
+    /// Check links from `@import` rules and `url()` functions in CSS files.
+    pub check_css_imports: bool,
+    /// Headings without `id` define an anchor derived from their text, like on GitHub.
+    pub heading_anchors: bool,
+    /// Which site generator's heading anchors to imitate, see `--anchor-style`.
+    pub anchor_style: AnchorStyle,
+    /// Warn about links to pages that are written without `.html`, see `--require-html-extension`.
+    pub require_html_extension: bool,
+    /// Additional attributes to extract links from.
+    pub link_attributes: Vec<LinkAttribute>,
+    /// Only extract links from `link_attributes`, see `--no-default-tags`.
+    pub no_default_tags: bool,
+    /// Additional attributes that define anchors like `id`, such as `data-anchor-id`.
+    pub anchor_attributes: Vec<String>,
+    /// Keep the trailing slash of links to directories, see `--strict-trailing-slash`.
+    pub strict_trailing_slash: bool,
+    /// Report links starting with a single slash as broken, see `--filesystem-mode`.
+    pub filesystem_mode: bool,
+    /// Extract links from markup in comments, see `--parse-comments`.
+    pub parse_comments: bool,
+    /// Warn about hrefs containing backslashes, see `--report-mixed-separators`.
+    pub report_mixed_separators: bool,
+    /// Warn about hrefs that look like unrendered templates, see `--warn-unrendered-templates`.
+    pub warn_unrendered_templates: bool,
+    /// Warn about external links with schemes outside of an allowlist, see `--strict-scheme`.
+    pub strict_scheme: bool,
+    /// Warn about `id` and `name` attributes that are empty, see `--report-empty-anchors`.
+    pub report_empty_anchors: bool,
+    /// Warn about repeated links within a page, see `--warn-duplicate-links`.
+    pub warn_duplicate_links: bool,
+    /// Check the ids referenced by `aria-describedby` and `aria-labelledby` like anchors, see
+    /// `--check-aria-references`.
+    pub check_aria_references: bool,
+    /// Check the ids referenced by `<label for>` like anchors, see `--check-label-for`.
+    pub check_label_for: bool,
+    /// Warn about `<track>` elements that media players cannot choose from, see
+    /// `--check-tracks`.
+    pub check_tracks: bool,
+    /// Record how anchors are written, see `--warn-anchor-encoding-mismatch`.
+    pub warn_anchor_encoding_mismatch: bool,
+    /// Elements to not extract links from, see `--ignore-selector`.
+    pub ignore_selectors: Vec<IgnoreSelector>,
+    /// Treat `<meta http-equiv=refresh>` like a rule in `_redirects`, see
+    /// `--follow-meta-refresh-chain`.
+    pub follow_meta_refresh_chain: bool,
+    /// Do not define link targets for dangling symlinks, see `--resolve-symlinked-targets`.
+    pub resolve_symlinked_targets: bool,
+    /// Define link targets for directories, see `--allow-directory-listings`.
+    pub allow_directory_listings: bool,
+    /// Do not read documents larger than this many bytes, see `--max-file-size`.
+    pub max_file_size: Option<u64>,
+    /// Limits how many documents are read at the same time, see `--max-open-files`.
+    pub open_files: Option<Arc<OpenFiles>>,
+    /// Record anchors on unexpected elements, see `--anchor-element-policy`.
+    pub anchor_element_policy: Option<AnchorElementPolicy>,
+    /// Keep the query string of links to documents routed by query string, see `--valid-routes`.
+    pub valid_routes: Option<ValidRoutes>,
+    /// Treat fragments starting with `#!` as client routes rather than anchors, see
+    /// `--hashbang-routes`.
+    pub hashbang_routes: bool,
+    /// Record the position of links in HTML documents, counting tabs as this many columns, see
+    /// `--html-tab-size`.
+    pub html_tab_size: Option<usize>,
+}
+
+impl ParserOptions {
+    /// Whether `rel_href` points outside of the site when opened from disk, see
+    /// `--filesystem-mode`.
+    pub fn is_absolute_link(&self, rel_href: &str) -> bool {
+        self.filesystem_mode && rel_href.starts_with('/') && !rel_href.starts_with("//")
     }
 }
 
 const BUF_SIZE: usize = 1024 * 1024;
 
+/// A semaphore for file handles, so that many threads do not run into the file descriptor limit.
+#[derive(Debug)]
+pub struct OpenFiles {
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+impl OpenFiles {
+    pub fn new(max: usize) -> Self {
+        OpenFiles {
+            available: Mutex::new(max),
+            released: Condvar::new(),
+        }
+    }
+
+    fn acquire(self: &Arc<Self>) -> OpenFile {
+        let mut available = self.available.lock().unwrap();
+        while *available == 0 {
+            available = self.released.wait(available).unwrap();
+        }
+        *available -= 1;
+        OpenFile(self.clone())
+    }
+}
+
+/// A permit of `OpenFiles`, given back when the file is closed.
+struct OpenFile(Arc<OpenFiles>);
+
+impl Drop for OpenFile {
+    fn drop(&mut self) {
+        *self.0.available.lock().unwrap() += 1;
+        self.0.released.notify_one();
+    }
+}
+
+/// A file that holds on to its `OpenFile` permit until it is dropped.
+struct LimitedFile {
+    file: fs::File,
+    _permit: OpenFile,
+}
+
+impl Read for LimitedFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
 /// This struct is initialized once per "batch of documents" that will be processed on a single
 /// worker thread (as determined by rayon). It pays off to do as much heap allocation as possible
 /// here once instead of in Document::links.
@@ -239,6 +757,25 @@ pub struct Document {
     pub path: Arc<PathBuf>,
     href: String,
     pub is_index_html: bool,
+    /// Whether the file is a Brotli-compressed HTML file such as `index.html.br`.
+    pub is_brotli: bool,
+    /// Whether the file is an XML document such as SVG or XHTML, where `xml:base` is honored.
+    pub is_xml: bool,
+    /// The contents of the file if it is not read from disk, see `--from-tar`.
+    contents: Option<Arc<[u8]>>,
+}
+
+/// File extensions of XML documents that are checked like HTML documents.
+pub static XML_FILES: &[&str] = &["svg", "xhtml"];
+
+/// For a Brotli-compressed HTML file like `foo.html.br`, return the path it is served under.
+pub fn strip_brotli_extension(path: &Path) -> Option<&Path> {
+    let path = path.to_str()?.strip_suffix(".br")?;
+    if path.ends_with(".html") || path.ends_with(".htm") {
+        Some(Path::new(path))
+    } else {
+        None
+    }
 }
 
 impl Document {
@@ -247,16 +784,23 @@ impl Document {
             .strip_prefix(base_path)
             .expect("base_path is not a base of path");
 
+        let is_brotli = strip_brotli_extension(href_path).is_some();
+        href_path = strip_brotli_extension(href_path).unwrap_or(href_path);
+
         let is_index_html = href_path.ends_with("index.html") || href_path.ends_with("index.htm");
+        let is_xml = href_path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| XML_FILES.contains(&extension));
 
         if is_index_html {
             href_path = href_path.parent().unwrap_or(href_path);
         }
 
-        let mut href = href_path
-            .to_str()
-            .expect("Invalid unicode in path")
-            .to_owned();
+        let mut href = escape_path(Cow::Borrowed(
+            href_path.to_str().expect("Invalid unicode in path"),
+        ))
+        .into_owned();
 
         if cfg!(windows) {
             unsafe {
@@ -275,6 +819,54 @@ impl Document {
             path: Arc::new(path.to_owned()),
             href,
             is_index_html,
+            is_brotli,
+            is_xml,
+            contents: None,
+        }
+    }
+
+    /// Undo the collapsing of `foo/index.html` into `foo`, for servers that do not serve directory
+    /// indices.
+    pub fn without_directory_index(mut self) -> Self {
+        if self.is_index_html {
+            let path = strip_brotli_extension(&self.path).unwrap_or(&self.path);
+            if let Some(file_name) = path.file_name().and_then(|x| x.to_str()) {
+                if !self.href.is_empty() {
+                    self.href.push('/');
+                }
+                self.href.push_str(file_name);
+                self.is_index_html = false;
+            }
+        }
+
+        self
+    }
+
+    /// Read the document from memory instead of from disk.
+    pub fn with_contents(mut self, contents: Arc<[u8]>) -> Self {
+        self.contents = Some(contents);
+        self
+    }
+
+    fn open(&self, options: &ParserOptions) -> Result<Box<dyn Read>, Error> {
+        let read: Box<dyn Read> = match (&self.contents, &options.open_files) {
+            (Some(contents), _) => Box::new(io::Cursor::new(contents.clone())),
+            (None, Some(open_files)) => {
+                let permit = open_files.acquire();
+                Box::new(LimitedFile {
+                    file: fs::File::open(&*self.path)?,
+                    _permit: permit,
+                })
+            }
+            (None, None) => Box::new(fs::File::open(&*self.path)?),
+        };
+
+        if self.is_brotli {
+            Ok(Box::new(brotli_decompressor::Decompressor::new(
+                read, BUF_SIZE,
+            )))
+        } else {
+            Ok(read)
         }
     }
 
@@ -282,26 +874,55 @@ impl Document {
         Href(&self.href)
     }
 
-    fn join<'b>(
+    /// The href of this document with `--strict-trailing-slash`, where directory indices are only
+    /// served with a trailing slash.
+    pub fn href_with_trailing_slash(&self) -> Cow<'_, str> {
+        if self.is_index_html && !self.href.is_empty() {
+            Cow::Owned(format!("{}/", self.href))
+        } else {
+            Cow::Borrowed(&self.href)
+        }
+    }
+
+    /// Resolve `rel_href` relative to this document, producing the href that is compared against
+    /// the hrefs of other documents.
+    pub fn join<'b>(
         &self,
         arena: &'b bumpalo::Bump,
-        preserve_anchor: bool,
+        options: &ParserOptions,
         rel_href: &str,
     ) -> Href<'b> {
         let qs_start = rel_href.find(&['?', '#'][..]).unwrap_or(rel_href.len());
         let anchor_start = rel_href.find('#').unwrap_or(rel_href.len());
 
         let mut href = BumpString::from_str_in(&self.href, arena);
-        if self.is_index_html {
+        // The root index has an empty href, relative links from it must not start with a slash.
+        if self.is_index_html && !self.href.is_empty() {
             href.push('/');
         }
 
-        push_and_canonicalize(&mut href, &try_percent_decode(&rel_href[..qs_start]));
+        push_and_canonicalize(
+            &mut href,
+            &try_percent_decode_path(&rel_href[..qs_start]),
+            options.directory_index != DirectoryIndex::None,
+            options.strict_trailing_slash,
+        );
+
+        // Routes are checked like anchors, and anchors within them are not checked at all.
+        if let Some(ref valid_routes) = options.valid_routes {
+            let query = &rel_href[qs_start..anchor_start];
+            if query.len() > 1 && valid_routes.is_routed(&href) {
+                href.push_str(query);
+                return Href(href.into_bump_str());
+            }
+        }
 
-        if preserve_anchor {
+        if options.check_anchors {
             let anchor = &rel_href[anchor_start..];
-            if anchor.len() > 1 {
-                href.push_str(&try_percent_decode(anchor));
+            // A hashbang like #!/section is a route of a legacy AJAX app, only the page is checked.
+            let is_hashbang = options.hashbang_routes && anchor.starts_with("#!");
+            if anchor.len() > 1 && !is_hashbang {
+                href.push_str(&options.anchor_case.fold(&try_percent_decode(anchor)));
             }
         }
 
@@ -311,24 +932,69 @@ impl Document {
     pub fn links<'b, 'l, P: ParagraphWalker>(
         &self,
         doc_buf: &'b mut DocumentBuffers,
-        check_anchors: bool,
+        options: &ParserOptions,
     ) -> Result<impl Iterator<Item = Link<'l, P::Paragraph>>, Error>
     where
         'b: 'l,
     {
-        self.links_from_read::<_, P>(doc_buf, fs::File::open(&*self.path)?, check_anchors)
+        self.links_from_read::<_, P>(doc_buf, self.open(options)?, options)
+    }
+
+    /// Links from a CSS file, see `--check-css-imports`.
+    pub fn css_links<'b, P: 'b>(
+        &self,
+        doc_buf: &'b mut DocumentBuffers,
+        options: &ParserOptions,
+    ) -> Result<impl Iterator<Item = Link<'b, P>>, Error> {
+        let mut css = String::new();
+        self.open(options)?.read_to_string(&mut css)?;
+        self.css_links_from_str(doc_buf, &css, options)
+    }
+
+    fn css_links_from_str<'b, P: 'b>(
+        &self,
+        doc_buf: &'b mut DocumentBuffers,
+        css: &str,
+        options: &ParserOptions,
+    ) -> Result<impl Iterator<Item = Link<'b, P>>, Error> {
+        // Fragments in CSS usually point into SVG files, which we do not parse for anchors.
+        let options = ParserOptions {
+            check_anchors: false,
+            ..options.clone()
+        };
+
+        let mut link_buf = BumpVec::new_in(&doc_buf.arena);
+        for (url, element) in css_urls(css) {
+            link_buf.push(Link::Uses(UsedLink {
+                href: self.join(&doc_buf.arena, &options, url),
+                raw_href: doc_buf.arena.alloc_str(url),
+                path: self.path.clone(),
+                paragraph: None,
+                element,
+                media: None,
+                referrer: None,
+                absolute: options.is_absolute_link(url),
+                position: None,
+            }));
+        }
+
+        Ok(link_buf.into_iter())
     }
 
     fn links_from_read<'b, 'l, R: Read, P: ParagraphWalker>(
         &self,
         doc_buf: &'b mut DocumentBuffers,
         read: R,
-        check_anchors: bool,
+        options: &ParserOptions,
     ) -> Result<impl Iterator<Item = Link<'l, P::Paragraph>>, Error>
     where
         'b: 'l,
     {
         let mut link_buf = BumpVec::new_in(&doc_buf.arena);
+        let position = Cell::new(Position {
+            line: 1,
+            ..Default::default()
+        });
 
         {
             let emitter = parser::HyperlinkEmitter {
@@ -340,9 +1006,15 @@ impl Document {
                 last_paragraph_i: 0,
                 buffers: &mut doc_buf.parser_buffers,
                 current_tag_is_closing: false,
-                check_anchors,
+                options,
+                position: options.html_tab_size.map(|_| &position),
             };
             let ioreader = IoReader::new_with_buffer(read, doc_buf.html_read_buffer.as_mut());
+            let ioreader = parser::PositionReader {
+                reader: ioreader,
+                position: &position,
+                tab_size: options.html_tab_size,
+            };
             let reader = Tokenizer::new_with_emitter(ioreader, emitter);
 
             for error in reader {
@@ -371,6 +1043,43 @@ fn test_document_href() {
     assert_eq!(doc.href(), Href("platforms/python/troubleshooting.html"));
 }
 
+#[test]
+fn test_document_href_brotli() {
+    let doc = Document::new(Path::new("public/"), Path::new("public/foo/index.html.br"));
+    assert!(doc.is_brotli);
+    assert_eq!(doc.href(), Href("foo"));
+    assert_eq!(doc.without_directory_index().href(), Href("foo/index.html"));
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/bar.htm.br"));
+    assert!(doc.is_brotli);
+    assert_eq!(doc.href(), Href("bar.htm"));
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/app.js.br"));
+    assert!(!doc.is_brotli);
+    assert_eq!(doc.href(), Href("app.js.br"));
+}
+
+/// A link to `href`, written as `raw_href` in the document at `path`.
+#[cfg(test)]
+fn used_link<P>(
+    path: &Arc<PathBuf>,
+    href: &'static str,
+    raw_href: &'static str,
+    element: &'static str,
+) -> Link<'static, P> {
+    Link::Uses(UsedLink {
+        href: Href(href),
+        raw_href,
+        path: path.clone(),
+        paragraph: None,
+        element,
+        media: None,
+        referrer: None,
+        absolute: false,
+        position: None,
+    })
+}
+
 #[test]
 fn test_html_parsing_malformed_script() {
     use crate::paragraph::ParagraphHasher;
@@ -397,20 +1106,79 @@ fn test_html_parsing_malformed_script() {
     let mut doc_buf = DocumentBuffers::default();
 
     let links = doc
-        .links_from_read::<_, ParagraphHasher>(&mut doc_buf, html.as_bytes(), false)
+        .links_from_read::<_, ParagraphHasher>(
+            &mut doc_buf,
+            html.as_bytes(),
+            &ParserOptions::default(),
+        )
         .unwrap();
 
-    let used_link = |x: &'static str| {
-        Link::Uses(UsedLink {
-            href: Href(x),
-            path: doc.path.clone(),
-            paragraph: None,
-        })
+    assert_eq!(
+        links.collect::<Vec<_>>(),
+        &[
+            used_link(&doc.path, "foo", "foo", "<a href>"),
+            used_link(&doc.path, "bar", "bar", "<a href>")
+        ]
+    );
+}
+
+#[test]
+fn test_parse_errors() {
+    use crate::paragraph::ParagraphHasher;
+
+    let options = ParserOptions {
+        report_parse_errors: true,
+        ..Default::default()
     };
 
+    let doc = Document::new(Path::new("public/"), Path::new("public/hello.html"));
+
+    let mut doc_buf = DocumentBuffers::default();
+
+    // junk in scripts and styles is not our concern
+    let html = r###"
+        <a href=foo />
+        <script>
+        if (a <b && c<"d") { document.write('<a href="wut"title=x>'); }
+        </script>
+        <style>a[href^="<!--"] { color: red }</style>
+        <a href=bar />
+    "###;
+
+    let links = doc
+        .links_from_read::<_, ParagraphHasher>(&mut doc_buf, html.as_bytes(), &options)
+        .unwrap();
+
     assert_eq!(
         links.collect::<Vec<_>>(),
-        &[used_link("foo"), used_link("bar")]
+        &[
+            used_link(&doc.path, "foo", "foo", "<a href>"),
+            used_link(&doc.path, "bar", "bar", "<a href>")
+        ]
+    );
+
+    doc_buf.reset();
+
+    let html = r###"
+        <a href="foo"title="Foo">
+        <!-- <a href="bar">
+    "###;
+
+    let warnings: Vec<_> = doc
+        .links_from_read::<_, ParagraphHasher>(&mut doc_buf, html.as_bytes(), &options)
+        .unwrap()
+        .filter_map(|link| match link {
+            Link::Warns(warning) => Some(warning.message),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        warnings,
+        &[
+            "HTML syntax error: missing-whitespace-between-attributes",
+            "HTML syntax error: eof-in-comment",
+        ]
     );
 }
 
@@ -463,18 +1231,10 @@ fn test_document_links() {
     />
     """#
         .as_bytes(),
-        false,
+        &ParserOptions::default(),
     )
     .unwrap();
 
-    let used_link = |x: &'static str| {
-        Link::Uses(UsedLink {
-            href: Href(x),
-            path: doc.path.clone(),
-            paragraph: None,
-        })
-    };
-
     let arena = Bump::new();
 
     assert_eq!(
@@ -482,19 +1242,131 @@ fn test_document_links() {
             .filter_map(|x| canonicalize_local_link(&arena, x))
             .collect::<Vec<_>>(),
         &[
-            used_link("platforms/ruby"),
-            used_link("platforms/perl"),
-            used_link("platforms/rust"),
-            used_link("platforms/go"),
-            used_link("platforms/go"),
-            used_link("platforms/python/troubleshooting/ma"),
-            used_link("platforms/python/troubleshooting/[slug].js"),
-            used_link("platforms/python/troubleshooting/[schlug].js"),
-            used_link("platforms/python/troubleshooting/case"),
-            used_link("platforms/python/troubleshooting/whitespace"),
-            used_link("static/image.png"),
-            used_link("static/image300.png"),
-            used_link("static/image600.png"),
+            used_link(&doc.path, "platforms/ruby", "../../ruby/", "<a href>"),
+            used_link(&doc.path, "platforms/perl", "/platforms/perl/", "<a href>"),
+            used_link(&doc.path, "platforms/rust", "../../rust/", "<a href>"),
+            used_link(
+                &doc.path,
+                "platforms/go",
+                "../../go/?foo=bar&bar=baz",
+                "<a href>"
+            ),
+            used_link(&doc.path, "platforms/go", "../../go/", "<a href>"),
+            used_link(
+                &doc.path,
+                "platforms/python/troubleshooting/ma",
+                "ma",
+                "<a href>"
+            ),
+            used_link(
+                &doc.path,
+                "platforms/python/troubleshooting/[slug].js",
+                "%5Bslug%5D.js",
+                "<a href>"
+            ),
+            used_link(
+                &doc.path,
+                "platforms/python/troubleshooting/[schlug].js",
+                "%5Bschlug%5D.js",
+                "<a href>"
+            ),
+            used_link(
+                &doc.path,
+                "platforms/python/troubleshooting/case",
+                "case",
+                "<a href>"
+            ),
+            used_link(
+                &doc.path,
+                "platforms/python/troubleshooting/whitespace",
+                "whitespace",
+                "<a href>"
+            ),
+            used_link(
+                &doc.path,
+                "static/image.png",
+                "/static/image.png",
+                "<img src>"
+            ),
+            used_link(
+                &doc.path,
+                "static/image300.png",
+                "/static/image300.png",
+                "<img srcset>"
+            ),
+            used_link(
+                &doc.path,
+                "static/image600.png",
+                "/static/image600.png",
+                "<img srcset>"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_embed_and_object() {
+    use crate::paragraph::ParagraphHasher;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/docs/manual.html"));
+
+    let mut doc_buf = DocumentBuffers::default();
+
+    let links = doc
+        .links_from_read::<_, ParagraphHasher>(
+            &mut doc_buf,
+            r#"
+            <embed src="manual.pdf" type="application/pdf">
+            <video src="intro.mp4" poster="/static/intro.png">
+                <track src="/captions/intro.en.vtt" kind="captions" srclang="en">
+            </video>
+            <object data="movie.swf" codebase="/plugins/"></object>
+            <object codebase="../vendor/player" data="player.swf"></object>
+            <object data="/static/chart.svg" codebase="/plugins/"></object>
+            <object classid="clsid:D27CDB6E-AE6D-11cf-96B8-444553540000"></object>
+            <object data="plain.svg"></object>
+            "#
+            .as_bytes(),
+            &ParserOptions::default(),
+        )
+        .unwrap();
+
+    assert_eq!(
+        links.collect::<Vec<_>>(),
+        &[
+            used_link(&doc.path, "docs/manual.pdf", "manual.pdf", "<embed src>"),
+            used_link(
+                &doc.path,
+                "static/intro.png",
+                "/static/intro.png",
+                "<video poster>"
+            ),
+            used_link(
+                &doc.path,
+                "captions/intro.en.vtt",
+                "/captions/intro.en.vtt",
+                "<track src>"
+            ),
+            used_link(&doc.path, "plugins/movie.swf", "movie.swf", "<object data>"),
+            used_link(
+                &doc.path,
+                "vendor/player.swf",
+                "player.swf",
+                "<object data>"
+            ),
+            used_link(
+                &doc.path,
+                "static/chart.svg",
+                "/static/chart.svg",
+                "<object data>"
+            ),
+            used_link(
+                &doc.path,
+                "clsid:D27CDB6E-AE6D-11cf-96B8-444553540000",
+                "clsid:D27CDB6E-AE6D-11cf-96B8-444553540000",
+                "<object classid>"
+            ),
+            used_link(&doc.path, "docs/plain.svg", "plain.svg", "<object data>"),
         ]
     );
 }
@@ -502,6 +1374,11 @@ fn test_document_links() {
 #[test]
 fn test_document_join_index_html() {
     let arena = bumpalo::Bump::new();
+    let with_anchors = ParserOptions {
+        check_anchors: true,
+        ..Default::default()
+    };
+    let without_anchors = ParserOptions::default();
 
     let doc = Document::new(
         Path::new("public/"),
@@ -509,31 +1386,89 @@ fn test_document_join_index_html() {
     );
 
     assert_eq!(
-        doc.join(&arena, false, "../../ruby#foo"),
+        doc.join(&arena, &without_anchors, "../../ruby#foo"),
         Href("platforms/ruby")
     );
     assert_eq!(
-        doc.join(&arena, true, "../../ruby#foo"),
+        doc.join(&arena, &with_anchors, "../../ruby#foo"),
         Href("platforms/ruby#foo")
     );
     assert_eq!(
-        doc.join(&arena, true, "../../ruby?bar=1#foo"),
+        doc.join(&arena, &with_anchors, "../../ruby?bar=1#foo"),
         Href("platforms/ruby#foo")
     );
 
     assert_eq!(
-        doc.join(&arena, false, "/platforms/ruby"),
+        doc.join(&arena, &without_anchors, "/platforms/ruby"),
         Href("platforms/ruby")
     );
     assert_eq!(
-        doc.join(&arena, true, "/platforms/ruby?bar=1#foo"),
+        doc.join(&arena, &with_anchors, "/platforms/ruby?bar=1#foo"),
         Href("platforms/ruby#foo")
     );
 }
 
+#[test]
+fn test_document_join_strict_trailing_slash() {
+    let arena = bumpalo::Bump::new();
+    let options = ParserOptions {
+        check_anchors: true,
+        strict_trailing_slash: true,
+        ..Default::default()
+    };
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/docs/index.html"));
+    assert_eq!(doc.href_with_trailing_slash(), "docs/");
+    assert_eq!(doc.join(&arena, &options, "#setup"), Href("docs/#setup"));
+    assert_eq!(doc.join(&arena, &options, "guide/"), Href("docs/guide/"));
+    assert_eq!(doc.join(&arena, &options, "guide"), Href("docs/guide"));
+    assert_eq!(doc.join(&arena, &options, "/"), Href(""));
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+    assert_eq!(doc.href_with_trailing_slash(), "");
+    assert_eq!(doc.join(&arena, &options, "#setup"), Href("#setup"));
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/docs/intro.html"));
+    assert_eq!(doc.href_with_trailing_slash(), "docs/intro.html");
+}
+
+#[test]
+fn test_document_join_root_index() {
+    let arena = bumpalo::Bump::new();
+    let options = ParserOptions {
+        check_anchors: true,
+        ..Default::default()
+    };
+
+    for (base_path, path) in [
+        (".", "./index.html"),
+        ("public/", "public/index.html"),
+        ("public/", "public/index.html.br"),
+    ] {
+        let doc = Document::new(Path::new(base_path), Path::new(path));
+        assert_eq!(doc.href(), Href(""));
+
+        assert_eq!(
+            doc.join(&arena, &options, "sub/page.html"),
+            Href("sub/page.html")
+        );
+        assert_eq!(doc.join(&arena, &options, "./sub/"), Href("sub"));
+        assert_eq!(doc.join(&arena, &options, "../sub"), Href("sub"));
+        assert_eq!(doc.join(&arena, &options, "/sub#foo"), Href("sub#foo"));
+        assert_eq!(doc.join(&arena, &options, "#foo"), Href("#foo"));
+        assert_eq!(doc.join(&arena, &options, "?foo"), Href(""));
+        assert_eq!(doc.join(&arena, &options, "./"), Href(""));
+    }
+}
+
 #[test]
 fn test_document_join_bare_html() {
     let arena = bumpalo::Bump::new();
+    let with_anchors = ParserOptions {
+        check_anchors: true,
+        ..Default::default()
+    };
+    let without_anchors = ParserOptions::default();
 
     let doc = Document::new(
         Path::new("public/"),
@@ -541,32 +1476,36 @@ fn test_document_join_bare_html() {
     );
 
     assert_eq!(
-        doc.join(&arena, false, "../ruby#foo"),
+        doc.join(&arena, &without_anchors, "../ruby#foo"),
         Href("platforms/ruby")
     );
     assert_eq!(
-        doc.join(&arena, true, "../ruby#foo"),
+        doc.join(&arena, &with_anchors, "../ruby#foo"),
         Href("platforms/ruby#foo")
     );
     assert_eq!(
-        doc.join(&arena, true, "../ruby?bar=1#foo"),
+        doc.join(&arena, &with_anchors, "../ruby?bar=1#foo"),
         Href("platforms/ruby#foo")
     );
 
     assert_eq!(
-        doc.join(&arena, false, "/platforms/ruby"),
+        doc.join(&arena, &without_anchors, "/platforms/ruby"),
         Href("platforms/ruby")
     );
     assert_eq!(
-        doc.join(&arena, true, "/platforms/ruby?bar=1#foo"),
+        doc.join(&arena, &with_anchors, "/platforms/ruby?bar=1#foo"),
         Href("platforms/ruby#foo")
     );
     assert_eq!(
-        doc.join(&arena, false, "/locations/troms%C3%B8"),
+        doc.join(&arena, &without_anchors, "/locations/troms%C3%B8"),
         Href("locations/tromsø")
     );
     assert_eq!(
-        doc.join(&arena, true, "/locations/oslo#gr%C3%BCnerl%C3%B8kka"),
+        doc.join(
+            &arena,
+            &with_anchors,
+            "/locations/oslo#gr%C3%BCnerl%C3%B8kka"
+        ),
         Href("locations/oslo#grünerløkka")
     );
 }
@@ -582,8 +1521,1360 @@ fn test_json_script() {
     let mut doc_buf = DocumentBuffers::default();
 
     let links = doc
-        .links_from_read::<_, ParagraphHasher>(&mut doc_buf, html.as_bytes(), false)
+        .links_from_read::<_, ParagraphHasher>(
+            &mut doc_buf,
+            html.as_bytes(),
+            &ParserOptions::default(),
+        )
         .unwrap();
 
     assert_eq!(links.collect::<Vec<_>>(), &[]);
 }
+
+#[test]
+fn test_index_html_links() {
+    let arena = bumpalo::Bump::new();
+
+    for (target, links) in [
+        (
+            "public/foo/index.html",
+            &["/foo/index.html", "foo/index.html"][..],
+        ),
+        (
+            "public/foo/index.htm",
+            &["/foo/index.htm", "./foo/index.htm"][..],
+        ),
+        (
+            "public/index.html",
+            &["/index.html", "index.html", "./"][..],
+        ),
+    ] {
+        let target = Document::new(Path::new("public/"), Path::new(target));
+
+        for strict_trailing_slash in [false, true] {
+            let options = ParserOptions {
+                strict_trailing_slash,
+                ..Default::default()
+            };
+            let expected = if strict_trailing_slash {
+                target.href_with_trailing_slash()
+            } else {
+                Cow::Borrowed(target.href().0)
+            };
+
+            for source in ["public/index.html", "public/about.html"] {
+                let source = Document::new(Path::new("public/"), Path::new(source));
+                for link in links {
+                    assert_eq!(
+                        source.join(&arena, &options, link).0,
+                        expected,
+                        "{} from {}, strict: {}",
+                        link,
+                        source.href().0,
+                        strict_trailing_slash
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_document_without_directory_index() {
+    let arena = bumpalo::Bump::new();
+
+    let options = ParserOptions {
+        directory_index: DirectoryIndex::None,
+        ..Default::default()
+    };
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/foo/index.html"))
+        .without_directory_index();
+
+    assert_eq!(doc.href(), Href("foo/index.html"));
+    assert_eq!(doc.join(&arena, &options, "bar.html"), Href("foo/bar.html"));
+    assert_eq!(
+        doc.join(&arena, &options, "/foo/index.html"),
+        Href("foo/index.html")
+    );
+    assert_eq!(doc.join(&arena, &options, "/foo/"), Href("foo"));
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"))
+        .without_directory_index();
+    assert_eq!(doc.href(), Href("index.html"));
+}
+
+#[test]
+fn test_download_links() {
+    use crate::paragraph::ParagraphHasher;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+
+    let mut doc_buf = DocumentBuffers::default();
+
+    let links = doc
+        .links_from_read::<_, ParagraphHasher>(
+            &mut doc_buf,
+            r#"
+            <a href="files/report.pdf" download>Report</a>
+            <a download="data.csv" href="files/data.csv">Data</a>
+            <a href="about.html">About</a>
+            "#
+            .as_bytes(),
+            &ParserOptions::default(),
+        )
+        .unwrap();
+
+    assert_eq!(
+        links.collect::<Vec<_>>(),
+        &[
+            used_link(
+                &doc.path,
+                "files/report.pdf",
+                "files/report.pdf",
+                "<a download>"
+            ),
+            used_link(
+                &doc.path,
+                "files/data.csv",
+                "files/data.csv",
+                "<a download>"
+            ),
+            used_link(&doc.path, "about.html", "about.html", "<a href>"),
+        ]
+    );
+}
+
+#[test]
+fn test_anchor_attributes() {
+    use crate::paragraph::ParagraphHasher;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+
+    let mut doc_buf = DocumentBuffers::default();
+
+    let links = doc
+        .links_from_read::<_, ParagraphHasher>(
+            &mut doc_buf,
+            r#"<h2 data-anchor-id="setup">Setup</h2> <h2 data-other="nope">Nope</h2>"#.as_bytes(),
+            &ParserOptions {
+                check_anchors: true,
+                anchor_attributes: vec!["data-anchor-id".to_owned()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    assert_eq!(
+        links.collect::<Vec<_>>(),
+        &[Link::Defines(DefinedLink {
+            href: Href("#setup"),
+            unexpected_element: None,
+            raw_anchor: None,
+        })]
+    );
+}
+
+#[test]
+fn test_footnote_anchors() {
+    use crate::collector::{BrokenLinkCollector, CheckOptions, LinkCollector};
+    use crate::paragraph::NoopParagraphWalker;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/blog/post.html"));
+
+    let mut doc_buf = DocumentBuffers::default();
+
+    // as rendered by pulldown-cmark and Hugo, the back-reference lives on a <sup>
+    let links: Vec<_> = doc
+        .links_from_read::<_, NoopParagraphWalker>(
+            &mut doc_buf,
+            r##"
+            <p>Claim<sup class="footnote-ref" id="fnref1"><a href="#fn1">1</a></sup></p>
+            <p>Other claim<sup id="fnref:2"><a href="#fn:2" class="footnote-ref">2</a></sup></p>
+            <section class="footnotes">
+                <ol>
+                    <li id="fn1"><p>Source <a href="#fnref1" class="footnote-backref">↩</a></p></li>
+                    <li id="fn:2"><p>Source <a href="#fnref:2" class="footnote-backref">↩</a></p></li>
+                </ol>
+            </section>
+            "##
+            .as_bytes(),
+            &ParserOptions {
+                check_anchors: true,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .collect();
+
+    let defined: Vec<_> = links
+        .iter()
+        .filter_map(|link| match link {
+            Link::Defines(DefinedLink { href, .. }) => Some(href.0),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        defined,
+        &[
+            "blog/post.html#fnref1",
+            "blog/post.html#fnref:2",
+            "blog/post.html#fn1",
+            "blog/post.html#fn:2",
+        ]
+    );
+
+    let mut collector = BrokenLinkCollector::new();
+    collector.ingest(Link::Defines(DefinedLink {
+        href: doc.href(),
+        unexpected_element: None,
+        raw_anchor: None,
+    }));
+    for link in links {
+        collector.ingest(link);
+    }
+
+    let options = CheckOptions {
+        check_anchors: true,
+        ..Default::default()
+    };
+    assert_eq!(collector.get_broken_links(&options).count(), 0);
+}
+
+#[test]
+fn test_form_actions() {
+    use crate::paragraph::ParagraphHasher;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/search/index.html"));
+
+    let mut doc_buf = DocumentBuffers::default();
+
+    let links = doc
+        .links_from_read::<_, ParagraphHasher>(
+            &mut doc_buf,
+            r#"
+            <form action="/api/search">
+                <button formaction="advanced/">Advanced</button>
+                <input type=submit formaction="">
+                <input type=image src="submit.png" alt="Search">
+            </form>
+            "#
+            .as_bytes(),
+            &ParserOptions::default(),
+        )
+        .unwrap();
+
+    assert_eq!(
+        links.collect::<Vec<_>>(),
+        &[
+            used_link(&doc.path, "api/search", "/api/search", "<form action>"),
+            used_link(
+                &doc.path,
+                "search/advanced",
+                "advanced/",
+                "<button formaction>"
+            ),
+            used_link(&doc.path, "search", "", "<input formaction>"),
+            used_link(&doc.path, "search/submit.png", "submit.png", "<input src>"),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_comments() {
+    use crate::paragraph::ParagraphHasher;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+    let html = r#"
+        <!--[if lt IE 9]><link rel="stylesheet" href="ie.css"><![endif]-->
+        <!-- <a href="old/">old</a> -->
+        <a href="new/">new</a>
+    "#;
+
+    let hrefs = |options: &ParserOptions| {
+        let mut doc_buf = DocumentBuffers::default();
+        doc.links_from_read::<_, ParagraphHasher>(&mut doc_buf, html.as_bytes(), options)
+            .unwrap()
+            .filter_map(|link| match link {
+                Link::Uses(used) => Some((used.href.0.to_owned(), used.element)),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        hrefs(&ParserOptions::default()),
+        &[("new".to_owned(), "<a href>")]
+    );
+    assert_eq!(
+        hrefs(&ParserOptions {
+            parse_comments: true,
+            ..Default::default()
+        }),
+        &[
+            ("ie.css".to_owned(), "<link href>"),
+            ("old".to_owned(), "<a href>"),
+            ("new".to_owned(), "<a href>"),
+        ]
+    );
+}
+
+#[test]
+fn test_report_mixed_separators() {
+    use crate::paragraph::ParagraphHasher;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+    let html = r#"<a href="docs\guide.html"></a><a href="docs/guide.html"></a>"#;
+
+    let warnings = |options: &ParserOptions| {
+        let mut doc_buf = DocumentBuffers::default();
+        doc.links_from_read::<_, ParagraphHasher>(&mut doc_buf, html.as_bytes(), options)
+            .unwrap()
+            .filter_map(|link| match link {
+                Link::Warns(warning) => Some(warning.message),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert!(warnings(&ParserOptions::default()).is_empty());
+    assert_eq!(
+        warnings(&ParserOptions {
+            report_mixed_separators: true,
+            ..Default::default()
+        }),
+        &["backslash in href: docs\\guide.html"]
+    );
+}
+
+#[test]
+fn test_report_empty_anchors() {
+    use crate::paragraph::ParagraphHasher;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/guide.html"));
+    let html = r#"<h2 id="">Intro</h2> <a name=" "></a> <h2 id=setup>Setup</h2>"#;
+
+    let links = |options: &ParserOptions| {
+        let mut doc_buf = DocumentBuffers::default();
+        doc.links_from_read::<_, ParagraphHasher>(&mut doc_buf, html.as_bytes(), options)
+            .unwrap()
+            .map(|link| match link {
+                Link::Defines(defined_link) => defined_link.href.0.to_owned(),
+                Link::Warns(warning) => warning.message,
+                link => panic!("unexpected link {:?}", link),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut options = ParserOptions {
+        check_anchors: true,
+        ..Default::default()
+    };
+    assert_eq!(links(&options), &["guide.html#setup"]);
+
+    options.report_empty_anchors = true;
+    assert_eq!(
+        links(&options),
+        &[
+            "empty id attribute",
+            "empty name attribute",
+            "guide.html#setup"
+        ]
+    );
+}
+
+#[test]
+fn test_strict_scheme() {
+    use crate::paragraph::ParagraphHasher;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+    let html = r#"<a href="htps://example.com"></a><a href="MAILTO:a@example.com"></a>
+        <img src="data:image/png;base64,AAAA"><a href="guide.html"></a>"#;
+
+    let warnings = |options: &ParserOptions| {
+        let mut doc_buf = DocumentBuffers::default();
+        doc.links_from_read::<_, ParagraphHasher>(&mut doc_buf, html.as_bytes(), options)
+            .unwrap()
+            .filter_map(|link| match link {
+                Link::Warns(warning) => Some(warning.message),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert!(warnings(&ParserOptions::default()).is_empty());
+    assert_eq!(
+        warnings(&ParserOptions {
+            strict_scheme: true,
+            ..Default::default()
+        }),
+        &["unknown URL scheme \"htps\""]
+    );
+}
+
+#[test]
+fn test_warn_duplicate_links() {
+    use crate::paragraph::ParagraphHasher;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+    let html = r#"
+        <a href="guide.html">Guide</a>
+        <a href=guide.html><img src=guide.png></a>
+        <a href="/about/"> <b>Read   more</b> </a>
+        <a href="/blog/">Read more</a>
+        <a href="/about/">Read more</a>
+        <a href="guide.html">Guide
+        <a href="faq.html">FAQ</a>
+    "#;
+
+    let warnings = |options: &ParserOptions| {
+        let mut doc_buf = DocumentBuffers::default();
+        doc.links_from_read::<_, ParagraphHasher>(&mut doc_buf, html.as_bytes(), options)
+            .unwrap()
+            .filter_map(|link| match link {
+                Link::Warns(warning) => Some(warning.message),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert!(warnings(&ParserOptions::default()).is_empty());
+    assert_eq!(
+        warnings(&ParserOptions {
+            warn_duplicate_links: true,
+            ..Default::default()
+        }),
+        &[
+            "duplicate link to guide.html",
+            "links with the same text \"Read more\" point to /about/ and /blog/",
+            "duplicate link to /about/",
+        ]
+    );
+}
+
+#[test]
+fn test_ignore_selectors() {
+    use crate::paragraph::ParagraphHasher;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+    let mut doc_buf = DocumentBuffers::default();
+    let options = ParserOptions {
+        check_anchors: true,
+        ignore_selectors: ["nav", ".footer", "#sidebar", "img"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect(),
+        ..Default::default()
+    };
+
+    let links = doc
+        .links_from_read::<_, ParagraphHasher>(
+            &mut doc_buf,
+            r#"
+            <nav><a href="nav.html">Nav</a><nav><a href=nested.html></nav><a href=nav2.html></nav>
+            <div class="page footer"><a href="footer.html" id=credits>Credits</a></div>
+            <a href="sidebar.html" id="sidebar"></a>
+            <img src="logo.png"><br>
+            <a href="main.html">Main</a>
+            <div class="footer-like"><a href="other.html"></a></div>
+            "#
+            .as_bytes(),
+            &options,
+        )
+        .unwrap()
+        .map(|link| match link {
+            Link::Uses(UsedLink { href, .. }) => format!("uses {}", href.0),
+            Link::Defines(DefinedLink { href, .. }) => format!("defines {}", href.0),
+            Link::Warns(warning) => warning.message,
+            Link::Redirects(RedirectLink { to, .. }) => format!("redirects to {}", to.0),
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        links,
+        &[
+            "defines #credits",
+            "defines #sidebar",
+            "uses main.html",
+            "uses other.html",
+        ]
+    );
+
+    // Elements whose end tag is omitted end where the browser ends them.
+    let options = ParserOptions {
+        ignore_selectors: [".nav", ".footer"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect(),
+        ..Default::default()
+    };
+    let mut doc_buf = DocumentBuffers::default();
+    let links = doc
+        .links_from_read::<_, ParagraphHasher>(
+            &mut doc_buf,
+            r#"
+            <ul><li class=nav><a href=m1.html>x</a><li><a href=m2.html>y</a></ul>
+            <ul><li class=nav><a href=m3.html>x</a></ul><a href=m4.html>z</a>
+            <p class=footer>foo<p><a href=m5.html>bar</a>
+            <div><p class=footer><a href=m6.html></div><a href=m7.html>
+            "#
+            .as_bytes(),
+            &options,
+        )
+        .unwrap()
+        .filter_map(|link| match link {
+            Link::Uses(UsedLink { href, .. }) => Some(href.0),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(links, &["m2.html", "m4.html", "m5.html", "m7.html"]);
+
+    assert_eq!(
+        "DIV".parse::<IgnoreSelector>(),
+        Ok(IgnoreSelector::Tag("div".to_owned()))
+    );
+    assert_eq!(
+        "nav a".parse::<IgnoreSelector>(),
+        Err("unsupported selector \"nav a\", expected a tag, .class or #id".to_owned())
+    );
+    assert!("div.footer".parse::<IgnoreSelector>().is_err());
+    assert!(".".parse::<IgnoreSelector>().is_err());
+}
+
+#[test]
+fn test_img_many_attributes() {
+    use crate::paragraph::ParagraphHasher;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+    let mut doc_buf = DocumentBuffers::default();
+
+    let links = doc
+        .links_from_read::<_, ParagraphHasher>(
+            &mut doc_buf,
+            r##"
+            <img loading="lazy" decoding=async ismap alt='href="wrong.png"' width=100
+                 height="50" class="a b" data-src="lazy.png" hidden
+                 srcset="small.png 1x, large.png 2x" fetchpriority=low draggable=false src="photo.png"
+                 crossorigin>
+            <img src=first.png decoding="async" loading=lazy sizes="100vw" usemap="#map" itemprop
+                 srcset="a.png 100w,b.png 200w" referrerpolicy=no-referrer title=src>
+            "##
+            .as_bytes(),
+            &ParserOptions::default(),
+        )
+        .unwrap();
+
+    assert_eq!(
+        links.collect::<Vec<_>>(),
+        &[
+            used_link(&doc.path, "small.png", "small.png", "<img srcset>"),
+            used_link(&doc.path, "large.png", "large.png", "<img srcset>"),
+            used_link(&doc.path, "photo.png", "photo.png", "<img src>"),
+            used_link(&doc.path, "first.png", "first.png", "<img src>"),
+            used_link(&doc.path, "a.png", "a.png", "<img srcset>"),
+            used_link(&doc.path, "b.png", "b.png", "<img srcset>"),
+        ]
+    );
+}
+
+#[test]
+fn test_picture_sources() {
+    use crate::paragraph::ParagraphHasher;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+    let mut doc_buf = DocumentBuffers::default();
+
+    let links = doc
+        .links_from_read::<_, ParagraphHasher>(
+            &mut doc_buf,
+            r#"
+            <picture>
+                <source srcset="wide.avif 1x, wide@2x.avif 2x" media=" (min-width: 800px) ">
+                <source srcset="narrow.avif">
+                <img src="fallback.png">
+            </picture>
+            "#
+            .as_bytes(),
+            &ParserOptions::default(),
+        )
+        .unwrap();
+
+    assert_eq!(
+        links
+            .filter_map(|link| match link {
+                Link::Uses(used_link) =>
+                    Some((used_link.href.0, used_link.element, used_link.media)),
+                _ => None,
+            })
+            .collect::<Vec<_>>(),
+        &[
+            ("wide.avif", "<source srcset>", Some("(min-width: 800px)")),
+            (
+                "wide@2x.avif",
+                "<source srcset>",
+                Some("(min-width: 800px)")
+            ),
+            ("narrow.avif", "<source srcset>", None),
+            ("fallback.png", "<img src>", None),
+        ]
+    );
+}
+
+#[test]
+fn test_xml_base() {
+    use crate::paragraph::ParagraphHasher;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/img/diagram.svg"));
+    assert!(doc.is_xml);
+
+    let mut doc_buf = DocumentBuffers::default();
+
+    let links = doc
+        .links_from_read::<_, ParagraphHasher>(
+            &mut doc_buf,
+            r#"
+            <svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">
+                <a href="a.html"><rect/></a>
+                <g xml:base="/docs/">
+                    <a xlink:href="b.html" xml:base="guide/"/>
+                    <g xml:base="../api/index.html">
+                        <a href="c.html"></a>
+                    </g>
+                    <a href="d.html"></a>
+                    <a href="/e.html"></a>
+                </g>
+                <a href="f.html"></a>
+            </svg>
+            "#
+            .as_bytes(),
+            &ParserOptions::default(),
+        )
+        .unwrap();
+
+    assert_eq!(
+        links.collect::<Vec<_>>(),
+        &[
+            used_link(&doc.path, "img/a.html", "a.html", "<a href>"),
+            used_link(&doc.path, "docs/guide/b.html", "b.html", "<a xlink:href>"),
+            used_link(&doc.path, "api/c.html", "c.html", "<a href>"),
+            used_link(&doc.path, "docs/d.html", "d.html", "<a href>"),
+            used_link(&doc.path, "e.html", "/e.html", "<a href>"),
+            used_link(&doc.path, "img/f.html", "f.html", "<a href>"),
+        ]
+    );
+
+    // xml:base has no meaning in HTML documents
+    let doc = Document::new(Path::new("public/"), Path::new("public/img/index.html"));
+    let mut doc_buf = DocumentBuffers::default();
+    let links = doc
+        .links_from_read::<_, ParagraphHasher>(
+            &mut doc_buf,
+            r#"<div xml:base="/docs/"><a href="a.html"></a></div>"#.as_bytes(),
+            &ParserOptions::default(),
+        )
+        .unwrap();
+
+    assert_eq!(
+        links.collect::<Vec<_>>(),
+        &[used_link(&doc.path, "img/a.html", "a.html", "<a href>")]
+    );
+}
+
+#[test]
+fn test_link_attributes() {
+    use crate::paragraph::ParagraphHasher;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+    let html = r#"
+        <a href="a.html" id="top">a</a>
+        <img src="logo.png">
+        <div data-href="b.html"></div>
+        <DIV DATA-HREF="c.html"></DIV>
+    "#;
+
+    assert!("div".parse::<LinkAttribute>().is_err());
+    assert!(":href".parse::<LinkAttribute>().is_err());
+
+    let mut options = ParserOptions {
+        check_anchors: true,
+        link_attributes: vec!["div:data-href".parse().unwrap()],
+        ..Default::default()
+    };
+
+    let mut doc_buf = DocumentBuffers::default();
+    let links = doc
+        .links_from_read::<_, ParagraphHasher>(&mut doc_buf, html.as_bytes(), &options)
+        .unwrap();
+
+    assert_eq!(
+        links.collect::<Vec<_>>(),
+        &[
+            used_link(&doc.path, "a.html", "a.html", "<a href>"),
+            Link::Defines(DefinedLink {
+                href: Href("#top"),
+                unexpected_element: None,
+                raw_anchor: None,
+            }),
+            used_link(&doc.path, "logo.png", "logo.png", "<img src>"),
+            used_link(&doc.path, "b.html", "b.html", "<div data-href>"),
+            used_link(&doc.path, "c.html", "c.html", "<div data-href>"),
+        ]
+    );
+
+    options.no_default_tags = true;
+    let mut doc_buf = DocumentBuffers::default();
+    let links = doc
+        .links_from_read::<_, ParagraphHasher>(&mut doc_buf, html.as_bytes(), &options)
+        .unwrap();
+
+    assert_eq!(
+        links.collect::<Vec<_>>(),
+        &[
+            Link::Defines(DefinedLink {
+                href: Href("#top"),
+                unexpected_element: None,
+                raw_anchor: None,
+            }),
+            used_link(&doc.path, "b.html", "b.html", "<div data-href>"),
+            used_link(&doc.path, "c.html", "c.html", "<div data-href>"),
+        ]
+    );
+}
+
+#[test]
+fn test_ping_and_target() {
+    use crate::paragraph::ParagraphHasher;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+    let html = r#"
+        <a href="a.html" ping="/track /count?a=1" target="_blank">a</a>
+        <map><area href="b.html" ping=" track " target="frame"></map>
+        <form><button formaction="c.html" formtarget="_SELF">c</button></form>
+    "#;
+
+    let options = ParserOptions {
+        link_attributes: vec![
+            "a:target".parse().unwrap(),
+            "area:target".parse().unwrap(),
+            "button:formtarget".parse().unwrap(),
+        ],
+        ..Default::default()
+    };
+
+    let mut doc_buf = DocumentBuffers::default();
+    let links = doc
+        .links_from_read::<_, ParagraphHasher>(&mut doc_buf, html.as_bytes(), &options)
+        .unwrap();
+
+    assert_eq!(
+        links.collect::<Vec<_>>(),
+        &[
+            used_link(&doc.path, "a.html", "a.html", "<a href>"),
+            used_link(&doc.path, "track", "/track", "<a ping>"),
+            used_link(&doc.path, "count", "/count?a=1", "<a ping>"),
+            used_link(&doc.path, "b.html", "b.html", "<area href>"),
+            used_link(&doc.path, "track", "track", "<area ping>"),
+            used_link(&doc.path, "frame", "frame", "<area target>"),
+            used_link(&doc.path, "c.html", "c.html", "<button formaction>"),
+        ]
+    );
+}
+
+#[test]
+fn test_id_references() {
+    use crate::paragraph::ParagraphHasher;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/docs/index.html"));
+    let html = r#"
+        <input aria-describedby="hint  error" aria-labelledby=label>
+        <p id=hint>
+        <label for=email>Email</label>
+    "#;
+
+    let options = ParserOptions {
+        check_anchors: true,
+        check_aria_references: true,
+        check_label_for: true,
+        ..Default::default()
+    };
+
+    let mut doc_buf = DocumentBuffers::default();
+    let links = doc
+        .links_from_read::<_, ParagraphHasher>(&mut doc_buf, html.as_bytes(), &options)
+        .unwrap();
+
+    let id_reference =
+        |href, raw_href, element, referrer| match used_link(&doc.path, href, raw_href, element) {
+            Link::Uses(link) => Link::Uses(UsedLink {
+                referrer: Some(referrer),
+                ..link
+            }),
+            link => link,
+        };
+
+    assert_eq!(
+        links.collect::<Vec<_>>(),
+        &[
+            id_reference(
+                "docs#hint",
+                "#hint",
+                "<* aria-describedby>",
+                "aria-describedby"
+            ),
+            id_reference(
+                "docs#error",
+                "#error",
+                "<* aria-describedby>",
+                "aria-describedby"
+            ),
+            id_reference(
+                "docs#label",
+                "#label",
+                "<* aria-labelledby>",
+                "aria-labelledby"
+            ),
+            Link::Defines(DefinedLink {
+                href: Href("docs#hint"),
+                unexpected_element: None,
+                raw_anchor: None,
+            }),
+            id_reference("docs#email", "#email", "<label for>", "label"),
+        ]
+    );
+}
+
+#[test]
+fn test_meta_links() {
+    use crate::paragraph::ParagraphHasher;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/old/index.html"));
+
+    let mut doc_buf = DocumentBuffers::default();
+
+    let links = doc
+        .links_from_read::<_, ParagraphHasher>(
+            &mut doc_buf,
+            r#"
+            <meta charset="utf-8">
+            <meta name="referrer" content="no-referrer">
+            <meta name="description" content="visit http://example.com or /docs/ for more">
+            <meta name="viewport" content="width=device-width, initial-scale=1">
+            <meta property="og:title" content="/not/a/link">
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <meta http-equiv="refresh" content="30">
+            <meta content="0; url=../new/" http-equiv="Refresh">
+            <meta property="og:image" content="/static/card.png">
+            <meta name="twitter:image" content="card.png">
+            "#
+            .as_bytes(),
+            &ParserOptions::default(),
+        )
+        .unwrap();
+
+    assert_eq!(
+        links.collect::<Vec<_>>(),
+        &[
+            used_link(&doc.path, "new", "../new/", "<meta http-equiv=refresh>"),
+            used_link(
+                &doc.path,
+                "static/card.png",
+                "/static/card.png",
+                "<meta property=og:image>"
+            ),
+            used_link(
+                &doc.path,
+                "old/card.png",
+                "card.png",
+                "<meta name=twitter:image>"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_follow_meta_refresh_chain() {
+    use crate::paragraph::ParagraphHasher;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/old/index.html"));
+    let mut doc_buf = DocumentBuffers::default();
+    let options = ParserOptions {
+        follow_meta_refresh_chain: true,
+        ..Default::default()
+    };
+
+    let links = doc
+        .links_from_read::<_, ParagraphHasher>(
+            &mut doc_buf,
+            r#"
+            <meta http-equiv="refresh" content="0; url=../new/#intro">
+            <meta property="og:image" content="/static/card.png">
+            "#
+            .as_bytes(),
+            &options,
+        )
+        .unwrap()
+        .filter(|link| !matches!(link, Link::Uses(_)))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        links,
+        &[Link::Redirects(RedirectLink {
+            from: Href("old"),
+            to: Href("new"),
+        })]
+    );
+}
+
+#[test]
+fn test_heading_anchors() {
+    use crate::paragraph::ParagraphHasher;
+
+    let options = ParserOptions {
+        check_anchors: true,
+        heading_anchors: true,
+        ..Default::default()
+    };
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/hello.html"));
+    let mut doc_buf = DocumentBuffers::default();
+
+    let html = r###"
+        <a href="#" id="top">Top</a>
+        <h2 id="intro"><code>foo</code> bar</h2>
+        <h2><code>foo</code> <em>Bar</em>, baz!</h2>
+        <h3>Usage</h3>
+        <h3>Usage</h3>
+    "###;
+
+    let defined: Vec<_> = doc
+        .links_from_read::<_, ParagraphHasher>(&mut doc_buf, html.as_bytes(), &options)
+        .unwrap()
+        .filter_map(|link| match link {
+            Link::Defines(DefinedLink { href, .. }) => Some(href),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        defined,
+        &[
+            Href("hello.html#top"),
+            Href("hello.html#intro"),
+            Href("hello.html#foo-bar-baz"),
+            Href("hello.html#usage"),
+            Href("hello.html#usage-1"),
+        ]
+    );
+}
+
+#[test]
+fn test_links_at_buffer_boundary() {
+    use crate::paragraph::ParagraphHasher;
+
+    /// Returns at most 4096 bytes per read, like a pipe or a decompressor.
+    struct ChunkedRead<'a>(&'a [u8]);
+
+    impl Read for ChunkedRead<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(self.0.len()).min(4096);
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Ok(len)
+        }
+    }
+
+    let options = ParserOptions {
+        check_anchors: true,
+        heading_anchors: true,
+        ..Default::default()
+    };
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+    let mut doc_buf = DocumentBuffers::default();
+
+    let tag = r#"<a href="boundary.html">"#;
+    let heading = "<h2>Über</h2>";
+    for offset in 0..=tag.len().max(heading.len()) {
+        let mut html = "x".repeat(BUF_SIZE - offset);
+        html.push_str(tag);
+        html.push_str("<p>");
+        html.push_str(&"y".repeat(BUF_SIZE - tag.len() - 3));
+        html.push_str(heading);
+
+        for chunked in [false, true] {
+            let read: Box<dyn Read> = if chunked {
+                Box::new(ChunkedRead(html.as_bytes()))
+            } else {
+                Box::new(html.as_bytes())
+            };
+            let links: Vec<_> = doc
+                .links_from_read::<_, ParagraphHasher>(&mut doc_buf, read, &options)
+                .unwrap()
+                .map(|link| match link {
+                    Link::Uses(UsedLink { href, .. }) => href.0.to_owned(),
+                    Link::Defines(DefinedLink { href, .. }) => href.0.to_owned(),
+                    Link::Warns(warning) => warning.message,
+                    Link::Redirects(RedirectLink { to, .. }) => to.0.to_owned(),
+                })
+                .collect();
+            doc_buf.reset();
+
+            assert_eq!(
+                links,
+                ["boundary.html", "#über"],
+                "offset {}, chunked {}",
+                offset,
+                chunked
+            );
+        }
+    }
+}
+
+#[test]
+fn test_css_links() {
+    let mut doc_buf = DocumentBuffers::default();
+    let doc = Document::new(Path::new("public/"), Path::new("public/css/theme.css"));
+    let options = ParserOptions {
+        check_anchors: true,
+        ..Default::default()
+    };
+
+    let links: Vec<Link<'_, ()>> = doc
+        .css_links_from_str(
+            &mut doc_buf,
+            "@import 'fonts.css'; a { background: url(../img/a.svg#icon) } b { background: url(//example.com/b.png) }",
+            &options,
+        )
+        .unwrap()
+        .collect();
+
+    let path = Arc::new(PathBuf::from("public/css/theme.css"));
+    assert_eq!(
+        links,
+        vec![
+            used_link(&path, "css/fonts.css", "fonts.css", "@import"),
+            used_link(&path, "img/a.svg", "../img/a.svg#icon", "url()"),
+            used_link(&path, "//example.com/b.png", "//example.com/b.png", "url()"),
+        ]
+    );
+}
+
+#[test]
+fn test_fragment_only_links() {
+    use crate::paragraph::ParagraphHasher;
+
+    let options = ParserOptions {
+        check_anchors: true,
+        ..Default::default()
+    };
+
+    let arena = bumpalo::Bump::new();
+    let partial = Document::new(
+        Path::new("public/"),
+        Path::new("public/partials/header.html"),
+    );
+    assert_eq!(
+        partial.join(&arena, &options, "#nav"),
+        Href("partials/header.html#nav")
+    );
+    let index = Document::new(Path::new("public/"), Path::new("public/docs/index.html"));
+    assert_eq!(index.join(&arena, &options, "#nav"), Href("docs#nav"));
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/img/icons.svg"));
+    let mut doc_buf = DocumentBuffers::default();
+    let links: Vec<_> = doc
+        .links_from_read::<_, ParagraphHasher>(
+            &mut doc_buf,
+            r##"
+            <svg xmlns="http://www.w3.org/2000/svg" xml:base="/shared/">
+                <a href="#icon"/>
+                <a href="sprite.svg#icon"/>
+            </svg>
+            "##
+            .as_bytes(),
+            &options,
+        )
+        .unwrap()
+        .filter_map(|link| match link {
+            Link::Uses(used_link) => Some(used_link.href.0.to_owned()),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(links, ["img/icons.svg#icon", "shared/sprite.svg#icon"]);
+}
+
+#[test]
+fn test_document_join_anchor_case() {
+    let arena = bumpalo::Bump::new();
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/intro.html"));
+
+    let options = |anchor_case| ParserOptions {
+        check_anchors: true,
+        anchor_case,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        doc.join(&arena, &options(AnchorCase::Sensitive), "#Intro-İ"),
+        Href("intro.html#Intro-İ")
+    );
+    assert_eq!(
+        doc.join(&arena, &options(AnchorCase::Ascii), "#Intro-İ"),
+        Href("intro.html#intro-İ")
+    );
+    assert_eq!(
+        doc.join(&arena, &options(AnchorCase::Unicode), "#Intro-%C3%84"),
+        Href("intro.html#intro-ä")
+    );
+}
+
+#[test]
+fn test_filesystem_mode() {
+    use crate::paragraph::ParagraphHasher;
+
+    let mut doc_buf = DocumentBuffers::default();
+    let doc = Document::new(Path::new("public/"), Path::new("public/docs/guide.html"));
+    let options = ParserOptions {
+        filesystem_mode: true,
+        ..Default::default()
+    };
+
+    let links: Vec<_> = doc
+        .links_from_read::<_, ParagraphHasher>(
+            &mut doc_buf,
+            r#"
+            <a href="/about.html">About</a>
+            <a href="../about.html">About</a>
+            <a href="//example.com/">Example</a>
+            <img srcset="/a.png 1x, b.png 2x">
+            "#
+            .as_bytes(),
+            &options,
+        )
+        .unwrap()
+        .filter_map(|link| match link {
+            Link::Uses(UsedLink { href, absolute, .. }) => Some((href.0, absolute)),
+            _ => None,
+        })
+        .collect();
+
+    // Absolute links keep their canonical href, and are only marked as such.
+    assert_eq!(
+        links,
+        [
+            ("about.html", true),
+            ("about.html", false),
+            ("//example.com/", false),
+            ("a.png", true),
+            ("docs/b.png", false),
+        ]
+    );
+}
+
+#[test]
+fn test_document_join_hashbang_routes() {
+    let arena = bumpalo::Bump::new();
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/app.html"));
+
+    let mut options = ParserOptions {
+        check_anchors: true,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        doc.join(&arena, &options, "#!/section"),
+        Href("app.html#!/section")
+    );
+
+    options.hashbang_routes = true;
+    assert_eq!(doc.join(&arena, &options, "#!/section"), Href("app.html"));
+    assert_eq!(
+        doc.join(&arena, &options, "guide.html#!section"),
+        Href("guide.html")
+    );
+    assert_eq!(
+        doc.join(&arena, &options, "guide.html#setup"),
+        Href("guide.html#setup")
+    );
+}
+
+#[test]
+fn test_open_files() {
+    let open_files = Arc::new(OpenFiles::new(2));
+    let first = open_files.acquire();
+    let second = open_files.acquire();
+    assert_eq!(*open_files.available.lock().unwrap(), 0);
+
+    let waiter = {
+        let open_files = open_files.clone();
+        std::thread::spawn(move || drop(open_files.acquire()))
+    };
+    drop(first);
+    waiter.join().unwrap();
+    assert_eq!(*open_files.available.lock().unwrap(), 1);
+
+    drop(second);
+    assert_eq!(*open_files.available.lock().unwrap(), 2);
+}
+
+#[test]
+fn test_document_reserved_characters_in_path() {
+    let arena = bumpalo::Bump::new();
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/c#/why?.html"));
+    assert_eq!(doc.href(), Href("c%23/why%3F.html"));
+
+    let options = ParserOptions {
+        check_anchors: true,
+        ..Default::default()
+    };
+
+    let index = Document::new(Path::new("public/"), Path::new("public/index.html"));
+    assert_eq!(
+        index.join(&arena, &options, "c%23/why%3F.html#intro"),
+        Href("c%23/why%3F.html#intro")
+    );
+    assert_eq!(
+        index
+            .join(&arena, &options, "c%23/why%3F.html")
+            .without_anchor(),
+        doc.href()
+    );
+}
+
+#[test]
+fn test_link_positions() {
+    use crate::paragraph::ParagraphHasher;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+
+    let mut doc_buf = DocumentBuffers::default();
+
+    let html = "<!-- <a href=a.html> -->\r\n\t<a href=a.html>a</a>\n\
+                <script>'<a href=\"b.html\">'</script><p>über <a\thref='&#98;.html'>b</a>\n\
+                <img alt=x src=a.html>";
+    let positions: Vec<_> = doc
+        .links_from_read::<_, ParagraphHasher>(
+            &mut doc_buf,
+            html.as_bytes(),
+            &ParserOptions {
+                html_tab_size: Some(4),
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .filter_map(|link| match link {
+            Link::Uses(used_link) => Some((used_link.raw_href, used_link.position.unwrap())),
+            _ => None,
+        })
+        .collect();
+
+    let position = |offset, line, column| Position {
+        offset,
+        line,
+        column,
+    };
+    assert_eq!(
+        positions,
+        &[
+            ("a.html", position(30, 2, 8)),
+            ("b.html", position(96, 3, 49)),
+            ("a.html", position(131, 4, 12)),
+        ]
+    );
+    assert!(html[96..].starts_with("href='&#98;.html'"));
+}
+
+#[test]
+fn test_check_tracks() {
+    use crate::paragraph::ParagraphHasher;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+
+    let mut doc_buf = DocumentBuffers::default();
+
+    let warnings: Vec<_> = doc
+        .links_from_read::<_, ParagraphHasher>(
+            &mut doc_buf,
+            r#"
+            <video src="a.mp4">
+                <track src="en.vtt" srclang="en" label="English" default>
+                <track src="de.vtt" kind="captions" srclang="de" label="Deutsch" default>
+                <track src="chapters.vtt" kind="chapters" srclang="en" default>
+                <track src="meta1.vtt" kind="metadata" label="a" default>
+                <track src="meta2.vtt" kind="metadata" label="b" default>
+            </video>
+            <audio src="a.mp3">
+                <track src="a.vtt" kind="Subtitle" srclang="en">
+                <track src="b.vtt">
+                <track src="c.vtt" kind="captions" srclang="EN">
+                <track src="d.vtt" kind="captions" srclang="en">
+            </audio>
+            <video src="b.mp4">
+                <track src="en.vtt" kind="SUBTITLES" srclang="en" default>
+            </video>
+            "#
+            .as_bytes(),
+            &ParserOptions {
+                check_tracks: true,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .filter_map(|link| match link {
+            Link::Warns(warning) => Some(warning.message),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        warnings,
+        &[
+            "<video> has 2 default subtitles or captions tracks, at most one is allowed",
+            "<track> has unknown kind \"Subtitle\"",
+            "<track kind=subtitles> without srclang",
+            "<audio> has multiple captions tracks with the same srclang and label",
+        ]
+    );
+}
+
+#[test]
+fn test_check_srcset_sizes() {
+    use crate::paragraph::ParagraphHasher;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+
+    let mut doc_buf = DocumentBuffers::default();
+
+    let warnings: Vec<_> = doc
+        .links_from_read::<_, ParagraphHasher>(
+            &mut doc_buf,
+            r#"
+            <img srcset="a.png 300w, b.png 600w">
+            <img srcset="a.png 300w, b.png 600w" sizes="50vw">
+            <img sizes="50vw" srcset="a.png 300w, b.png 600w">
+            <img srcset="a.png 1x, b.png 2x">
+            <img src="a.png">
+            "#
+            .as_bytes(),
+            &ParserOptions {
+                check_srcset_sizes: true,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .filter(|link| matches!(link, Link::Warns(_)))
+        .collect();
+
+    assert_eq!(
+        warnings,
+        &[Link::Warns(Warning {
+            path: doc.path.clone(),
+            message: "srcset with width descriptors but without sizes attribute".to_owned(),
+        })]
+    );
+}
@@ -1,27 +1,330 @@
+use std::cell::Cell;
+use std::collections::{BTreeSet, HashMap};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
 use bumpalo::collections::String as BumpString;
 use bumpalo::collections::Vec as BumpVec;
 use bumpalo::Bump;
-use html5gum::{Emitter, Error, State};
+use html5gum::{Emitter, Error, Reader, State, Tokenizer};
 
-use crate::html::{DefinedLink, Document, Link, UsedLink};
+use crate::html::{
+    DefinedLink, Document, Href, Link, ParserOptions, Position, RedirectLink, UsedLink, Warning,
+};
 use crate::paragraph::ParagraphWalker;
+use crate::urls::{is_external_link, is_known_scheme, url_scheme};
+
+/// Whether `href` contains template syntax or markup, which means that a template variable was
+/// not rendered, or that the template produced broken HTML.
+fn looks_like_template(href: &str) -> bool {
+    ["{{", "{%", "<%", "<", ">"]
+        .iter()
+        .any(|needle| href.contains(needle))
+}
+
+/// The URLs of the candidates of a `srcset` attribute.
+///
+/// https://html.spec.whatwg.org/multipage/images.html#srcset-attribute
+fn srcset_urls(srcset: &str) -> impl Iterator<Item = &str> {
+    srcset
+        .split(',')
+        .filter_map(|candidate| candidate.split_whitespace().next())
+        .filter(|value| !value.is_empty())
+}
+
+/// Whether `value` of a `target` or `formtarget` attribute is a keyword like `_blank` rather than
+/// the name of a browsing context. Neither is a URL, but keywords are common enough to be skipped
+/// when such an attribute is passed to `--link-attribute`.
+///
+/// https://html.spec.whatwg.org/multipage/document-sequences.html#valid-navigable-target-name-or-keyword
+fn is_browsing_context_keyword(attribute: &[u8], value: &[u8]) -> bool {
+    matches!(attribute, b"target" | b"formtarget")
+        && [&b"_blank"[..], b"_self", b"_parent", b"_top"]
+            .iter()
+            .any(|keyword| value.trim_ascii().eq_ignore_ascii_case(keyword))
+}
 
 #[inline]
 fn is_paragraph_tag(tag: &[u8]) -> bool {
     tag == b"p" || tag == b"li" || tag == b"dt" || tag == b"dd"
 }
 
+/// Whether `href` is an internal link written without a file extension, like `/about` or
+/// `guide/`, which only works on hosts that serve clean URLs.
+fn lacks_html_extension(href: &str) -> bool {
+    if is_external_link(href.as_bytes()) {
+        return false;
+    }
+
+    let path = &href[..href.find(&['?', '#'][..]).unwrap_or(href.len())];
+    if path.is_empty() {
+        // links within the current page
+        return false;
+    }
+
+    let file_name = &path[path.rfind('/').map_or(0, |i| i + 1)..];
+    Path::new(file_name).extension().is_none()
+}
+
+#[inline]
+/// Elements that never have an end tag or children.
+fn is_void_tag(tag: &[u8]) -> bool {
+    matches!(
+        tag,
+        b"area"
+            | b"base"
+            | b"br"
+            | b"col"
+            | b"embed"
+            | b"hr"
+            | b"img"
+            | b"input"
+            | b"link"
+            | b"meta"
+            | b"source"
+            | b"track"
+            | b"wbr"
+    )
+}
+
+/// Elements whose end tag may be omitted, so that they end when their parent ends.
+fn has_optional_end_tag(tag: &[u8]) -> bool {
+    matches!(
+        tag,
+        b"p" | b"li"
+            | b"dt"
+            | b"dd"
+            | b"option"
+            | b"optgroup"
+            | b"thead"
+            | b"tbody"
+            | b"tfoot"
+            | b"tr"
+            | b"td"
+            | b"th"
+    )
+}
+
+/// Whether the start tag `start` ends the open element `open` whose end tag was omitted, such as
+/// `<li>` ending the previous `<li>`.
+fn is_closed_by_start_tag(open: &[u8], start: &[u8]) -> bool {
+    match open {
+        b"p" => matches!(
+            start,
+            b"address"
+                | b"article"
+                | b"aside"
+                | b"blockquote"
+                | b"details"
+                | b"div"
+                | b"dl"
+                | b"fieldset"
+                | b"figcaption"
+                | b"figure"
+                | b"footer"
+                | b"form"
+                | b"h1"
+                | b"h2"
+                | b"h3"
+                | b"h4"
+                | b"h5"
+                | b"h6"
+                | b"header"
+                | b"hgroup"
+                | b"hr"
+                | b"main"
+                | b"menu"
+                | b"nav"
+                | b"ol"
+                | b"p"
+                | b"pre"
+                | b"section"
+                | b"table"
+                | b"ul"
+        ),
+        b"li" => start == b"li",
+        b"dt" | b"dd" => matches!(start, b"dt" | b"dd"),
+        b"option" => matches!(start, b"option" | b"optgroup"),
+        b"optgroup" => start == b"optgroup",
+        b"thead" | b"tbody" | b"tfoot" => matches!(start, b"tbody" | b"tfoot"),
+        b"tr" => matches!(start, b"tr" | b"tbody" | b"tfoot"),
+        b"td" | b"th" => matches!(start, b"td" | b"th" | b"tr" | b"tbody" | b"tfoot"),
+        _ => false,
+    }
+}
+
+fn is_heading_tag(tag: &[u8]) -> bool {
+    matches!(tag, b"h1" | b"h2" | b"h3" | b"h4" | b"h5" | b"h6")
+}
+
+/// Tokenizer errors after which we may have extracted the wrong links, or none at all. Most other
+/// errors are about doctypes or character references, and are not relevant for link checking.
+fn is_structural_error(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::EofInTag
+            | Error::EofInComment
+            | Error::NestedComment
+            | Error::IncorrectlyClosedComment
+            | Error::EndTagWithAttributes
+            | Error::MissingAttributeValue
+            | Error::MissingWhitespaceBetweenAttributes
+            | Error::UnexpectedCharacterInAttributeName
+            | Error::UnexpectedCharacterInUnquotedAttributeValue
+            | Error::UnexpectedEqualsSignBeforeAttributeName
+    )
+}
+
 #[inline]
 fn try_normalize_href_value(input: &str) -> &str {
     input.trim()
 }
 
+/// The "directory" part of a base URL like `<object codebase>` or `xml:base`. Like for any other
+/// URL, relative URLs replace everything after the last slash.
+fn base_directory(base: &str) -> &str {
+    let base = &base[..base.find(&['?', '#'][..]).unwrap_or(base.len())];
+    &base[..base.rfind('/').map_or(0, |i| i + 1)]
+}
+
+/// Whether `value` is resolved against a base URL, as opposed to replacing it.
+fn is_relative_to_base(value: &str) -> bool {
+    !value.starts_with('/') && !is_external_link(value.as_bytes())
+}
+
+/// Extract the URL from the `content` of `<meta http-equiv=refresh>`, such as `0; url=/foo`.
+///
+/// https://html.spec.whatwg.org/multipage/semantics.html#attr-meta-http-equiv-refresh
+fn parse_meta_refresh(content: &str) -> Option<&str> {
+    let content = content.trim_start();
+    let rest = content.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+    let rest = rest.trim_start();
+    let rest = rest.strip_prefix(&[';', ','][..])?.trim_start();
+
+    let rest = match rest.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("url") => {
+            match rest[3..].trim_start().strip_prefix('=') {
+                Some(rest) => rest.trim_start(),
+                None => rest,
+            }
+        }
+        _ => rest,
+    };
+
+    let url = match rest.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let rest = &rest[1..];
+            &rest[..rest.find(quote).unwrap_or(rest.len())]
+        }
+        _ => rest.trim_end(),
+    };
+
+    if url.is_empty() {
+        None
+    } else {
+        Some(url)
+    }
+}
+
+/// Values of the `kind` attribute of `<track>`.
+///
+/// https://html.spec.whatwg.org/multipage/media.html#attr-track-kind
+static TRACK_KINDS: &[&str] = &[
+    "subtitles",
+    "captions",
+    "descriptions",
+    "chapters",
+    "metadata",
+];
+
+/// The attributes of a `<track>` that `check_tracks` looks at.
+#[derive(Default)]
+struct Track {
+    kind: Option<String>,
+    srclang: Option<String>,
+    label: Option<String>,
+    default: bool,
+}
+
+impl Track {
+    /// The state of `kind`, which is subtitles if it is missing and metadata if it is invalid.
+    fn kind(&self) -> &'static str {
+        match self.kind {
+            None => "subtitles",
+            Some(ref kind) => TRACK_KINDS
+                .iter()
+                .find(|known| known.eq_ignore_ascii_case(kind.trim()))
+                .copied()
+                .unwrap_or("metadata"),
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct ParserBuffers {
     current_tag_name: Vec<u8>,
     current_attribute_name: Vec<u8>,
     current_attribute_value: Vec<u8>,
+    /// Where the current attribute starts, only tracked with `html_tab_size`.
+    current_attribute_position: Option<Position>,
     last_start_tag: Vec<u8>,
+    /// `<object codebase>` is the base URL for `data` and `classid`, and may appear after them. We
+    /// defer extracting those links until the end of the tag.
+    object_codebase: String,
+    object_urls: Vec<(String, &'static str, Option<Position>)>,
+    /// Whether the current tag has a `srcset` with width descriptors, and whether it has `sizes`.
+    /// Only tracked for `check_srcset_sizes`.
+    srcset_has_widths: bool,
+    has_sizes: bool,
+    /// `<source srcset>` is reported together with the `media` condition of the tag, which may
+    /// appear after it. We defer extracting links until the end of the tag.
+    source_srcset: String,
+    source_srcset_position: Option<Position>,
+    source_media: String,
+    /// `<meta content>` is only a link depending on the other attributes of the tag, which may
+    /// appear after it. We defer extracting links until the end of the tag.
+    meta_content: String,
+    meta_content_position: Option<Position>,
+    meta_element: Option<&'static str>,
+    current_tag_has_id: bool,
+    /// Whether we are in a heading without `id`, and its text content so far. Only tracked for
+    /// `heading_anchors`.
+    in_heading: bool,
+    heading_text: Vec<u8>,
+    /// The heading slugs of the current document, to disambiguate repeated headings (`#foo`,
+    /// `#foo-1`, ...), see `AnchorStyle::disambiguate`.
+    heading_slugs: HashMap<String, usize>,
+    /// In XML documents, `xml:base` applies to the element it is defined on and all of its
+    /// descendants, and may appear after the links of the element. We defer extracting links
+    /// until the end of the tag, and keep a stack of the bases in scope together with the depth of
+    /// the element that defined them.
+    xml_base: Option<String>,
+    xml_base_stack: Vec<(usize, String)>,
+    xml_urls: Vec<(String, &'static str, Option<Position>)>,
+    element_depth: usize,
+    current_tag_self_closing: bool,
+    /// Whether the current tag is `<a download>`, and where its links start in `link_buf`. The
+    /// `download` attribute may appear after `href`, so we relabel the links at the end of the tag.
+    current_tag_has_download: bool,
+    current_tag_links_start: usize,
+    /// Contents of the current comment. Only tracked for `parse_comments`.
+    comment: Vec<u8>,
+    /// Whether we are in an `<a href>`, its href and its text content so far, and the hrefs and
+    /// texts of the previous links of the document. Only tracked for `warn_duplicate_links`.
+    in_link: bool,
+    link_href: String,
+    link_text: Vec<u8>,
+    link_hrefs: HashMap<String, usize>,
+    link_texts: HashMap<String, String>,
+    /// The open elements starting at the element matching `ignore_selectors` we are in, and
+    /// whether the current tag matches by class or id.
+    ignored_elements: Vec<Vec<u8>>,
+    current_tag_ignored: bool,
+    /// The attributes of the current `<track>`, and the open `<video>` and `<audio>` elements with
+    /// their tracks so far. Only tracked for `check_tracks`.
+    track: Track,
+    media_tracks: Vec<(&'static str, Vec<Track>)>,
 }
 
 impl ParserBuffers {
@@ -29,7 +332,100 @@ impl ParserBuffers {
         self.current_tag_name.clear();
         self.current_attribute_name.clear();
         self.current_attribute_value.clear();
+        self.current_attribute_position = None;
         self.last_start_tag.clear();
+        self.object_codebase.clear();
+        self.object_urls.clear();
+        self.srcset_has_widths = false;
+        self.has_sizes = false;
+        self.source_srcset.clear();
+        self.source_srcset_position = None;
+        self.source_media.clear();
+        self.meta_content.clear();
+        self.meta_content_position = None;
+        self.meta_element = None;
+        self.current_tag_has_id = false;
+        self.in_heading = false;
+        self.heading_text.clear();
+        self.heading_slugs.clear();
+        self.xml_base = None;
+        self.xml_base_stack.clear();
+        self.xml_urls.clear();
+        self.element_depth = 0;
+        self.current_tag_self_closing = false;
+        self.current_tag_has_download = false;
+        self.current_tag_links_start = 0;
+        self.comment.clear();
+        self.in_link = false;
+        self.link_href.clear();
+        self.link_text.clear();
+        self.link_hrefs.clear();
+        self.link_texts.clear();
+        self.ignored_elements.clear();
+        self.current_tag_ignored = false;
+        self.track = Track::default();
+        self.media_tracks.clear();
+    }
+}
+
+/// Counts the bytes, lines and columns the tokenizer has consumed, so that `HyperlinkEmitter` can
+/// record where attributes start. `position.column` is the number of columns consumed on the
+/// current line. Nothing is counted without a tab size.
+pub struct PositionReader<'p, R> {
+    pub reader: R,
+    pub position: &'p Cell<Position>,
+    pub tab_size: Option<usize>,
+}
+
+fn advance_position(position: &Cell<Position>, tab_size: usize, bytes: &[u8]) {
+    let mut new_position = position.get();
+    new_position.offset += bytes.len();
+    for &byte in bytes {
+        match byte {
+            b'\n' => {
+                new_position.line += 1;
+                new_position.column = 0;
+            }
+            b'\t' if tab_size > 0 => {
+                new_position.column = (new_position.column / tab_size + 1) * tab_size;
+            }
+            // UTF-8 continuation bytes do not start a new character.
+            0x80..=0xbf => {}
+            _ => new_position.column += 1,
+        }
+    }
+    position.set(new_position);
+}
+
+impl<R: Reader> Reader for PositionReader<'_, R> {
+    type Error = R::Error;
+
+    fn read_byte(&mut self) -> Result<Option<u8>, Self::Error> {
+        let byte = self.reader.read_byte()?;
+        if let (Some(tab_size), Some(byte)) = (self.tab_size, byte) {
+            advance_position(self.position, tab_size, &[byte]);
+        }
+        Ok(byte)
+    }
+
+    fn try_read_string(&mut self, s: &[u8], case_sensitive: bool) -> Result<bool, Self::Error> {
+        let matched = self.reader.try_read_string(s, case_sensitive)?;
+        if let (Some(tab_size), true) = (self.tab_size, matched) {
+            advance_position(self.position, tab_size, s);
+        }
+        Ok(matched)
+    }
+
+    fn read_until<'b>(
+        &'b mut self,
+        needle: &[u8],
+        char_buf: &'b mut [u8; 4],
+    ) -> Result<Option<&'b [u8]>, Self::Error> {
+        let bytes = self.reader.read_until(needle, char_buf)?;
+        if let (Some(tab_size), Some(bytes)) = (self.tab_size, bytes) {
+            advance_position(self.position, tab_size, bytes);
+        }
+        Ok(bytes)
     }
 }
 
@@ -42,7 +438,9 @@ pub struct HyperlinkEmitter<'a, 'l, 'd, P: ParagraphWalker> {
     pub last_paragraph_i: usize,
     pub buffers: &'d mut ParserBuffers,
     pub current_tag_is_closing: bool,
-    pub check_anchors: bool,
+    pub options: &'d ParserOptions,
+    /// What `PositionReader` has consumed so far, only with `html_tab_size`.
+    pub position: Option<&'d Cell<Position>>,
 }
 
 impl<'a, 'l, P> HyperlinkEmitter<'a, 'l, '_, P>
@@ -50,15 +448,57 @@ where
     'a: 'l,
     P: ParagraphWalker,
 {
-    fn extract_used_link(&mut self) {
+    fn extract_used_link(&mut self, element: &'static str) {
         let value = try_normalize_href_value(
             std::str::from_utf8(&self.buffers.current_attribute_value).unwrap(),
         );
 
+        if self.options.warn_unrendered_templates && looks_like_template(value) {
+            // not push_warning, value still borrows from self.buffers
+            self.link_buf.push(Link::Warns(Warning {
+                path: self.document.path.clone(),
+                message: format!("href looks like unrendered template: {value}"),
+            }));
+        }
+
+        if self.options.report_mixed_separators && value.contains('\\') {
+            self.link_buf.push(Link::Warns(Warning {
+                path: self.document.path.clone(),
+                message: format!("backslash in href: {value}"),
+            }));
+        }
+
+        if self.options.strict_scheme {
+            if let Some(scheme) = url_scheme(value.as_bytes()).filter(|x| !is_known_scheme(x)) {
+                self.link_buf.push(Link::Warns(Warning {
+                    path: self.document.path.clone(),
+                    message: format!(
+                        "unknown URL scheme {:?}",
+                        std::str::from_utf8(scheme).unwrap()
+                    ),
+                }));
+            }
+        }
+
+        if self.document.is_xml {
+            self.buffers.xml_urls.push((
+                value.to_owned(),
+                element,
+                self.buffers.current_attribute_position,
+            ));
+            return;
+        }
+
         self.link_buf.push(Link::Uses(UsedLink {
-            href: self.document.join(self.arena, self.check_anchors, value),
+            href: self.document.join(self.arena, self.options, value),
+            raw_href: self.arena.alloc_str(value),
             path: self.document.path.clone(),
             paragraph: None,
+            element,
+            media: None,
+            referrer: None,
+            absolute: self.options.is_absolute_link(value),
+            position: self.buffers.current_attribute_position,
         }));
     }
 
@@ -67,47 +507,676 @@ where
             std::str::from_utf8(&self.buffers.current_attribute_value).unwrap(),
         );
 
-        // https://html.spec.whatwg.org/multipage/images.html#srcset-attribute
-        for value in value
-            .split(',')
-            .filter_map(|candidate: &str| candidate.split_whitespace().next())
-            .filter(|value| !value.is_empty())
-        {
+        if self.options.check_srcset_sizes {
+            self.buffers.srcset_has_widths = value.split(',').any(|candidate| {
+                candidate
+                    .split_whitespace()
+                    .nth(1)
+                    .is_some_and(|descriptor| descriptor.ends_with('w'))
+            });
+        }
+
+        for value in srcset_urls(value) {
             self.link_buf.push(Link::Uses(UsedLink {
-                href: self.document.join(self.arena, self.check_anchors, value),
+                href: self.document.join(self.arena, self.options, value),
+                raw_href: self.arena.alloc_str(value),
                 path: self.document.path.clone(),
                 paragraph: None,
+                element: "<img srcset>",
+                media: None,
+                referrer: None,
+                absolute: self.options.is_absolute_link(value),
+                position: self.buffers.current_attribute_position,
             }));
         }
     }
 
-    fn extract_anchor_def(&mut self) {
-        if self.check_anchors {
+    /// `ping` holds URLs separated by whitespace, which are requested when following the link.
+    fn extract_used_link_ping(&mut self, element: &'static str) {
+        let value = std::str::from_utf8(&self.buffers.current_attribute_value).unwrap();
+        for value in value.split_ascii_whitespace() {
+            let value = try_normalize_href_value(value);
+            self.link_buf.push(Link::Uses(UsedLink {
+                href: self.document.join(self.arena, self.options, value),
+                raw_href: self.arena.alloc_str(value),
+                path: self.document.path.clone(),
+                paragraph: None,
+                element,
+                media: None,
+                referrer: None,
+                absolute: self.options.is_absolute_link(value),
+                position: self.buffers.current_attribute_position,
+            }));
+        }
+    }
+
+    /// `aria-describedby`, `aria-labelledby` and `<label for>` hold ids of the same document
+    /// separated by whitespace, which are checked like links to anchors. `referrer` is what the
+    /// report says references a missing id.
+    fn extract_id_references(&mut self, element: &'static str, referrer: &'static str) {
+        let value = std::str::from_utf8(&self.buffers.current_attribute_value).unwrap();
+        for id in value.split_ascii_whitespace() {
+            let mut href = BumpString::new_in(self.arena);
+            href.push('#');
+            href.push_str(id);
+            self.link_buf.push(Link::Uses(UsedLink {
+                href: self.document.join(self.arena, self.options, &href),
+                raw_href: href.into_bump_str(),
+                path: self.document.path.clone(),
+                paragraph: None,
+                element,
+                media: None,
+                referrer: Some(referrer),
+                absolute: false,
+                position: self.buffers.current_attribute_position,
+            }));
+        }
+    }
+
+    fn flush_source_srcset(&mut self) {
+        let media = self.buffers.source_media.trim();
+        let media = (!media.is_empty()).then(|| &*self.arena.alloc_str(media));
+
+        let srcset = try_normalize_href_value(&self.buffers.source_srcset);
+        for value in srcset_urls(srcset) {
+            self.link_buf.push(Link::Uses(UsedLink {
+                href: self.document.join(self.arena, self.options, value),
+                raw_href: self.arena.alloc_str(value),
+                path: self.document.path.clone(),
+                paragraph: None,
+                element: "<source srcset>",
+                media,
+                referrer: None,
+                absolute: self.options.is_absolute_link(value),
+                position: self.buffers.source_srcset_position,
+            }));
+        }
+
+        self.buffers.source_srcset.clear();
+        self.buffers.source_media.clear();
+    }
+
+    fn defer_object_url(&mut self, element: &'static str) {
+        let value = std::str::from_utf8(&self.buffers.current_attribute_value).unwrap();
+        self.buffers.object_urls.push((
+            value.to_owned(),
+            element,
+            self.buffers.current_attribute_position,
+        ));
+    }
+
+    fn extract_object_codebase(&mut self) {
+        let value = std::str::from_utf8(&self.buffers.current_attribute_value).unwrap();
+        self.buffers.object_codebase.clear();
+        self.buffers.object_codebase.push_str(value);
+    }
+
+    fn flush_object_urls(&mut self) {
+        let codebase = base_directory(try_normalize_href_value(&self.buffers.object_codebase));
+
+        for (value, element, position) in self.buffers.object_urls.drain(..) {
+            let value = try_normalize_href_value(&value);
             let mut href = BumpString::new_in(self.arena);
+            if is_relative_to_base(value) {
+                href.push_str(codebase);
+            }
+            href.push_str(value);
+
+            self.link_buf.push(Link::Uses(UsedLink {
+                href: self.document.join(self.arena, self.options, &href),
+                raw_href: self.arena.alloc_str(value),
+                path: self.document.path.clone(),
+                paragraph: None,
+                element,
+                media: None,
+                referrer: None,
+                absolute: self.options.is_absolute_link(&href),
+                position,
+            }));
+        }
+
+        self.buffers.object_codebase.clear();
+    }
+
+    fn extract_xml_base(&mut self) {
+        let value = std::str::from_utf8(&self.buffers.current_attribute_value).unwrap();
+        self.buffers.xml_base = Some(value.to_owned());
+    }
+
+    /// Extract the links of the current tag in an XML document, and keep track of the `xml:base`
+    /// in scope for its children.
+    fn flush_xml_urls(&mut self) {
+        let buffers = &mut *self.buffers;
+
+        if self.current_tag_is_closing {
+            if matches!(buffers.xml_base_stack.last(), Some((depth, _)) if *depth == buffers.element_depth)
+            {
+                buffers.xml_base_stack.pop();
+            }
+            buffers.element_depth = buffers.element_depth.saturating_sub(1);
+            return;
+        }
+
+        let parent_base = buffers
+            .xml_base_stack
+            .last()
+            .map_or("", |(_, base)| base.as_str());
+
+        // A relative xml:base is itself resolved against the xml:base of the parent element.
+        let base = buffers.xml_base.take().map(|value| {
+            let value = try_normalize_href_value(&value);
+            if is_relative_to_base(value) {
+                format!("{}{}", base_directory(parent_base), value)
+            } else {
+                value.to_owned()
+            }
+        });
+
+        let directory = base_directory(base.as_deref().unwrap_or(parent_base));
+
+        for (value, element, position) in buffers.xml_urls.drain(..) {
+            let mut href = BumpString::new_in(self.arena);
+            // Fragment-only links point into the current document, whatever its xml:base.
+            if is_relative_to_base(&value) && !value.starts_with('#') {
+                href.push_str(directory);
+            }
+            href.push_str(&value);
+
+            self.link_buf.push(Link::Uses(UsedLink {
+                href: self.document.join(self.arena, self.options, &href),
+                raw_href: self.arena.alloc_str(&value),
+                path: self.document.path.clone(),
+                paragraph: None,
+                element,
+                media: None,
+                referrer: None,
+                absolute: self.options.is_absolute_link(&href),
+                position,
+            }));
+        }
+
+        if !buffers.current_tag_self_closing {
+            buffers.element_depth += 1;
+            if let Some(base) = base {
+                buffers.xml_base_stack.push((buffers.element_depth, base));
+            }
+        }
+    }
+
+    fn flush_download_links(&mut self) {
+        for link in self.link_buf[self.buffers.current_tag_links_start..].iter_mut() {
+            if let Link::Uses(used_link) = link {
+                if used_link.element == "<a href>" {
+                    used_link.element = "<a download>";
+                }
+            }
+        }
+
+        self.buffers.current_tag_has_download = false;
+    }
+
+    fn extract_meta_element(&mut self) {
+        let value = std::str::from_utf8(&self.buffers.current_attribute_value).unwrap();
+        let value = value.trim();
+
+        let element = match self.buffers.current_attribute_name.as_slice() {
+            b"http-equiv" if value.eq_ignore_ascii_case("refresh") => "<meta http-equiv=refresh>",
+            b"property" if value == "og:image" => "<meta property=og:image>",
+            b"name" if value == "twitter:image" => "<meta name=twitter:image>",
+            _ => return,
+        };
+
+        self.buffers.meta_element = Some(element);
+    }
+
+    fn flush_meta(&mut self) {
+        if let Some(element) = self.buffers.meta_element.take() {
+            let value = if element == "<meta http-equiv=refresh>" {
+                parse_meta_refresh(&self.buffers.meta_content)
+            } else {
+                Some(try_normalize_href_value(&self.buffers.meta_content)).filter(|x| !x.is_empty())
+            };
+
+            if let Some(value) = value {
+                let href = self.document.join(self.arena, self.options, value);
+                let target = href.without_anchor();
+                self.link_buf.push(Link::Uses(UsedLink {
+                    href,
+                    raw_href: self.arena.alloc_str(value),
+                    path: self.document.path.clone(),
+                    paragraph: None,
+                    element,
+                    media: None,
+                    referrer: None,
+                    absolute: self.options.is_absolute_link(value),
+                    position: self.buffers.meta_content_position,
+                }));
+
+                if self.options.follow_meta_refresh_chain && element == "<meta http-equiv=refresh>"
+                {
+                    let from = if self.options.strict_trailing_slash {
+                        self.document.href_with_trailing_slash()
+                    } else {
+                        self.document.href().0.into()
+                    };
+                    self.link_buf.push(Link::Redirects(RedirectLink {
+                        from: Href(self.arena.alloc_str(&from)),
+                        to: target,
+                    }));
+                }
+            }
+        }
+
+        self.buffers.meta_content.clear();
+    }
+
+    /// Conditional comments like `<!--[if IE]><link href="ie.css"><![endif]-->` contain markup
+    /// that old browsers render, so we tokenize the comment like a document of its own.
+    fn flush_comment(&mut self) {
+        if !self.buffers.ignored_elements.is_empty() {
+            return;
+        }
+
+        let comment = std::mem::take(&mut self.buffers.comment);
+        let mut buffers = ParserBuffers::default();
+        let emitter = HyperlinkEmitter {
+            paragraph_walker: P::new(),
+            arena: self.arena,
+            document: self.document,
+            link_buf: &mut *self.link_buf,
+            in_paragraph: false,
+            last_paragraph_i: 0,
+            buffers: &mut buffers,
+            current_tag_is_closing: false,
+            options: self.options,
+            // Positions within the comment are not those in the document.
+            position: None,
+        };
+        for _ in Tokenizer::new_with_emitter(comment.as_slice(), emitter) {}
+        self.buffers.comment = comment;
+    }
+
+    fn push_warning(&mut self, message: String) {
+        self.link_buf.push(Link::Warns(Warning {
+            path: self.document.path.clone(),
+            message,
+        }));
+    }
+
+    fn check_srcset_sizes(&mut self) {
+        if self.buffers.srcset_has_widths && !self.buffers.has_sizes {
+            self.push_warning(
+                "srcset with width descriptors but without sizes attribute".to_owned(),
+            );
+        }
+
+        self.buffers.srcset_has_widths = false;
+        self.buffers.has_sizes = false;
+    }
+
+    fn check_tracks(&mut self) {
+        let track = mem::take(&mut self.buffers.track);
+        let media = match self.buffers.current_tag_name.as_slice() {
+            b"video" => "<video>",
+            b"audio" => "<audio>",
+            b"track" if !self.current_tag_is_closing => {
+                if let Some(ref kind) = track.kind {
+                    if track.kind() == "metadata" && !kind.trim().eq_ignore_ascii_case("metadata") {
+                        self.push_warning(format!("<track> has unknown kind {kind:?}"));
+                    }
+                }
+                if track.kind() == "subtitles" && track.srclang.is_none() {
+                    self.push_warning("<track kind=subtitles> without srclang".to_owned());
+                }
+                if let Some((_, tracks)) = self.buffers.media_tracks.last_mut() {
+                    tracks.push(track);
+                }
+                return;
+            }
+            _ => return,
+        };
+
+        if !self.current_tag_is_closing {
+            if !self.buffers.current_tag_self_closing {
+                self.buffers.media_tracks.push((media, Vec::new()));
+            }
+            return;
+        }
+
+        let tracks = match self.buffers.media_tracks.pop() {
+            Some((_, tracks)) => tracks,
+            None => return,
+        };
+
+        // Subtitles and captions are chosen from together, any number of metadata tracks may be
+        // enabled.
+        for kinds in [
+            &["subtitles", "captions"][..],
+            &["descriptions"],
+            &["chapters"],
+        ] {
+            let defaults = tracks
+                .iter()
+                .filter(|track| track.default && kinds.contains(&track.kind()))
+                .count();
+            if defaults > 1 {
+                self.push_warning(format!(
+                    "{media} has {defaults} default {} tracks, at most one is allowed",
+                    kinds.join(" or ")
+                ));
+            }
+        }
+
+        let mut seen = BTreeSet::new();
+        for track in &tracks {
+            let srclang = track.srclang.as_deref().map(str::to_ascii_lowercase);
+            if !seen.insert((track.kind(), srclang, track.label.as_deref())) {
+                self.push_warning(format!(
+                    "{media} has multiple {} tracks with the same srclang and label",
+                    track.kind()
+                ));
+            }
+        }
+    }
+
+    fn check_html_extension(&mut self) {
+        if self.options.require_html_extension {
             let value = try_normalize_href_value(
                 std::str::from_utf8(&self.buffers.current_attribute_value).unwrap(),
             );
+
+            if lacks_html_extension(value) {
+                let message = format!("link {value} has no .html extension");
+                self.push_warning(message);
+            }
+        }
+    }
+
+    fn extract_anchor_def(&mut self) {
+        let value = try_normalize_href_value(
+            std::str::from_utf8(&self.buffers.current_attribute_value).unwrap(),
+        );
+
+        // An empty id cannot be linked to, as #-links point to the top of the page instead.
+        if value.is_empty() {
+            if self.options.report_empty_anchors {
+                let attribute = String::from_utf8_lossy(&self.buffers.current_attribute_name);
+                self.push_warning(format!("empty {attribute} attribute"));
+            }
+            return;
+        }
+
+        if self.options.check_anchors {
+            let mut href = BumpString::new_in(self.arena);
             href.push('#');
             href.push_str(value);
 
+            let unexpected_element = self.unexpected_anchor_element();
+            let raw_anchor = if self.options.warn_anchor_encoding_mismatch {
+                Some(&*self.arena.alloc_str(value))
+            } else {
+                None
+            };
             self.link_buf.push(Link::Defines(DefinedLink {
-                href: self.document.join(self.arena, self.check_anchors, &href),
+                href: self.document.join(self.arena, self.options, &href),
+                unexpected_element,
+                raw_anchor,
             }));
         }
     }
 
+    /// The current tag, if `--anchor-element-policy` does not expect anchors on it.
+    fn unexpected_anchor_element(&self) -> Option<(Arc<PathBuf>, &'a str)> {
+        let policy = self.options.anchor_element_policy.as_ref()?;
+        if policy.allows(&self.buffers.current_tag_name) {
+            return None;
+        }
+
+        let tag = std::str::from_utf8(&self.buffers.current_tag_name).unwrap();
+        Some((self.document.path.clone(), self.arena.alloc_str(tag)))
+    }
+
+    fn finish_heading(&mut self) {
+        if !self.buffers.in_heading {
+            return;
+        }
+
+        let style = self.options.anchor_style;
+        let slug = style.slug(&String::from_utf8_lossy(&self.buffers.heading_text));
+        self.buffers.in_heading = false;
+        self.buffers.heading_text.clear();
+        let slug = style.disambiguate(slug, &mut self.buffers.heading_slugs);
+
+        let mut href = BumpString::new_in(self.arena);
+        href.push('#');
+        href.push_str(&slug);
+
+        let unexpected_element = self.unexpected_anchor_element();
+        self.link_buf.push(Link::Defines(DefinedLink {
+            href: self.document.join(self.arena, self.options, &href),
+            unexpected_element,
+            raw_anchor: None,
+        }));
+    }
+
+    fn start_link(&mut self) {
+        self.finish_link();
+        let value = try_normalize_href_value(
+            std::str::from_utf8(&self.buffers.current_attribute_value).unwrap(),
+        );
+        self.buffers.link_href.push_str(value);
+        self.buffers.in_link = true;
+    }
+
+    fn finish_link(&mut self) {
+        if !self.buffers.in_link {
+            return;
+        }
+
+        self.buffers.in_link = false;
+        let href = std::mem::take(&mut self.buffers.link_href);
+        let text = String::from_utf8_lossy(&self.buffers.link_text)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.buffers.link_text.clear();
+
+        let count = self.buffers.link_hrefs.entry(href.clone()).or_insert(0);
+        *count += 1;
+        if *count == 2 {
+            self.push_warning(format!("duplicate link to {href}"));
+        }
+
+        if text.is_empty() {
+            return;
+        }
+
+        match self.buffers.link_texts.get(&text) {
+            Some(first_href) if *first_href != href => {
+                let message =
+                    format!("links with the same text {text:?} point to {first_href} and {href}");
+                self.push_warning(message);
+            }
+            Some(_) => (),
+            None => {
+                self.buffers.link_texts.insert(text, href);
+            }
+        }
+    }
+
+    /// Drop the links of the current tag if it or one of its ancestors matches
+    /// `ignore_selectors`. Anchors are still defined.
+    fn apply_ignore_selectors(&mut self) {
+        let buffers = &mut *self.buffers;
+        let tag = &buffers.current_tag_name;
+        let open = &mut buffers.ignored_elements;
+
+        if self.current_tag_is_closing {
+            if let Some(i) = open.iter().rposition(|name| name == tag) {
+                open.truncate(i);
+            } else if !self.document.is_xml && open.first().is_some_and(|x| has_optional_end_tag(x))
+            {
+                // The end tag of an ancestor also ends an element whose end tag was omitted.
+                open.clear();
+            }
+            return;
+        }
+
+        if !self.document.is_xml {
+            while open
+                .last()
+                .is_some_and(|name| is_closed_by_start_tag(name, tag))
+            {
+                open.pop();
+            }
+        }
+
+        let is_match = std::mem::take(&mut buffers.current_tag_ignored)
+            || self
+                .options
+                .ignore_selectors
+                .iter()
+                .any(|s| s.matches_tag(tag));
+        if open.is_empty() && !is_match {
+            return;
+        }
+        if !buffers.current_tag_self_closing && !is_void_tag(tag) {
+            open.push(tag.clone());
+        }
+
+        if tag == b"a" {
+            buffers.in_link = false;
+            buffers.link_href.clear();
+        }
+
+        let start = buffers.current_tag_links_start;
+        let defined: Vec<_> = self
+            .link_buf
+            .drain(start..)
+            .filter(|link| matches!(link, Link::Defines(_)))
+            .collect();
+        self.link_buf.extend(defined);
+    }
+
     fn flush_old_attribute(&mut self) {
-        match (
-            self.buffers.current_tag_name.as_slice(),
-            self.buffers.current_attribute_name.as_slice(),
-        ) {
-            (b"link" | b"area" | b"a", b"href") => self.extract_used_link(),
-            (b"a", b"name") => self.extract_anchor_def(),
-            (b"img" | b"script" | b"iframe", b"src") => self.extract_used_link(),
-            (b"img", b"srcset") => self.extract_used_link_srcset(),
-            (b"object", b"data") => self.extract_used_link(),
-            (_, b"id") => self.extract_anchor_def(),
-            _ => (),
+        let tag = self.buffers.current_tag_name.as_slice();
+        let attribute = self.buffers.current_attribute_name.as_slice();
+
+        if matches!(attribute, b"class" | b"id") {
+            let value = self.buffers.current_attribute_value.as_slice();
+            if self
+                .options
+                .ignore_selectors
+                .iter()
+                .any(|selector| selector.matches_attribute(attribute, value))
+            {
+                self.buffers.current_tag_ignored = true;
+            }
+        }
+
+        let link_attribute = self.options.link_attributes.iter().find(|link_attribute| {
+            link_attribute.tag.as_bytes() == tag && link_attribute.attribute.as_bytes() == attribute
+        });
+
+        if let Some(link_attribute) = link_attribute {
+            if !is_browsing_context_keyword(attribute, &self.buffers.current_attribute_value) {
+                self.extract_used_link(link_attribute.element);
+            }
+        } else {
+            match (tag, attribute) {
+                (b"a", b"name") => self.extract_anchor_def(),
+                (_, b"xml:base") if self.document.is_xml => self.extract_xml_base(),
+                (_, b"id") => {
+                    self.buffers.current_tag_has_id = true;
+                    self.extract_anchor_def();
+                }
+                (_, attribute)
+                    if self
+                        .options
+                        .anchor_attributes
+                        .iter()
+                        .any(|name| name.as_bytes() == attribute) =>
+                {
+                    self.buffers.current_tag_has_id = true;
+                    self.extract_anchor_def();
+                }
+                (_, b"aria-describedby") if self.options.check_aria_references => {
+                    self.extract_id_references("<* aria-describedby>", "aria-describedby");
+                }
+                (_, b"aria-labelledby") if self.options.check_aria_references => {
+                    self.extract_id_references("<* aria-labelledby>", "aria-labelledby");
+                }
+                (b"label", b"for") if self.options.check_label_for => {
+                    self.extract_id_references("<label for>", "label");
+                }
+                (b"track", b"kind" | b"srclang" | b"label" | b"default")
+                    if self.options.check_tracks =>
+                {
+                    let value =
+                        String::from_utf8_lossy(&self.buffers.current_attribute_value).into_owned();
+                    let track = &mut self.buffers.track;
+                    match attribute {
+                        b"kind" => track.kind = Some(value),
+                        b"srclang" => track.srclang = Some(value),
+                        b"label" => track.label = Some(value),
+                        _ => track.default = true,
+                    }
+                }
+                _ if self.options.no_default_tags => (),
+                (b"a", b"href") => {
+                    self.check_html_extension();
+                    if self.options.warn_duplicate_links {
+                        self.start_link();
+                    }
+                    self.extract_used_link("<a href>");
+                }
+                (b"a", b"ping") => self.extract_used_link_ping("<a ping>"),
+                (b"area", b"ping") => self.extract_used_link_ping("<area ping>"),
+                (b"link", b"href") => self.extract_used_link("<link href>"),
+                (b"area", b"href") => {
+                    self.check_html_extension();
+                    self.extract_used_link("<area href>");
+                }
+                (b"a", b"xlink:href") => self.extract_used_link("<a xlink:href>"),
+                (b"a", b"download") => self.buffers.current_tag_has_download = true,
+                (b"img", b"src") => self.extract_used_link("<img src>"),
+                (b"script", b"src") => self.extract_used_link("<script src>"),
+                (b"iframe", b"src") => self.extract_used_link("<iframe src>"),
+                (b"embed", b"src") => self.extract_used_link("<embed src>"),
+                (b"img", b"srcset") => self.extract_used_link_srcset(),
+                (b"img", b"sizes") if self.options.check_srcset_sizes => {
+                    self.buffers.has_sizes = true;
+                }
+                (b"source", b"srcset") => {
+                    let value = std::str::from_utf8(&self.buffers.current_attribute_value).unwrap();
+                    self.buffers.source_srcset.clear();
+                    self.buffers.source_srcset.push_str(value);
+                    self.buffers.source_srcset_position = self.buffers.current_attribute_position;
+                }
+                (b"source", b"media") => {
+                    let value = std::str::from_utf8(&self.buffers.current_attribute_value).unwrap();
+                    self.buffers.source_media.clear();
+                    self.buffers.source_media.push_str(value);
+                }
+                (b"video", b"poster") => self.extract_used_link("<video poster>"),
+                (b"track", b"src") => self.extract_used_link("<track src>"),
+                (b"meta", b"content") => {
+                    let value = std::str::from_utf8(&self.buffers.current_attribute_value).unwrap();
+                    self.buffers.meta_content.clear();
+                    self.buffers.meta_content.push_str(value);
+                    self.buffers.meta_content_position = self.buffers.current_attribute_position;
+                }
+                (b"meta", b"http-equiv" | b"property" | b"name") => self.extract_meta_element(),
+                (b"form", b"action") => self.extract_used_link("<form action>"),
+                (b"button", b"formaction") => self.extract_used_link("<button formaction>"),
+                (b"input", b"formaction") => self.extract_used_link("<input formaction>"),
+                // only image buttons have a src, so there is no need to look at type=image
+                (b"input", b"src") => self.extract_used_link("<input src>"),
+                (b"object", b"data") => self.defer_object_url("<object data>"),
+                (b"object", b"classid") => self.defer_object_url("<object classid>"),
+                (b"object", b"codebase") => self.extract_object_codebase(),
+                _ => (),
+            }
         }
 
         self.buffers.current_attribute_name.clear();
@@ -115,6 +1184,69 @@ where
     }
 }
 
+#[test]
+fn test_lacks_html_extension() {
+    for href in [
+        "/about",
+        "guide/",
+        "/",
+        "..",
+        "../",
+        "about?page=2",
+        "about#team",
+    ] {
+        assert!(lacks_html_extension(href), "{}", href);
+    }
+
+    for href in [
+        "about.html",
+        "/guide/index.htm",
+        "logo.png",
+        "#team",
+        "?page=2",
+        "",
+        "https://example.com/about",
+        "mailto:foo@example.com",
+    ] {
+        assert!(!lacks_html_extension(href), "{}", href);
+    }
+}
+
+#[test]
+fn test_looks_like_template() {
+    for href in [
+        "{{ url }}",
+        "/blog/{{post.slug}}/",
+        "{% url 'home' %}",
+        "<%= link %>",
+        "<a href=",
+        "foo>bar",
+    ] {
+        assert!(looks_like_template(href), "{}", href);
+    }
+
+    for href in ["/blog/post/", "foo{bar}.html", "?q=%7B%7B", "#{id}"] {
+        assert!(!looks_like_template(href), "{}", href);
+    }
+}
+
+#[test]
+fn test_parse_meta_refresh() {
+    assert_eq!(parse_meta_refresh("0; url=/foo"), Some("/foo"));
+    assert_eq!(parse_meta_refresh("0;URL='/foo bar'"), Some("/foo bar"));
+    assert_eq!(
+        parse_meta_refresh(" 5 , url = \"foo.html\" "),
+        Some("foo.html")
+    );
+    assert_eq!(
+        parse_meta_refresh("3.5; https://example.com"),
+        Some("https://example.com")
+    );
+    assert_eq!(parse_meta_refresh("30"), None);
+    assert_eq!(parse_meta_refresh("0; url="), None);
+    assert_eq!(parse_meta_refresh("visit /foo"), None);
+}
+
 impl<'a, 'l, P> Emitter for HyperlinkEmitter<'a, 'l, '_, P>
 where
     'a: 'l,
@@ -137,21 +1269,78 @@ where
         if !P::is_noop() && self.in_paragraph {
             self.paragraph_walker.update(c);
         }
+
+        if self.buffers.in_heading {
+            // Decode only once the heading is complete, a character may be split across reads.
+            self.buffers.heading_text.extend_from_slice(c);
+        }
+
+        if self.buffers.in_link {
+            self.buffers.link_text.extend_from_slice(c);
+        }
     }
 
     fn init_start_tag(&mut self) {
         self.buffers.current_tag_name.clear();
+        self.buffers.current_tag_self_closing = false;
+        self.buffers.current_tag_links_start = self.link_buf.len();
         self.current_tag_is_closing = false;
     }
 
     fn init_end_tag(&mut self) {
         self.buffers.current_tag_name.clear();
+        self.buffers.current_tag_self_closing = false;
         self.current_tag_is_closing = true;
     }
 
     fn emit_current_tag(&mut self) -> Option<State> {
         self.flush_old_attribute();
 
+        if self.document.is_xml {
+            self.flush_xml_urls();
+        }
+
+        if self.buffers.current_tag_has_download {
+            self.flush_download_links();
+        }
+
+        if self.buffers.current_tag_name == b"object" {
+            self.flush_object_urls();
+        } else if self.buffers.current_tag_name == b"meta" {
+            self.flush_meta();
+        } else if self.buffers.current_tag_name == b"source" {
+            self.flush_source_srcset();
+        }
+
+        if self.options.check_srcset_sizes {
+            self.check_srcset_sizes();
+        }
+
+        if self.options.check_tracks {
+            self.check_tracks();
+        }
+
+        if !self.options.ignore_selectors.is_empty() {
+            self.apply_ignore_selectors();
+        }
+
+        if self.options.heading_anchors
+            && self.options.check_anchors
+            && is_heading_tag(&self.buffers.current_tag_name)
+        {
+            if self.current_tag_is_closing {
+                self.finish_heading();
+            } else {
+                self.buffers.in_heading = !self.buffers.current_tag_has_id;
+                self.buffers.heading_text.clear();
+            }
+        }
+        self.buffers.current_tag_has_id = false;
+
+        if self.current_tag_is_closing && self.buffers.current_tag_name == b"a" {
+            self.finish_link();
+        }
+
         self.buffers.last_start_tag.clear();
 
         let is_paragraph_tag = !P::is_noop() && is_paragraph_tag(&self.buffers.current_tag_name);
@@ -174,7 +1363,7 @@ where
                         Link::Uses(ref mut x) => {
                             x.paragraph = paragraph.clone();
                         }
-                        Link::Defines(_) => (),
+                        Link::Defines(_) | Link::Warns(_) | Link::Redirects(_) => (),
                     }
                 }
                 self.in_paragraph = false;
@@ -187,6 +1376,7 @@ where
     }
 
     fn set_self_closing(&mut self) {
+        self.buffers.current_tag_self_closing = true;
         if !P::is_noop() && is_paragraph_tag(&self.buffers.current_tag_name) {
             self.in_paragraph = false;
         }
@@ -198,6 +1388,14 @@ where
 
     fn init_attribute(&mut self) {
         self.flush_old_attribute();
+        // The tokenizer has already consumed the first byte of the attribute name.
+        self.buffers.current_attribute_position = self.position.map(|position| {
+            let position = position.get();
+            Position {
+                offset: position.offset - 1,
+                ..position
+            }
+        });
     }
 
     fn push_attribute_name(&mut self, s: &[u8]) {
@@ -214,17 +1412,37 @@ where
             && self.buffers.current_tag_name == self.buffers.last_start_tag
     }
 
-    fn emit_current_comment(&mut self) {}
+    fn emit_current_comment(&mut self) {
+        if self.options.parse_comments {
+            self.flush_comment();
+        }
+    }
     fn emit_current_doctype(&mut self) {}
     fn emit_eof(&mut self) {}
-    fn emit_error(&mut self, _: Error) {}
+    fn emit_error(&mut self, error: Error) {
+        // Scripts and styles are tokenized as raw text, and we tolerate whatever junk is in there.
+        if html5gum::naive_next_state(&self.buffers.last_start_tag).is_some() {
+            return;
+        }
+
+        if is_structural_error(&error) {
+            self.push_warning(format!("HTML syntax error: {error}"));
+        }
+    }
+
     #[inline]
     fn should_emit_errors(&mut self) -> bool {
-        false
+        self.options.report_parse_errors
+    }
+    fn init_comment(&mut self) {
+        self.buffers.comment.clear();
     }
-    fn init_comment(&mut self) {}
     fn init_doctype(&mut self) {}
-    fn push_comment(&mut self, _: &[u8]) {}
+    fn push_comment(&mut self, s: &[u8]) {
+        if self.options.parse_comments {
+            self.buffers.comment.extend(s);
+        }
+    }
     fn push_doctype_name(&mut self, _: &[u8]) {}
     fn push_doctype_public_identifier(&mut self, _: &[u8]) {}
     fn push_doctype_system_identifier(&mut self, _: &[u8]) {}
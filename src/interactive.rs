@@ -0,0 +1,363 @@
+//! `--interactive`: Browse the broken links of a report in a terminal UI, grouped by file.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Error;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::report::{FileReport, Occurrences, Report, ReportedLink};
+
+/// Lines of source shown above and below a broken link.
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Filter {
+    All,
+    Links,
+    Anchors,
+}
+
+impl Filter {
+    fn next(self) -> Self {
+        match self {
+            Filter::All => Filter::Links,
+            Filter::Links => Filter::Anchors,
+            Filter::Anchors => Filter::All,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Filter::All => "links and anchors",
+            Filter::Links => "links",
+            Filter::Anchors => "anchors",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Focus {
+    Files,
+    Links,
+}
+
+/// A broken link in a file, and whether it is a broken anchor.
+type Entry<'a> = (&'a ReportedLink, &'a Occurrences, bool);
+
+fn entries(file: &FileReport, filter: Filter) -> Vec<Entry<'_>> {
+    let links = file
+        .bad_links
+        .iter()
+        .filter(|_| filter != Filter::Anchors)
+        .map(|(link, occurrences)| (link, occurrences, false));
+    let anchors = file
+        .bad_anchors
+        .iter()
+        .filter(|_| filter != Filter::Links)
+        .map(|(link, occurrences)| (link, occurrences, true));
+
+    links.chain(anchors).collect()
+}
+
+struct App<'a> {
+    report: &'a Report,
+    filter: Filter,
+    focus: Focus,
+    files: ListState,
+    links: ListState,
+    /// Contents of the files shown so far, `None` if they could not be read.
+    sources: HashMap<PathBuf, Option<String>>,
+}
+
+impl<'a> App<'a> {
+    fn new(report: &'a Report) -> Self {
+        App {
+            report,
+            filter: Filter::All,
+            focus: Focus::Files,
+            files: ListState::default().with_selected(Some(0)),
+            links: ListState::default().with_selected(Some(0)),
+            sources: HashMap::new(),
+        }
+    }
+
+    fn visible_files(&self) -> Vec<&'a FileReport> {
+        let filter = self.filter;
+        self.report
+            .files
+            .iter()
+            .filter(|file| !entries(file, filter).is_empty())
+            .collect()
+    }
+
+    fn selected_file(&self) -> Option<&'a FileReport> {
+        self.visible_files().get(self.files.selected()?).copied()
+    }
+
+    fn run(mut self, terminal: &mut DefaultTerminal) -> Result<(), Error> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Tab | KeyCode::Left | KeyCode::Right | KeyCode::Char('h' | 'l') => {
+                    self.focus = match self.focus {
+                        Focus::Files => Focus::Links,
+                        Focus::Links => Focus::Files,
+                    };
+                }
+                KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+                KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+                KeyCode::Char('f') => {
+                    self.filter = self.filter.next();
+                    self.files.select(Some(0));
+                    self.links.select(Some(0));
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let (state, len) = match self.focus {
+            Focus::Files => {
+                let len = self.visible_files().len();
+                (&mut self.files, len)
+            }
+            Focus::Links => {
+                let len = self
+                    .selected_file()
+                    .map_or(0, |file| entries(file, self.filter).len());
+                (&mut self.links, len)
+            }
+        };
+
+        let selected = state.selected().unwrap_or(0) as isize + delta;
+        state.select(Some(
+            selected.clamp(0, len.saturating_sub(1) as isize) as usize
+        ));
+
+        if self.focus == Focus::Files {
+            self.links.select(Some(0));
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame<'_>) {
+        let [main, context, help] = Layout::vertical([
+            Constraint::Min(5),
+            Constraint::Length(2 * CONTEXT_LINES as u16 + 3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [files_area, links_area] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(main);
+
+        let files: Vec<String> = self
+            .visible_files()
+            .iter()
+            .map(|file| {
+                let count: usize = entries(file, self.filter)
+                    .iter()
+                    .map(|(_, occurrences, _)| occurrences.count)
+                    .sum();
+                format!("{} ({})", file.path.display(), count)
+            })
+            .collect();
+        let files = List::new(files)
+            .block(self.block("Files", Focus::Files))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(files, files_area, &mut self.files);
+
+        let file = self.selected_file();
+        let entries = file.map_or_else(Vec::new, |file| entries(file, self.filter));
+        let links: Vec<String> = entries
+            .iter()
+            .map(|(link, occurrences, is_anchor)| {
+                let mut item = format!(
+                    "{} /{}",
+                    if *is_anchor { "anchor" } else { "link" },
+                    link.href
+                );
+                if occurrences.count > 1 {
+                    item.push_str(&format!(" (×{})", occurrences.count));
+                }
+                item.push_str(&format!(" (from {})", link.origin()));
+                item
+            })
+            .collect();
+        let links = List::new(links)
+            .block(self.block("Broken links", Focus::Links))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(links, links_area, &mut self.links);
+
+        let entry = self.links.selected().and_then(|i| entries.get(i));
+        if let (Some(file), Some(&(link, occurrences, _))) = (file, entry) {
+            self.draw_context(frame, context, &file.path, link, occurrences);
+        } else {
+            frame.render_widget(Block::bordered().title("Source"), context);
+        }
+
+        frame.render_widget(
+            Line::from(format!(
+                " ↑↓ select  ←→ switch pane  f filter: {}  q quit",
+                self.filter.label()
+            )),
+            help,
+        );
+    }
+
+    fn block(&self, title: &'static str, focus: Focus) -> Block<'static> {
+        let block = Block::bordered().title(title);
+        if self.focus == focus {
+            block.border_style(Style::new().add_modifier(Modifier::BOLD))
+        } else {
+            block
+        }
+    }
+
+    fn draw_context(
+        &mut self,
+        frame: &mut Frame<'_>,
+        area: Rect,
+        path: &Path,
+        link: &ReportedLink,
+        occurrences: &Occurrences,
+    ) {
+        let source = self
+            .sources
+            .entry(path.to_owned())
+            .or_insert_with(|| fs::read_to_string(path).ok());
+
+        let source = match source {
+            Some(source) => source,
+            None => {
+                let message = Paragraph::new(format!("Could not read {}", path.display()));
+                frame.render_widget(message.block(Block::bordered().title("Source")), area);
+                return;
+            }
+        };
+
+        // Without --sources we only know the file, so look for how the link is written in it.
+        let lineno = link.line.or_else(|| {
+            occurrences
+                .written_as
+                .iter()
+                .filter(|raw_href| !raw_href.is_empty())
+                .find_map(|raw_href| {
+                    source
+                        .lines()
+                        .position(|line| line.contains(raw_href.as_str()))
+                })
+                .map(|i| i + 1)
+        });
+
+        let title = match lineno {
+            Some(lineno) => format!("{}:{}", path.display(), lineno),
+            None => path.display().to_string(),
+        };
+
+        let lineno = lineno.unwrap_or(1);
+        let lines: Vec<Line<'_>> = source
+            .lines()
+            .enumerate()
+            .skip(lineno.saturating_sub(CONTEXT_LINES + 1))
+            .take(2 * CONTEXT_LINES + 1)
+            .map(|(i, line)| {
+                let line = Line::from(format!("{:>5} {}", i + 1, line));
+                if i + 1 == lineno {
+                    line.style(Style::new().add_modifier(Modifier::REVERSED))
+                } else {
+                    line
+                }
+            })
+            .collect();
+
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(title)),
+            area,
+        );
+    }
+}
+
+/// Show the broken links of `report` until the user quits.
+pub fn run(report: &Report) -> Result<(), Error> {
+    let mut terminal = ratatui::init();
+    let result = App::new(report).run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+#[test]
+fn test_draw() {
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    let mut file = FileReport::new(PathBuf::from("index.html"));
+    for (hard_404, href) in [(true, "bar.html"), (true, "bar.html"), (false, "#missing")] {
+        file.add_broken_link(
+            hard_404,
+            ReportedLink {
+                line: None,
+                column: None,
+                href: href.to_owned(),
+                element: "<a href>",
+                media: None,
+                absolute: false,
+                redirect_from: None,
+            },
+            href,
+        );
+    }
+    let report = Report {
+        files: vec![file],
+        bad_links: 2,
+        bad_anchors: 1,
+        ..Default::default()
+    };
+
+    let mut app = App::new(&report);
+    let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+    let screen = |terminal: &Terminal<TestBackend>| {
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    terminal.draw(|frame| app.draw(frame)).unwrap();
+    let text = screen(&terminal);
+    assert!(text.contains("index.html (3)"), "{}", text);
+    assert!(
+        text.contains("link /bar.html (×2) (from <a href>)"),
+        "{}",
+        text
+    );
+    assert!(
+        text.contains("anchor /#missing (from <a href>)"),
+        "{}",
+        text
+    );
+    assert!(text.contains("Could not read index.html"), "{}", text);
+
+    app.filter = Filter::Anchors;
+    terminal.draw(|frame| app.draw(frame)).unwrap();
+    let text = screen(&terminal);
+    assert!(text.contains("index.html (1)"), "{}", text);
+    assert!(!text.contains("/bar.html"), "{}", text);
+}
@@ -0,0 +1,97 @@
+use std::fmt::Write;
+use std::str;
+
+use serde::Serialize;
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum InventoryFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+impl str::FromStr for InventoryFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(InventoryFormat::Json),
+            "csv" => Ok(InventoryFormat::Csv),
+            _ => Err(format!("unknown format {s:?}, expected json or csv")),
+        }
+    }
+}
+
+/// One used link, as printed by the emit-inventory command.
+#[derive(Debug, Serialize)]
+pub struct InventoryEntry {
+    /// The file containing the link.
+    pub source: String,
+    /// The link as written in the file.
+    pub href: String,
+    /// What the link resolves to, as a path from the site root for internal links.
+    pub target: String,
+    pub external: bool,
+    /// Whether the link is broken. External links are not checked, and this is `None`.
+    pub broken: Option<bool>,
+}
+
+pub fn inventory_to_csv(entries: &[InventoryEntry]) -> String {
+    let mut csv = String::from("source,href,target,external,broken\n");
+    for entry in entries {
+        let broken = match entry.broken {
+            Some(broken) => broken.to_string(),
+            None => String::new(),
+        };
+        writeln!(
+            csv,
+            "{},{},{},{},{}",
+            csv_field(&entry.source),
+            csv_field(&entry.href),
+            csv_field(&entry.target),
+            entry.external,
+            broken
+        )
+        .unwrap();
+    }
+    csv
+}
+
+/// Quote a field if it contains a separator, quote or line break, as in RFC 4180.
+fn csv_field(value: &str) -> String {
+    if value.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+#[test]
+fn test_inventory_to_csv() {
+    let entries = vec![
+        InventoryEntry {
+            source: "public/index.html".to_owned(),
+            href: "a.html?x=1,2".to_owned(),
+            target: "/a.html".to_owned(),
+            external: false,
+            broken: Some(true),
+        },
+        InventoryEntry {
+            source: "public/index.html".to_owned(),
+            href: "https://example.com/\"q\"".to_owned(),
+            target: "https://example.com/\"q\"".to_owned(),
+            external: true,
+            broken: None,
+        },
+    ];
+
+    assert_eq!(
+        inventory_to_csv(&entries),
+        "source,href,target,external,broken\n\
+         public/index.html,\"a.html?x=1,2\",/a.html,false,true\n\
+         public/index.html,\"https://example.com/\"\"q\"\"\",\"https://example.com/\"\"q\"\"\",true,\n"
+    );
+}
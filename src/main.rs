@@ -1,30 +1,76 @@
 #![allow(clippy::manual_flatten)]
+mod anchor_synonyms;
+mod archive;
+mod baseline;
 mod collector;
+mod css;
+mod glob;
 mod html;
+#[cfg(feature = "interactive")]
+mod interactive;
+mod inventory;
 mod markdown;
+mod normalize;
 mod paragraph;
+mod redirects;
+mod report;
+mod rewrite_map;
+mod sitemap;
+mod slug;
+mod source_mapping;
 mod urls;
 
 use std::cmp;
 use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::io::{self, Write};
 use std::mem;
 use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::{anyhow, Context, Error};
 use bpaf::*;
 use jwalk::WalkDirGeneric;
 use markdown::DocumentSource;
+use rayon::iter::Either;
 use rayon::prelude::*;
 
-use collector::{BrokenLinkCollector, LinkCollector, LocalLinksOnly, UsedLinkCollector};
-use html::{DefinedLink, Document, DocumentBuffers, Link};
+use anchor_synonyms::AnchorSynonyms;
+use archive::TarFiles;
+use baseline::{Baseline, BaselineEntry};
+use collector::{
+    is_percent_encoded, BrokenLinkCollector, CheckOptions, InventoryCollector, LinkCollector,
+    LocalLinksOnly, UsedLinkCollector,
+};
+use glob::Glob;
+use html::{
+    AnchorCase, AnchorElementPolicy, DefinedLink, DirectoryIndex, Document, DocumentBuffers, Href,
+    IgnoreSelector, Link, LinkAttribute, OpenFiles, ParserOptions, UsedLink, ValidRoutes, Warning,
+};
+use inventory::{inventory_to_csv, InventoryEntry, InventoryFormat};
 use paragraph::{DebugParagraphWalker, NoopParagraphWalker, ParagraphHasher, ParagraphWalker};
-
-use crate::urls::is_external_link;
+use redirects::Redirects;
+use report::{
+    Event, FileError, FileErrorKind, FileReport, Metrics, Occurrences, OutputFormat, Report,
+    ReportedLink, ReportedWarning, StaleBaselineEntry,
+};
+use rewrite_map::RewriteMap;
+use sitemap::sitemap_locs;
+use source_mapping::{find_href_line, SourceMapping};
+
+use crate::normalize::{attribute_value_range, link_attribute, normalize_href, TrailingSlash};
+use crate::slug::AnchorStyle;
+use crate::urls::{
+    external_link_host, host_matches_domain, is_external_link, strip_origin, url_scheme,
+};
 
 static MARKDOWN_FILES: &[&str] = &["md", "mdx"];
 static HTML_FILES: &[&str] = &["htm", "html"];
+// Well below the common soft limit of 256 file descriptors on macOS.
+const DEFAULT_MAX_OPEN_FILES: usize = 128;
 
 #[derive(Bpaf, PartialEq, Debug)]
 struct MainCommand {
@@ -32,14 +78,329 @@ struct MainCommand {
     #[bpaf(long)]
     check_anchors: bool,
 
-    /// path to directory of markdown files to use for reporting errors
+    /// list anchors that are defined but never linked to, requires --check-anchors
+    #[bpaf(long)]
+    report_unused_anchors: bool,
+
+    /// warn about empty id and name attributes, which cannot be linked to
+    #[bpaf(long)]
+    report_empty_anchors: bool,
+
+    /// compare anchors case-insensitively, only folding ASCII letters
+    #[bpaf(long)]
+    ignore_anchor_case: bool,
+
+    /// compare anchors case-insensitively, using Unicode lowercasing
+    #[bpaf(long)]
+    unicode_anchor_case: bool,
+
+    /// headings without id define an anchor derived from their text, like on GitHub
+    #[bpaf(long)]
+    heading_anchors: bool,
+
+    /// derive heading anchors like github (default), hugo, jekyll or docusaurus
+    #[bpaf(long, argument("STYLE"))]
+    anchor_style: Option<AnchorStyle>,
+
+    /// also treat this attribute as an anchor definition like id, can be repeated
+    #[bpaf(argument("NAME"))]
+    anchor_attribute: Vec<String>,
+
+    /// warn about linked anchors on other elements than these, like headings,landmarks
+    ///
+    /// headings stands for h1 to h6, landmarks for main, nav, aside, header, footer, section,
+    /// article and form. Requires --check-anchors.
+    #[bpaf(long, argument("TAGS"))]
+    anchor_element_policy: Option<AnchorElementPolicy>,
+
+    /// check links with query strings against this list of routes, one per line, like /app?page=a
+    ///
+    /// Only links to the pages in the list keep their query string, a mistyped route is reported
+    /// as bad anchor. Requires --check-anchors.
+    #[bpaf(long, argument("FILE"))]
+    valid_routes: Option<PathBuf>,
+
+    /// accept old anchors in place of new ones from this file, one per line, like #old = #new
+    ///
+    /// Lines like /guide.html#old = #new only apply to one page. Links to old anchors are reported
+    /// as warnings. Requires --check-anchors.
+    #[bpaf(long, argument("FILE"))]
+    anchor_synonyms: Option<PathBuf>,
+
+    /// accept anchors with or without PREFIX, for generators that prefix generated ids
+    ///
+    /// With --anchor-prefix user-content-, a link to #intro is valid if there is an id
+    /// user-content-intro, and the other way around. Requires --check-anchors.
+    #[bpaf(long, argument("PREFIX"))]
+    anchor_prefix: Option<String>,
+
+    /// treat fragments starting with #! as client-side routes instead of anchors
+    ///
+    /// Links like /app.html#!/section then only need app.html to exist. Requires --check-anchors.
+    #[bpaf(long)]
+    hashbang_routes: bool,
+
+    /// check that aria-describedby and aria-labelledby only reference ids of the same page
+    ///
+    /// Missing ids are reported like bad anchors. Requires --check-anchors.
+    #[bpaf(long)]
+    check_aria_references: bool,
+
+    /// check that <label for> only references ids of the same page
+    ///
+    /// Missing ids are reported like bad anchors. Requires --check-anchors.
+    #[bpaf(long)]
+    check_label_for: bool,
+
+    /// warn about links that percent-encode an anchor whose id is written literally, or vice versa
+    ///
+    /// Both match, but mixing them is inconsistent. Requires --check-anchors.
+    #[bpaf(long)]
+    warn_anchor_encoding_mismatch: bool,
+
+    /// only check that linked pages exist, never anchors, even on links like #foo
+    #[bpaf(long)]
+    ignore_fragments: bool,
+
+    /// path to directory of markdown or html files to use for reporting errors
     #[bpaf(long("sources"))]
     sources_path: Option<PathBuf>,
 
+    /// parse all files in --sources as markdown or html, instead of by their extension
+    #[bpaf(long, argument("FORMAT"))]
+    sources_format: Option<SourcesFormat>,
+
+    /// only use files in --sources whose relative path matches this glob, like 'docs/**/*.md'
+    #[bpaf(long, argument("GLOB"))]
+    sources_glob: Option<Glob>,
+
+    /// map HTML files to source files by path instead, like '{}/index.html=content/{}.md'
+    #[bpaf(argument("PATTERN"))]
+    source_mapping: Option<SourceMapping>,
+
+    /// list the broken links and anchors of each source file together, sorted by line
+    ///
+    /// Requires --sources or --source-mapping.
+    #[bpaf(long)]
+    group_by_source: bool,
+
+    /// warn about <img srcset> with width descriptors but no sizes attribute
+    #[bpaf(long)]
+    check_srcset_sizes: bool,
+
+    /// warn about <track> elements with an unknown kind, missing srclang or conflicting defaults
+    #[bpaf(long)]
+    check_tracks: bool,
+
+    /// warn about HTML syntax errors that may cause links to be missed
+    #[bpaf(long)]
+    report_parse_errors: bool,
+
+    /// check links in @import rules and url() functions of CSS files
+    #[bpaf(long)]
+    check_css_imports: bool,
+
+    /// warn about links to pages that are written without .html, like /about or /guide/
+    #[bpaf(long)]
+    require_html_extension: bool,
+
+    /// warn about links to the source of a redirect in _redirects instead of to its target
+    #[bpaf(long)]
+    warn_redirect_links: bool,
+
+    /// warn about links that _redirects redirects more than HOPS times before reaching a file
+    #[bpaf(long, argument("HOPS"))]
+    report_long_redirect_chains: Option<usize>,
+
+    /// report rules in _redirects whose target does not exist as errors
+    #[bpaf(long("warn-on-redirect-to-404"))]
+    warn_on_redirect_to_404: bool,
+
+    /// treat pages with <meta http-equiv=refresh> like redirects in _redirects
+    ///
+    /// Links to them are reported by --warn-redirect-links and followed by
+    /// --report-long-redirect-chains.
+    #[bpaf(long)]
+    follow_meta_refresh_chain: bool,
+
+    /// also extract links from this tag and attribute, like div:data-href, can be repeated
+    #[bpaf(argument("TAG:ATTRIBUTE"))]
+    link_attribute: Vec<LinkAttribute>,
+
+    /// do not extract links from elements matching this selector, like nav, .footer or #sidebar
+    ///
+    /// Only tag, class and id selectors are supported. Anchors in these elements are still
+    /// defined. Can be repeated.
+    #[bpaf(long, argument("SELECTOR"))]
+    ignore_selector: Vec<IgnoreSelector>,
+
+    /// only extract links from --link-attribute, not from built-in ones like <a href>
+    #[bpaf(long)]
+    no_default_tags: bool,
+
+    /// also extract links from markup in comments, like IE conditional comments
+    #[bpaf(long)]
+    parse_comments: bool,
+
+    /// warn about hrefs containing backslashes, like docs\guide.html
+    #[bpaf(long)]
+    report_mixed_separators: bool,
+
+    /// warn about hrefs that look like unrendered templates, like {{ url }}
+    #[bpaf(long)]
+    warn_unrendered_templates: bool,
+
+    /// warn about links with a scheme outside of a known list, like the typo htps://
+    #[bpaf(long)]
+    strict_scheme: bool,
+
+    /// warn about repeated links within a page, and links with the same text but different targets
+    #[bpaf(long)]
+    warn_duplicate_links: bool,
+
+    /// treat symlinks whose target does not exist as missing, instead of as existing files
+    #[bpaf(long)]
+    resolve_symlinked_targets: bool,
+
+    /// treat links to directories without index.html as valid, for servers that list them
+    #[bpaf(long)]
+    allow_directory_listings: bool,
+
+    /// skip documents larger than BYTES with a warning, instead of reading them
+    #[bpaf(long, argument("BYTES"))]
+    max_file_size: Option<u64>,
+
+    /// how many files to read at the same time, default is 128
+    ///
+    /// Lower this if hyperlink fails with "too many open files" on systems with a low file
+    /// descriptor limit.
+    #[bpaf(long, argument("N"))]
+    max_open_files: Option<usize>,
+
+    /// enable a versioned set of checks and lints, and fail on warnings
+    ///
+    /// The set of checks is versioned so that it does not change across upgrades. Version 1 is
+    /// --check-anchors, --check-srcset-sizes, --report-parse-errors and --check-css-imports.
+    /// Version 2 adds --warn-unrendered-templates.
+    #[bpaf(long, argument("VERSION"))]
+    strict: Option<u32>,
+
+    /// how links to directories such as /foo/ resolve: index-html (default), same-name-html or none
+    ///
+    /// index-html resolves /foo/ to foo/index.html, same-name-html resolves it to foo.html, and
+    /// none treats links to directories as broken.
+    #[bpaf(long, argument("MODE"))]
+    directory_index_mode: Option<DirectoryIndex>,
+
+    /// treat /foo and /foo/ as different links, only /foo/ resolves to foo/index.html
+    #[bpaf(long)]
+    strict_trailing_slash: bool,
+
+    /// check docs that are opened from disk, where links starting with / are broken
+    ///
+    /// Without a server, /about.html points to the root of the filesystem instead of the site.
+    #[bpaf(long)]
+    filesystem_mode: bool,
+
+    /// try appending this extension to links without one, such as html for href="/about"
+    #[bpaf(long, argument("EXTENSION"))]
+    assume_extension: Option<String>,
+
+    /// JSON file mapping clean URLs to the files they are served from, like {"/guide": "guide.abc.html"}
+    #[bpaf(long, argument("FILE"))]
+    rewrite_map: Option<PathBuf>,
+
+    /// the root path of your server, defaults to BASE-PATH
+    ///
+    /// Use this to only check links in a subdirectory BASE-PATH of the site, while still resolving
+    /// href="/foo" against the actual root.
+    #[bpaf(long("root"), argument("PATH"))]
+    root_path: Option<PathBuf>,
+
+    /// read the site from a tar archive on stdin, BASE-PATH is then a directory inside of it
+    #[bpaf(long)]
+    from_tar: bool,
+
+    /// only check the documents whose paths are read from stdin, separated by NUL bytes
+    #[bpaf(long)]
+    stdin_paths_null: bool,
+
     /// enable specialized output for GitHub actions
     #[bpaf(long)]
     github_actions: bool,
 
+    /// put all bad links of a file into one GitHub actions annotation
+    ///
+    /// GitHub only shows a few annotations per step. The annotation is placed at the first bad
+    /// link, and every link is listed with its line. Requires --github-actions.
+    #[bpaf(long)]
+    group_consecutive_lines: bool,
+
+    /// output format: text (default), json or ndjson
+    #[bpaf(long, argument("FORMAT"))]
+    format: Option<OutputFormat>,
+
+    /// print the report of --format json on a single line instead of indented
+    #[bpaf(long)]
+    json_compact: bool,
+
+    /// print each bad link with this template, using {path}, {href}, {line}, {column} and {kind}
+    ///
+    /// Replaces the default text output such as "error: bad link /foo (from <a href>)", for
+    /// example to translate it or to adapt it to a log parser.
+    #[bpaf(long, argument("TEMPLATE"))]
+    message_template: Option<MessageTemplate>,
+
+    /// report line and column of bad links in HTML files, counting tabs as N columns
+    #[bpaf(long, argument("N"))]
+    html_tab_size: Option<usize>,
+
+    /// browse broken links in a terminal UI, requires the interactive feature
+    #[bpaf(long)]
+    interactive: bool,
+
+    /// warn about pages with more than THRESHOLD broken links, a count like 10 or a percentage like 50%
+    ///
+    /// A page where most links are broken usually indicates a systemic issue, such as a wrong base
+    /// path, rather than individual typos.
+    #[bpaf(long, argument("THRESHOLD"))]
+    link_density_warning: Option<LinkDensity>,
+
+    /// also count how many distinct targets the bad links point to across all files
+    #[bpaf(long)]
+    dedupe_across_files: bool,
+
+    /// only report the first broken link, all files are still read
+    #[bpaf(long)]
+    fail_fast: bool,
+
+    /// only print the number of bad links and bad anchors
+    #[bpaf(long)]
+    count_only: bool,
+
+    /// do not print progress messages and the timing summary
+    #[bpaf(long)]
+    quiet: bool,
+
+    /// write JSON metrics such as file counts and phase durations to PATH
+    #[bpaf(argument("PATH"))]
+    metrics_file: Option<PathBuf>,
+
+    /// print the JSON schema of the output of --format json and exit
+    #[bpaf(long)]
+    print_json_schema: bool,
+
+    /// file of known broken links, which are ignored
+    ///
+    /// Create it using --write-baseline. Baseline entries that are not broken anymore are
+    /// reported so the baseline can be pruned.
+    #[bpaf(long, argument("FILE"))]
+    baseline: Option<PathBuf>,
+
+    /// record all current broken links in the --baseline file and exit
+    #[bpaf(long)]
+    write_baseline: bool,
+
     /// the static file path to check
     ///
     /// This will be assumed to be the root path of your server as well, so
@@ -64,6 +425,8 @@ struct Cli {
     command: Command,
 }
 
+// Parsed once at startup, so the size of MainCommand does not matter.
+#[allow(clippy::large_enum_variant)]
 #[derive(Bpaf, PartialEq, Debug)]
 enum Command {
     /// Dump out internal data for markdown or html file.
@@ -115,6 +478,95 @@ enum Command {
         /// base path
         #[bpaf(long)]
         base_path: PathBuf,
+
+        /// comma-separated domains to leave out, including their subdomains
+        #[bpaf(long, argument("DOMAINS"))]
+        exclude_external_domains: Vec<String>,
+    },
+
+    /// Count the URL schemes of all links, such as https, mailto or javascript.
+    ///  This helps to spot unexpected schemes, such as typos like htttp.
+    #[bpaf(command("list-schemes"))]
+    ListSchemes {
+        /// base path
+        #[bpaf(long)]
+        base_path: PathBuf,
+    },
+
+    /// List protocol-relative links and the https:// link they would become.
+    ///  Each line has the file, the link and its replacement, so that migrating to explicit
+    /// https:// links can be scripted.
+    #[bpaf(command("list-protocol-relative"))]
+    ListProtocolRelative {
+        /// base path
+        #[bpaf(long)]
+        base_path: PathBuf,
+    },
+
+    /// Print every link of the site with its target and whether it is broken, as JSON or CSV.
+    ///  External links are listed, but not checked.
+    #[bpaf(command("emit-inventory"))]
+    EmitInventory {
+        /// base path
+        #[bpaf(long)]
+        base_path: PathBuf,
+
+        /// check anchors as with --check-anchors
+        #[bpaf(long)]
+        check_anchors: bool,
+
+        /// json (default) or csv
+        #[bpaf(long, argument("FORMAT"))]
+        format: Option<InventoryFormat>,
+    },
+
+    /// Check that every URL in sitemap.xml points to an existing page.
+    ///  Absolute URLs are resolved from the root of the site, whatever their domain.
+    #[bpaf(command("check-sitemap"))]
+    CheckSitemap {
+        /// base path
+        #[bpaf(long)]
+        base_path: PathBuf,
+
+        /// also warn about HTML pages that are not in the sitemap
+        #[bpaf(long)]
+        report_unlisted_pages: bool,
+    },
+
+    /// Rewrite links to the canonical form of their target.
+    ///  For example, ../docs/./guide.html in docs/intro.html becomes guide.html. Only prints the
+    /// changes, unless --write is given.
+    #[bpaf(command("normalize"))]
+    Normalize {
+        /// base path
+        #[bpaf(long)]
+        base_path: PathBuf,
+
+        /// add or remove trailing slashes of links to directories
+        #[bpaf(long, argument("POLICY"))]
+        trailing_slash: Option<TrailingSlash>,
+
+        /// edit the files instead of only printing the changes
+        #[bpaf(long)]
+        write: bool,
+    },
+
+    /// Print the link target that an href in a HTML file resolves to.
+    ///  This can be used to figure out why a link is considered broken.
+    ///  NOTE: This is a tool for debugging and development.
+    #[bpaf(command("resolve-url"))]
+    ResolveUrl {
+        /// base path
+        #[bpaf(long)]
+        base_path: PathBuf,
+
+        /// html file containing the link
+        #[bpaf(long)]
+        file: PathBuf,
+
+        /// the href to resolve
+        #[bpaf(positional("HREF"))]
+        href: String,
     },
 
     Main(#[bpaf(external(main_command))] MainCommand),
@@ -141,12 +593,7 @@ fn main() -> Result<(), Error> {
         .build_global()
         .unwrap();
 
-    let MainCommand {
-        base_path,
-        check_anchors,
-        sources_path,
-        github_actions,
-    } = match command {
+    let mut main_command = match command {
         Command::DumpParagraphs { file } => {
             return dump_paragraphs(file);
         }
@@ -156,14 +603,57 @@ fn main() -> Result<(), Error> {
         } => {
             return match_all_paragraphs(base_path, sources_path);
         }
-        Command::DumpExternalLinks { base_path } => {
-            return dump_external_links(base_path);
+        Command::DumpExternalLinks {
+            base_path,
+            exclude_external_domains,
+        } => {
+            return dump_external_links(base_path, exclude_external_domains);
+        }
+        Command::ListSchemes { base_path } => {
+            return list_schemes(base_path);
+        }
+        Command::ListProtocolRelative { base_path } => {
+            return list_protocol_relative(base_path);
+        }
+        Command::CheckSitemap {
+            base_path,
+            report_unlisted_pages,
+        } => {
+            return check_sitemap(base_path, report_unlisted_pages);
+        }
+        Command::EmitInventory {
+            base_path,
+            check_anchors,
+            format,
+        } => {
+            return emit_inventory(base_path, check_anchors, format.unwrap_or_default());
+        }
+        Command::Normalize {
+            base_path,
+            trailing_slash,
+            write,
+        } => {
+            return normalize_links(base_path, trailing_slash, write);
+        }
+        Command::ResolveUrl {
+            base_path,
+            file,
+            href,
+        } => {
+            return resolve_url(base_path, file, href);
         }
         Command::Main(main_command) => main_command,
     };
 
-    let base_path = match base_path {
-        Some(base_path) => base_path,
+    if main_command.print_json_schema {
+        let schema = schemars::schema_for!(Report);
+        println!("{}", serde_json::to_string_pretty(&schema)?);
+        return Ok(());
+    }
+
+    let base_path = match main_command.base_path {
+        Some(ref base_path) => base_path.clone(),
+        None if main_command.from_tar => PathBuf::new(),
         None => {
             // Invalid invocation. Ultra hack to show help if no arguments are provided.
             let help_message = cli()
@@ -175,174 +665,1272 @@ fn main() -> Result<(), Error> {
         }
     };
 
-    if sources_path.is_some() {
-        check_links::<ParagraphHasher>(base_path, check_anchors, sources_path, github_actions)
-    } else {
-        check_links::<NoopParagraphWalker>(base_path, check_anchors, sources_path, github_actions)
+    if main_command.stdin_paths_null && main_command.from_tar {
+        return Err(anyhow!(
+            "--stdin-paths-null cannot be combined with --from-tar"
+        ));
     }
-}
-
-fn check_links<P: ParagraphWalker>(
-    base_path: PathBuf,
-    check_anchors: bool,
-    sources_path: Option<PathBuf>,
-    github_actions: bool,
-) -> Result<(), Error>
-where
-    P::Paragraph: Copy + PartialEq,
-{
-    println!("Reading files");
-
-    let html_result =
-        extract_html_links::<LocalLinksOnly<BrokenLinkCollector<_>>, P>(&base_path, check_anchors)?;
-
-    let used_links_len = html_result.collector.collector.used_links_count();
-    println!(
-        "Checking {} links from {} files ({} documents)",
-        used_links_len, html_result.file_count, html_result.documents_count,
-    );
 
-    let mut bad_links_and_anchors = BTreeMap::new();
-    let mut bad_links_count = 0;
-    let mut bad_anchors_count = 0;
-
-    let mut broken_links = html_result
-        .collector
-        .collector
-        .get_broken_links(check_anchors)
-        .peekable();
+    if main_command.ignore_fragments
+        && (main_command.check_anchors || main_command.strict.is_some())
+    {
+        return Err(anyhow!(
+            "--ignore-fragments cannot be combined with --check-anchors or --strict"
+        ));
+    }
 
-    let paragraps_to_sourcefile = if broken_links.peek().is_some() {
-        if let Some(ref sources_path) = sources_path {
-            println!("Found some broken links, reading source files");
-            extract_markdown_paragraphs::<P>(sources_path)?
-        } else {
-            BTreeMap::new()
+    match main_command.strict {
+        None => {}
+        Some(version @ 1..=2) => {
+            main_command.check_anchors = true;
+            main_command.check_srcset_sizes = true;
+            main_command.report_parse_errors = true;
+            main_command.check_css_imports = true;
+            if version >= 2 {
+                main_command.warn_unrendered_templates = true;
+            }
         }
-    } else {
-        BTreeMap::new()
-    };
+        Some(version) => {
+            return Err(anyhow!(
+                "Unknown --strict version {version}, expected 1 or 2"
+            ))
+        }
+    }
 
-    for broken_link in broken_links {
-        let mut had_sources = false;
+    if main_command.ignore_anchor_case && main_command.unicode_anchor_case {
+        return Err(anyhow!(
+            "--ignore-anchor-case cannot be combined with --unicode-anchor-case"
+        ));
+    }
 
-        if broken_link.hard_404 {
-            bad_links_count += 1;
-        } else {
-            bad_anchors_count += 1;
+    let anchor_flags = [
+        (
+            main_command.report_unused_anchors,
+            "--report-unused-anchors",
+        ),
+        (
+            main_command.anchor_element_policy.is_some(),
+            "--anchor-element-policy",
+        ),
+        (main_command.valid_routes.is_some(), "--valid-routes"),
+        (main_command.anchor_synonyms.is_some(), "--anchor-synonyms"),
+        (main_command.anchor_prefix.is_some(), "--anchor-prefix"),
+        (main_command.hashbang_routes, "--hashbang-routes"),
+        (
+            main_command.warn_anchor_encoding_mismatch,
+            "--warn-anchor-encoding-mismatch",
+        ),
+        (main_command.ignore_anchor_case, "--ignore-anchor-case"),
+        (main_command.unicode_anchor_case, "--unicode-anchor-case"),
+        (main_command.check_label_for, "--check-label-for"),
+        (
+            main_command.check_aria_references,
+            "--check-aria-references",
+        ),
+        (main_command.heading_anchors, "--heading-anchors"),
+        (
+            !main_command.anchor_attribute.is_empty(),
+            "--anchor-attribute",
+        ),
+    ];
+    if !main_command.check_anchors {
+        if let Some((_, flag)) = anchor_flags.iter().find(|(is_set, _)| *is_set) {
+            return Err(anyhow!("{flag} requires --check-anchors to be set"));
         }
+    }
 
-        if let Some(ref paragraph) = broken_link.link.paragraph {
-            if let Some(document_sources) = &paragraps_to_sourcefile.get(paragraph) {
-                debug_assert!(!document_sources.is_empty());
-                had_sources = true;
+    if main_command.anchor_style.is_some() && !main_command.heading_anchors {
+        return Err(anyhow!(
+            "--anchor-style requires --heading-anchors to be set"
+        ));
+    }
 
-                for (source, lineno) in *document_sources {
-                    let (bad_links, bad_anchors) = bad_links_and_anchors
-                        .entry((!had_sources, source.path.clone()))
-                        .or_insert_with(|| (BTreeSet::new(), BTreeSet::new()));
+    if main_command.group_by_source
+        && main_command.sources_path.is_none()
+        && main_command.source_mapping.is_none()
+    {
+        return Err(anyhow!(
+            "--group-by-source requires --sources or --source-mapping to be set"
+        ));
+    }
 
-                    if broken_link.hard_404 {
-                        bad_links
-                    } else {
-                        bad_anchors
-                    }
-                    .insert((Some(*lineno), broken_link.link.href.clone()));
-                }
-            }
-        }
+    if main_command.sources_format.is_some() && main_command.sources_path.is_none() {
+        return Err(anyhow!("--sources-format requires --sources to be set"));
+    }
 
-        if !had_sources {
-            let (bad_links, bad_anchors) = bad_links_and_anchors
-                .entry((!had_sources, broken_link.link.path))
-                .or_insert_with(|| (BTreeSet::new(), BTreeSet::new()));
+    if main_command.sources_glob.is_some() && main_command.sources_path.is_none() {
+        return Err(anyhow!("--sources-glob requires --sources to be set"));
+    }
 
-            if broken_link.hard_404 {
-                bad_links
-            } else {
-                bad_anchors
-            }
-            .insert((None, broken_link.link.href));
-        }
+    if main_command.source_mapping.is_some() && main_command.sources_path.is_some() {
+        return Err(anyhow!(
+            "--source-mapping cannot be combined with --sources"
+        ));
     }
 
-    // _is_raw_file is an unused parameter that is only there to control iteration order over keys.
-    // Sort markdown files to the start since otherwise the less valuable annotations on not
-    // checked in files fill up the limit on annotations (tested manually, seems to be 10 right
-    // now).
-    for ((_is_raw_file, filepath), (bad_links, bad_anchors)) in bad_links_and_anchors {
-        println!("{}", filepath.display());
+    if main_command.no_default_tags && main_command.link_attribute.is_empty() {
+        return Err(anyhow!(
+            "--no-default-tags requires --link-attribute to be set"
+        ));
+    }
 
-        for (lineno, href) in &bad_links {
-            print_href_error("error: bad link", href, *lineno);
+    if main_command.interactive {
+        if !cfg!(feature = "interactive") {
+            return Err(anyhow!(
+                "--interactive requires hyperlink to be built with the interactive feature"
+            ));
         }
 
-        for (lineno, href) in &bad_anchors {
-            print_href_error("error: bad link", href, *lineno);
+        if main_command.format.unwrap_or_default() != OutputFormat::Text || main_command.count_only
+        {
+            return Err(anyhow!(
+                "--interactive cannot be combined with --format json or --count-only"
+            ));
         }
+    }
 
-        if github_actions {
-            if !bad_links.is_empty() {
-                print_github_actions_href_list("bad links", &filepath, &bad_links)?;
-            }
+    if main_command.group_consecutive_lines && !main_command.github_actions {
+        return Err(anyhow!(
+            "--group-consecutive-lines requires --github-actions to be set"
+        ));
+    }
 
-            if !bad_anchors.is_empty() {
-                print_github_actions_href_list("bad anchors", &filepath, &bad_anchors)?;
-            }
+    if main_command.json_compact && main_command.format != Some(OutputFormat::Json) {
+        return Err(anyhow!("--json-compact requires --format json"));
+    }
+
+    let fail_fast_conflicts = [
+        (main_command.baseline.is_some(), "--baseline"),
+        (
+            main_command.link_density_warning.is_some(),
+            "--link-density-warning",
+        ),
+    ];
+    if main_command.fail_fast {
+        if let Some((_, flag)) = fail_fast_conflicts.iter().find(|(is_set, _)| *is_set) {
+            return Err(anyhow!("--fail-fast cannot be combined with {flag}"));
         }
+    }
 
-        println!();
+    if main_command.write_baseline && main_command.baseline.is_none() {
+        return Err(anyhow!("--write-baseline requires --baseline to be set"));
     }
 
-    println!("Found {bad_links_count} bad links");
+    if let Some(ref root_path) = main_command.root_path {
+        if !base_path.starts_with(root_path) {
+            return Err(anyhow!(
+                "{} is not inside of --root {}",
+                base_path.display(),
+                root_path.display()
+            ));
+        }
+    }
 
-    if check_anchors {
-        println!("Found {bad_anchors_count} bad anchors");
+    if main_command.sources_path.is_some() {
+        check_links::<ParagraphHasher>(base_path, &main_command)
+    } else {
+        check_links::<NoopParagraphWalker>(base_path, &main_command)
+    }
+}
+
+fn check_links<P: ParagraphWalker>(base_path: PathBuf, args: &MainCommand) -> Result<(), Error>
+where
+    P::Paragraph: Copy + PartialEq,
+{
+    let MainCommand {
+        check_anchors,
+        report_unused_anchors,
+        ignore_anchor_case,
+        unicode_anchor_case,
+        heading_anchors,
+        anchor_style,
+        ref anchor_attribute,
+        ref anchor_element_policy,
+        ref valid_routes,
+        ref anchor_synonyms,
+        ref anchor_prefix,
+        hashbang_routes,
+        check_aria_references,
+        check_label_for,
+        warn_anchor_encoding_mismatch,
+        ref sources_path,
+        sources_format,
+        ref sources_glob,
+        ref source_mapping,
+        group_by_source,
+        check_srcset_sizes,
+        check_tracks,
+        report_parse_errors,
+        check_css_imports,
+        require_html_extension,
+        warn_redirect_links,
+        report_long_redirect_chains,
+        warn_on_redirect_to_404,
+        follow_meta_refresh_chain,
+        ref link_attribute,
+        ref ignore_selector,
+        no_default_tags,
+        parse_comments,
+        report_mixed_separators,
+        warn_unrendered_templates,
+        strict_scheme,
+        report_empty_anchors,
+        warn_duplicate_links,
+        resolve_symlinked_targets,
+        allow_directory_listings,
+        max_file_size,
+        max_open_files,
+        strict,
+        directory_index_mode,
+        strict_trailing_slash,
+        filesystem_mode,
+        ref assume_extension,
+        ref rewrite_map,
+        ref root_path,
+        from_tar,
+        stdin_paths_null,
+        github_actions,
+        group_consecutive_lines,
+        format,
+        json_compact,
+        ref message_template,
+        html_tab_size,
+        link_density_warning,
+        dedupe_across_files,
+        fail_fast,
+        count_only,
+        quiet,
+        ref metrics_file,
+        baseline: ref baseline_path,
+        write_baseline,
+        ..
+    } = *args;
+
+    let format = format.unwrap_or_default();
+    // Progress messages would corrupt machine-readable output
+    let print_progress = format == OutputFormat::Text && !count_only && !quiet;
+
+    let baseline = match baseline_path {
+        Some(path) if !write_baseline => Some(Baseline::read(path)?),
+        _ => None,
+    };
+
+    let directory_index = directory_index_mode.unwrap_or_default();
+    let valid_routes = match valid_routes {
+        Some(path) => Some(ValidRoutes::read(
+            path,
+            directory_index != DirectoryIndex::None,
+            strict_trailing_slash,
+        )?),
+        None => None,
+    };
+
+    let max_open_files = max_open_files.unwrap_or(DEFAULT_MAX_OPEN_FILES);
+    if max_open_files == 0 {
+        return Err(anyhow!("--max-open-files must be at least 1"));
     }
 
+    let parser_options = ParserOptions {
+        check_anchors,
+        anchor_case: if unicode_anchor_case {
+            AnchorCase::Unicode
+        } else if ignore_anchor_case {
+            AnchorCase::Ascii
+        } else {
+            AnchorCase::Sensitive
+        },
+        check_srcset_sizes,
+        check_tracks,
+        report_parse_errors,
+        directory_index,
+        check_css_imports,
+        heading_anchors,
+        anchor_style: anchor_style.unwrap_or_default(),
+        require_html_extension,
+        link_attributes: link_attribute.clone(),
+        anchor_attributes: anchor_attribute
+            .iter()
+            .map(|name| name.to_ascii_lowercase())
+            .collect(),
+        no_default_tags,
+        strict_trailing_slash,
+        filesystem_mode,
+        parse_comments,
+        report_mixed_separators,
+        warn_unrendered_templates,
+        strict_scheme,
+        report_empty_anchors,
+        warn_duplicate_links,
+        check_aria_references,
+        check_label_for,
+        warn_anchor_encoding_mismatch,
+        ignore_selectors: ignore_selector.clone(),
+        follow_meta_refresh_chain,
+        resolve_symlinked_targets,
+        allow_directory_listings,
+        max_file_size,
+        open_files: Some(Arc::new(OpenFiles::new(max_open_files))),
+        anchor_element_policy: anchor_element_policy.clone(),
+        valid_routes,
+        hashbang_routes,
+        html_tab_size,
+    };
+
+    let root_path = root_path.as_ref().unwrap_or(&base_path);
+
+    let tar_files = if from_tar {
+        Some(TarFiles::read(io::stdin().lock())?)
+    } else {
+        None
+    };
+
+    let checked_files = if stdin_paths_null {
+        Some(read_paths_null(io::stdin().lock())?)
+    } else {
+        None
+    };
+
+    let redirects = match tar_files {
+        Some(ref tar_files) => tar_files
+            .get(&root_path.join("_redirects"))
+            .map(|contents| Redirects::parse(&String::from_utf8_lossy(contents)))
+            .unwrap_or_default(),
+        None => Redirects::read(root_path)?,
+    };
+
+    let anchor_synonyms = match anchor_synonyms {
+        Some(path) => Some(AnchorSynonyms::read(
+            path,
+            directory_index != DirectoryIndex::None,
+            strict_trailing_slash,
+            parser_options.anchor_case,
+        )?),
+        None => None,
+    };
+
+    let rewrite_map = match rewrite_map {
+        Some(path) => Some(RewriteMap::read(
+            path,
+            directory_index != DirectoryIndex::None,
+            strict_trailing_slash,
+        )?),
+        None => None,
+    };
+
+    let check_options = CheckOptions {
+        check_anchors,
+        directory_index: parser_options.directory_index,
+        assume_extension: assume_extension
+            .as_ref()
+            .map(|extension| extension.trim_start_matches('.').to_owned()),
+        redirects,
+        anchor_synonyms,
+        anchor_prefix: anchor_prefix
+            .as_ref()
+            .filter(|prefix| !prefix.is_empty())
+            .map(|prefix| parser_options.anchor_case.fold(prefix).into_owned()),
+        rewrite_map,
+        fail_fast,
+    };
+
+    let start = Instant::now();
+    let mut metrics = Metrics::default();
+
+    if print_progress {
+        println!("Reading files");
+    }
+
+    let mut html_result = extract_html_links::<LocalLinksOnly<BrokenLinkCollector<_>>, P>(
+        root_path,
+        &base_path,
+        &parser_options,
+        tar_files.as_ref(),
+        checked_files.as_ref(),
+        format != OutputFormat::Text,
+        format == OutputFormat::Ndjson,
+    )?;
+    metrics.add_duration("read_html", start);
+
+    if let Some(ref valid_routes) = parser_options.valid_routes {
+        for route in valid_routes.routes() {
+            html_result.collector.ingest(Link::Defines(DefinedLink {
+                href: Href(route),
+                unexpected_element: None,
+                raw_anchor: None,
+            }));
+        }
+    }
+
+    let used_links_len = html_result.collector.collector.used_links_count();
+    if print_progress {
+        println!(
+            "Checking {} links from {} files ({} documents)",
+            used_links_len, html_result.file_count, html_result.documents_count,
+        );
+    }
+
+    let mut report = Report::default();
+    let mut bad_links_and_anchors = BTreeMap::new();
+    let mut baseline_entries = BTreeSet::new();
+    let mut baselined_count = 0;
+
+    let check_start = Instant::now();
+    let mut broken_links: Vec<_> = html_result
+        .collector
+        .collector
+        .get_broken_links(&check_options)
+        .collect();
+    metrics.add_duration("check_links", check_start);
+
+    if baseline_path.is_some() {
+        broken_links.retain(|broken_link| {
+            let entry = BaselineEntry::new(
+                root_path,
+                &broken_link.link.path,
+                &broken_link.link.href,
+                broken_link.hard_404,
+            );
+            let is_known = baseline
+                .as_ref()
+                .is_some_and(|baseline| baseline.contains(&entry));
+            baseline_entries.insert(entry);
+
+            if is_known {
+                baselined_count += 1;
+            }
+
+            !is_known
+        });
+    }
+
+    if let (true, Some(baseline_path)) = (write_baseline, baseline_path) {
+        Baseline::write(baseline_path, &baseline_entries)?;
+        if print_progress {
+            println!(
+                "Wrote {} broken links to baseline {}",
+                baseline_entries.len(),
+                baseline_path.display()
+            );
+        } else if format == OutputFormat::Ndjson {
+            Event::BaselineWritten {
+                path: baseline_path,
+                entries: baseline_entries.len(),
+            }
+            .emit()?;
+        } else if format == OutputFormat::Json {
+            println!(
+                "{}",
+                serde_json::json!({
+                    "baseline": baseline_path,
+                    "baseline_entries": baseline_entries.len(),
+                    "success": true,
+                })
+            );
+        }
+        mem::forget(html_result);
+        return Ok(());
+    }
+
+    if let Some(threshold) = link_density_warning {
+        let mut broken_per_page = BTreeMap::new();
+        for broken_link in &broken_links {
+            *broken_per_page.entry(&broken_link.link.path).or_insert(0) += 1;
+        }
+
+        for (path, total) in html_result.collector.collector.used_links_per_page() {
+            let broken = broken_per_page.get(path).copied().unwrap_or(0);
+            if threshold.is_exceeded(broken, total) {
+                report.warnings += 1;
+                bad_links_and_anchors
+                    .entry((true, path.clone()))
+                    .or_insert_with(|| FileReport::new(path.to_path_buf()))
+                    .add_warning(ReportedWarning {
+                        line: None,
+                        message: format!(
+                            "page {} has {broken}/{total} broken links, likely systemic",
+                            path.display()
+                        ),
+                    });
+            }
+        }
+    }
+
+    if fail_fast && !broken_links.is_empty() {
+        // Prefer reporting a hard 404 over a broken anchor, as that determines the exit code.
+        // Usually the collector already stopped at the first one, but not with a baseline.
+        if let Some(i) = broken_links.iter().position(|link| link.hard_404) {
+            broken_links.swap(0, i);
+        }
+        broken_links.truncate(1);
+
+        if print_progress {
+            println!("Stopping after the first broken link");
+        }
+    }
+
+    let paragraps_to_sourcefile = if !broken_links.is_empty() && !count_only && !fail_fast {
+        if let Some(ref sources_path) = sources_path {
+            if print_progress {
+                println!("Found some broken links, reading source files");
+            }
+            let sources_start = Instant::now();
+            let paragraphs = extract_markdown_paragraphs::<P>(
+                sources_path,
+                sources_format,
+                sources_glob.as_ref(),
+            )?;
+            metrics.add_duration("read_sources", sources_start);
+            paragraphs
+        } else {
+            BTreeMap::new()
+        }
+    } else {
+        BTreeMap::new()
+    };
+
+    // Source files found via --source-mapping, None if the file cannot be read.
+    let mut mapped_sources: BTreeMap<PathBuf, Option<String>> = BTreeMap::new();
+
+    if dedupe_across_files {
+        let targets: BTreeSet<&str> = broken_links
+            .iter()
+            .filter(|broken_link| broken_link.hard_404)
+            .map(|broken_link| {
+                let href = &broken_link.link.href;
+                &href[..href.find('#').unwrap_or(href.len())]
+            })
+            .collect();
+        report.unique_bad_links = Some(targets.len());
+    }
+
+    for broken_link in broken_links {
+        let mut had_sources = false;
+
+        if broken_link.hard_404 {
+            report.bad_links += 1;
+        } else {
+            report.bad_anchors += 1;
+        }
+
+        if let Some(ref paragraph) = broken_link.link.paragraph {
+            if let Some(document_sources) = &paragraps_to_sourcefile.get(paragraph) {
+                debug_assert!(!document_sources.is_empty());
+                had_sources = true;
+
+                for (source, lineno) in *document_sources {
+                    bad_links_and_anchors
+                        .entry((!had_sources, source.path.clone()))
+                        .or_insert_with(|| FileReport::new(source.path.to_path_buf()))
+                        .add_broken_link(
+                            broken_link.hard_404,
+                            ReportedLink {
+                                line: *lineno,
+                                column: None,
+                                href: broken_link.link.href.clone(),
+                                element: broken_link.link.element,
+                                media: broken_link.link.media.clone(),
+                                referrer: broken_link.link.referrer,
+                                absolute: broken_link.link.absolute,
+                                redirect_from: None,
+                            },
+                            &broken_link.link.raw_href,
+                        );
+                }
+            }
+        }
+
+        if let Some(source_mapping) = source_mapping {
+            let source_path = broken_link
+                .link
+                .path
+                .strip_prefix(root_path)
+                .ok()
+                .and_then(|path| source_mapping.source_path(path));
+
+            if let Some(source_path) = source_path {
+                let source = mapped_sources
+                    .entry(source_path.clone())
+                    .or_insert_with(|| fs::read_to_string(&source_path).ok());
+
+                if let Some(source) = source {
+                    had_sources = true;
+                    bad_links_and_anchors
+                        .entry((false, Arc::new(source_path.clone())))
+                        .or_insert_with(|| FileReport::new(source_path))
+                        .add_broken_link(
+                            broken_link.hard_404,
+                            ReportedLink {
+                                line: find_href_line(source, &broken_link.link.href),
+                                column: None,
+                                href: broken_link.link.href.clone(),
+                                element: broken_link.link.element,
+                                media: broken_link.link.media.clone(),
+                                referrer: broken_link.link.referrer,
+                                absolute: broken_link.link.absolute,
+                                redirect_from: None,
+                            },
+                            &broken_link.link.raw_href,
+                        );
+                }
+            }
+        }
+
+        if !had_sources {
+            let position = broken_link.link.position;
+            bad_links_and_anchors
+                .entry((!had_sources, broken_link.link.path.clone()))
+                .or_insert_with(|| FileReport::new(broken_link.link.path.to_path_buf()))
+                .add_broken_link(
+                    broken_link.hard_404,
+                    ReportedLink {
+                        line: position.map(|position| position.line),
+                        column: position.map(|position| position.column),
+                        href: broken_link.link.href,
+                        element: broken_link.link.element,
+                        media: broken_link.link.media,
+                        referrer: broken_link.link.referrer,
+                        absolute: broken_link.link.absolute,
+                        redirect_from: None,
+                    },
+                    &broken_link.link.raw_href,
+                );
+        }
+    }
+
+    if warn_redirect_links {
+        for (link, to) in html_result
+            .collector
+            .collector
+            .get_redirected_links(&check_options)
+        {
+            report.warnings += 1;
+            bad_links_and_anchors
+                .entry((true, link.path.clone()))
+                .or_insert_with(|| FileReport::new(link.path.to_path_buf()))
+                .add_warning(ReportedWarning {
+                    line: None,
+                    message: format!(
+                        "link /{} redirects to {to}, link to the target directly",
+                        link.href
+                    ),
+                });
+        }
+    }
+
+    for (link, synonym) in html_result
+        .collector
+        .collector
+        .get_anchor_synonym_links(&check_options)
+    {
+        report.warnings += 1;
+        bad_links_and_anchors
+            .entry((true, link.path.clone()))
+            .or_insert_with(|| FileReport::new(link.path.to_path_buf()))
+            .add_warning(ReportedWarning {
+                line: None,
+                message: format!(
+                    "link /{} uses an old anchor, link to /{synonym} instead",
+                    link.href
+                ),
+            });
+    }
+
+    for (path, raw_href, raw_anchor) in html_result
+        .collector
+        .collector
+        .get_anchor_encoding_mismatches()
+    {
+        let anchor = &raw_href[raw_href.find('#').unwrap_or(0)..];
+        let message = if is_percent_encoded(raw_anchor) {
+            format!("anchor {anchor} is literal but target id uses percent-encoding {raw_anchor:?}")
+        } else {
+            format!("anchor {anchor} uses percent-encoding but target id is literal {raw_anchor:?}")
+        };
+        report.warnings += 1;
+        bad_links_and_anchors
+            .entry((true, path.clone()))
+            .or_insert_with(|| FileReport::new(path.to_path_buf()))
+            .add_warning(ReportedWarning {
+                line: None,
+                message,
+            });
+    }
+
+    if let Some(max_hops) = report_long_redirect_chains {
+        for (link, chain) in html_result
+            .collector
+            .collector
+            .get_redirect_chains(&check_options)
+            .filter(|(_, chain)| chain.len() > max_hops)
+        {
+            report.warnings += 1;
+            bad_links_and_anchors
+                .entry((true, link.path.clone()))
+                .or_insert_with(|| FileReport::new(link.path.to_path_buf()))
+                .add_warning(ReportedWarning {
+                    line: None,
+                    message: format!(
+                        "link /{} is redirected {} times: {}",
+                        link.href,
+                        chain.len(),
+                        chain.join(" -> ")
+                    ),
+                });
+        }
+    }
+
+    if warn_on_redirect_to_404 {
+        let redirects_path = Arc::new(root_path.join("_redirects"));
+        let root = Document::new(root_path, &root_path.join("index.html"));
+        for (from, to, href) in html_result
+            .collector
+            .collector
+            .get_redirects_to_missing_files(&check_options, &root, &parser_options)
+        {
+            report.bad_links += 1;
+            bad_links_and_anchors
+                .entry((true, redirects_path.clone()))
+                .or_insert_with(|| FileReport::new(redirects_path.to_path_buf()))
+                .add_broken_link(
+                    true,
+                    ReportedLink {
+                        line: None,
+                        column: None,
+                        href,
+                        element: "_redirects",
+                        media: None,
+                        referrer: None,
+                        absolute: false,
+                        redirect_from: Some(from),
+                    },
+                    to,
+                );
+        }
+    }
+
+    for warning in html_result.collector.collector.get_warnings() {
+        report.warnings += 1;
+        bad_links_and_anchors
+            .entry((true, warning.path.clone()))
+            .or_insert_with(|| FileReport::new(warning.path.to_path_buf()))
+            .add_warning(ReportedWarning {
+                line: None,
+                message: warning.message.clone(),
+            });
+    }
+
+    for (href, path, element) in html_result
+        .collector
+        .collector
+        .get_unexpected_anchor_elements()
+    {
+        let anchor = &href[href.find('#').unwrap_or(0)..];
+        report.warnings += 1;
+        bad_links_and_anchors
+            .entry((true, path.clone()))
+            .or_insert_with(|| FileReport::new(path.to_path_buf()))
+            .add_warning(ReportedWarning {
+                line: None,
+                message: format!("linked anchor {anchor} is defined on <{element}>"),
+            });
+    }
+
+    // The bool in the key is only there to control iteration order over keys. Sort markdown files
+    // to the start since otherwise the less valuable annotations on not checked in files fill up
+    // the limit on annotations (tested manually, seems to be 10 right now).
+    report.files = bad_links_and_anchors.into_values().collect();
+
+    if report_unused_anchors {
+        report.unused_anchors = Some(
+            html_result
+                .collector
+                .collector
+                .get_unused_anchors()
+                .map(str::to_owned)
+                .collect(),
+        );
+    }
+
+    report.errors = mem::take(&mut html_result.read_errors);
+    report.stale_baseline_entries = baseline.as_ref().map(|baseline| {
+        baseline
+            .stale_entries(&baseline_entries)
+            .map(|entry| StaleBaselineEntry {
+                kind: entry.kind.as_str(),
+                path: entry.path.clone(),
+                href: entry.href.clone(),
+            })
+            .collect()
+    });
+
+    if let Some(metrics_file) = metrics_file {
+        metrics.files = html_result.file_count;
+        metrics.documents = html_result.documents_count;
+        metrics.links = used_links_len;
+        metrics.bad_links = report.bad_links;
+        metrics.bad_anchors = report.bad_anchors;
+        metrics.warnings = report.warnings;
+        metrics.errors = report.errors.len();
+        metrics.add_duration("total", start);
+
+        fs::write(metrics_file, serde_json::to_string_pretty(&metrics)?)
+            .with_context(|| format!("Failed to write metrics file {}", metrics_file.display()))?;
+    }
+
+    let files_count = html_result.file_count;
     // We're about to exit the program and leaking the memory is faster than running drop
     mem::forget(html_result);
 
-    if bad_links_count > 0 {
-        process::exit(1);
+    let exit_code = if report.bad_links > 0 || !report.errors.is_empty() {
+        1
+    } else if report.bad_anchors > 0 || (strict.is_some() && report.warnings > 0) {
+        2
+    } else {
+        0
+    };
+    report.success = exit_code == 0;
+
+    match format {
+        OutputFormat::Text if count_only => println!(
+            "bad_links={} bad_anchors={}",
+            report.bad_links, report.bad_anchors
+        ),
+        OutputFormat::Ndjson if count_only => Event::Done {
+            summary: report.summary(),
+        }
+        .emit()?,
+        OutputFormat::Json if count_only => println!(
+            "{}",
+            serde_json::json!({
+                "bad_links": report.bad_links,
+                "bad_anchors": report.bad_anchors,
+                "success": report.success,
+            })
+        ),
+        #[cfg(feature = "interactive")]
+        OutputFormat::Text if args.interactive && report.bad_links + report.bad_anchors > 0 => {
+            interactive::run(&report)?
+        }
+        OutputFormat::Text => print_report(
+            &report,
+            check_anchors,
+            github_actions,
+            group_consecutive_lines,
+            group_by_source,
+            message_template.as_ref(),
+            baseline.as_ref().map(|_| baselined_count),
+        )?,
+        OutputFormat::Json if json_compact => println!("{}", serde_json::to_string(&report)?),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        OutputFormat::Ndjson => report.emit_events()?,
+    }
+
+    if github_actions {
+        if let Some(summary_path) = env::var_os("GITHUB_STEP_SUMMARY") {
+            let mut summary = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&summary_path)
+                .with_context(|| {
+                    format!(
+                        "Failed to open GITHUB_STEP_SUMMARY {}",
+                        Path::new(&summary_path).display()
+                    )
+                })?;
+            summary.write_all(report.to_markdown().as_bytes())?;
+        }
+    }
+
+    if !quiet {
+        // stderr, so that machine-readable output is not affected
+        let elapsed = start.elapsed().as_secs_f64();
+        eprintln!(
+            "Checked {} links across {} files in {:.2} seconds ({:.0} files/s)",
+            used_links_len,
+            files_count,
+            elapsed,
+            files_count as f64 / elapsed.max(f64::EPSILON),
+        );
     }
 
-    if bad_anchors_count > 0 {
-        process::exit(2);
+    if exit_code != 0 {
+        process::exit(exit_code);
     }
 
     Ok(())
 }
 
-fn print_href_error(message: &'static str, href: &str, lineno: Option<usize>) {
-    if let Some(lineno) = lineno {
-        println!("  {message} /{href} at line {lineno}");
-    } else {
-        println!("  {message} /{href}");
+/// Threshold for `--link-density-warning`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LinkDensity {
+    Count(usize),
+    Percent(usize),
+}
+
+impl LinkDensity {
+    fn is_exceeded(self, broken: usize, total: usize) -> bool {
+        match self {
+            LinkDensity::Count(count) => broken > count,
+            LinkDensity::Percent(percent) => broken * 100 > percent * total,
+        }
+    }
+}
+
+impl std::str::FromStr for LinkDensity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid threshold {s:?}, expected a number like 10 or 50%");
+
+        match s.strip_suffix('%') {
+            Some(percent) => match percent.parse() {
+                Ok(percent) if percent <= 100 => Ok(LinkDensity::Percent(percent)),
+                _ => Err(invalid()),
+            },
+            None => s.parse().map(LinkDensity::Count).map_err(|_| invalid()),
+        }
+    }
+}
+
+#[test]
+fn test_link_density() {
+    assert_eq!("10".parse(), Ok(LinkDensity::Count(10)));
+    assert_eq!("50%".parse(), Ok(LinkDensity::Percent(50)));
+    assert!("150%".parse::<LinkDensity>().is_err());
+    assert!("lots".parse::<LinkDensity>().is_err());
+
+    assert!(LinkDensity::Count(10).is_exceeded(11, 100));
+    assert!(!LinkDensity::Count(10).is_exceeded(10, 100));
+    assert!(LinkDensity::Percent(50).is_exceeded(6, 10));
+    assert!(!LinkDensity::Percent(50).is_exceeded(5, 10));
+}
+
+/// A template for `--message-template`.
+#[derive(Clone, Debug, PartialEq)]
+struct MessageTemplate(Vec<TemplatePart>);
+
+#[derive(Clone, Debug, PartialEq)]
+enum TemplatePart {
+    Literal(String),
+    Path,
+    Href,
+    Line,
+    Column,
+    Kind,
+}
+
+impl MessageTemplate {
+    /// Render the template for a bad link in `path`. `{line}` and `{column}` are empty if the
+    /// position is unknown.
+    fn render(&self, path: &Path, link: &ReportedLink, kind: &str) -> String {
+        let mut rv = String::new();
+        for part in &self.0 {
+            match part {
+                TemplatePart::Literal(literal) => rv.push_str(literal),
+                TemplatePart::Path => rv.push_str(&path.display().to_string()),
+                TemplatePart::Href => {
+                    rv.push('/');
+                    rv.push_str(&link.href);
+                }
+                TemplatePart::Line => {
+                    if let Some(line) = link.line {
+                        rv.push_str(&line.to_string());
+                    }
+                }
+                TemplatePart::Column => {
+                    if let Some(column) = link.column {
+                        rv.push_str(&column.to_string());
+                    }
+                }
+                TemplatePart::Kind => rv.push_str(kind),
+            }
+        }
+        rv
+    }
+}
+
+impl std::str::FromStr for MessageTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut rest = s;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(TemplatePart::Literal(rest[..start].to_owned()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| format!("unclosed placeholder in {s:?}"))?;
+            parts.push(match &rest[start + 1..start + end] {
+                "path" => TemplatePart::Path,
+                "href" => TemplatePart::Href,
+                "line" => TemplatePart::Line,
+                "column" => TemplatePart::Column,
+                "kind" => TemplatePart::Kind,
+                name => {
+                    return Err(format!(
+                        "unknown placeholder {{{name}}}, expected {{path}}, {{href}}, {{line}}, {{column}} or {{kind}}"
+                    ))
+                }
+            });
+            rest = &rest[start + end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Literal(rest.to_owned()));
+        }
+        Ok(MessageTemplate(parts))
     }
 }
 
-fn print_github_actions_href_list(
+#[test]
+fn test_message_template() {
+    let link = ReportedLink {
+        line: Some(3),
+        column: None,
+        href: "foo.html".to_owned(),
+        element: "<a href>",
+        media: None,
+        referrer: None,
+        absolute: false,
+        redirect_from: None,
+    };
+    let template: MessageTemplate = "{path}:{line}: {kind} {href}".parse().unwrap();
+    assert_eq!(
+        template.render(Path::new("index.html"), &link, "bad link"),
+        "index.html:3: bad link /foo.html"
+    );
+
+    let link = ReportedLink { line: None, ..link };
+    let template: MessageTemplate = "Defekter Link in {path}: {href}".parse().unwrap();
+    assert_eq!(
+        template.render(Path::new("index.html"), &link, "bad link"),
+        "Defekter Link in index.html: /foo.html"
+    );
+
+    assert!("{path".parse::<MessageTemplate>().is_err());
+    assert!("{file}".parse::<MessageTemplate>().is_err());
+}
+
+fn print_report(
+    report: &Report,
+    check_anchors: bool,
+    github_actions: bool,
+    group_consecutive_lines: bool,
+    group_by_source: bool,
+    message_template: Option<&MessageTemplate>,
+    baselined_count: Option<usize>,
+) -> Result<(), Error> {
+    for file in &report.files {
+        println!("{}", file.path.display());
+
+        let mut entries: Vec<_> = file
+            .bad_links
+            .iter()
+            .map(|(link, occurrences)| (link, occurrences, false))
+            .chain(
+                file.bad_anchors
+                    .iter()
+                    .map(|(link, occurrences)| (link, occurrences, true)),
+            )
+            .collect();
+        // Links are ordered by line already, but bad links and bad anchors are listed separately.
+        if group_by_source {
+            entries.sort_by_key(|&(link, _, _)| link);
+        }
+
+        for (link, occurrences, is_anchor) in entries {
+            if is_anchor {
+                match message_template {
+                    Some(template) => {
+                        println!("{}", template.render(&file.path, link, "bad anchor"))
+                    }
+                    None => match id_reference(link) {
+                        Some((referrer, id)) => {
+                            print!("  error: {referrer} references missing id {id}");
+                            print_error_location(link, occurrences);
+                        }
+                        None => print_href_error("bad link", link, occurrences),
+                    },
+                }
+                continue;
+            }
+
+            let message = link.problem();
+            match (message_template, &link.redirect_from) {
+                (Some(template), _) => {
+                    println!("{}", template.render(&file.path, link, message))
+                }
+                // The rule in _redirects is what needs fixing, not a link in a document.
+                (None, Some(from)) => {
+                    println!(
+                        "  error: redirect {from} targets missing file /{}",
+                        link.href
+                    )
+                }
+                (None, None) => print_href_error(message, link, occurrences),
+            }
+        }
+
+        for (warning, count) in &file.warnings {
+            if *count > 1 {
+                println!("  warning: {} (×{count})", warning.message);
+            } else {
+                println!("  warning: {}", warning.message);
+            }
+        }
+
+        if github_actions {
+            if !file.bad_links.is_empty() {
+                print_github_actions_href_list(
+                    "error",
+                    "bad links",
+                    &file.path,
+                    group_consecutive_lines,
+                    file.bad_links
+                        .keys()
+                        .map(|link| (link.line, link.href.as_str())),
+                )?;
+            }
+
+            if !file.bad_anchors.is_empty() {
+                print_github_actions_href_list(
+                    "error",
+                    "bad anchors",
+                    &file.path,
+                    group_consecutive_lines,
+                    file.bad_anchors
+                        .keys()
+                        .map(|link| (link.line, link.href.as_str())),
+                )?;
+            }
+
+            if !file.warnings.is_empty() {
+                print_github_actions_href_list(
+                    "warning",
+                    "warnings",
+                    &file.path,
+                    group_consecutive_lines,
+                    file.warnings
+                        .keys()
+                        .map(|warning| (warning.line, warning.message.as_str())),
+                )?;
+            }
+        }
+
+        println!();
+    }
+
+    if let Some(ref unused_anchors) = report.unused_anchors {
+        println!("Unused anchors:");
+        for href in unused_anchors {
+            let (page, anchor) = href.split_at(href.find('#').unwrap_or(0));
+            println!("  /{page} {anchor}");
+        }
+        println!();
+    }
+
+    if let Some(stale_entries) = &report.stale_baseline_entries {
+        if !stale_entries.is_empty() {
+            println!("Baseline entries that are no longer broken, remove them from the baseline:");
+            for entry in stale_entries {
+                println!("  {} {} /{}", entry.kind, entry.path, entry.href);
+            }
+            println!();
+        }
+    }
+
+    if let Some(baselined_count) = baselined_count {
+        println!("Ignored {baselined_count} known broken links from baseline");
+    }
+
+    match report.unique_bad_links {
+        Some(unique) => println!(
+            "Found {} bad links ({} unique targets)",
+            report.bad_links, unique
+        ),
+        None => println!("Found {} bad links", report.bad_links),
+    }
+
+    if check_anchors {
+        println!("Found {} bad anchors", report.bad_anchors);
+    }
+
+    if let Some(ref unused_anchors) = report.unused_anchors {
+        println!("Found {} unused anchors", unused_anchors.len());
+    }
+
+    if report.warnings > 0 {
+        println!("Found {} warnings", report.warnings);
+    }
+
+    Ok(())
+}
+
+/// What references the id of a broken link from `--check-aria-references` or `--check-label-for`,
+/// and the id, like `("aria-describedby", "desc2")` or `("label", "email")`.
+fn id_reference(link: &ReportedLink) -> Option<(&str, &str)> {
+    let (_, id) = link.href.split_once('#')?;
+    Some((link.referrer?, id))
+}
+
+fn print_href_error(message: &'static str, link: &ReportedLink, occurrences: &Occurrences) {
+    let href = &link.href;
+    print!("  error: {message} /{href}");
+    if occurrences.is_written_differently(href) {
+        let spellings: Vec<_> = occurrences
+            .written_as
+            .iter()
+            .map(|raw_href| format!("{raw_href:?}"))
+            .collect();
+        print!(" (written as {})", spellings.join(", "));
+    }
+    print_error_location(link, occurrences);
+}
+
+/// Finish an error line with how often and where the link occurs.
+fn print_error_location(link: &ReportedLink, occurrences: &Occurrences) {
+    let ReportedLink { line, column, .. } = *link;
+
+    if occurrences.count > 1 {
+        print!(" (×{})", occurrences.count);
+    }
+    match (line, column) {
+        (Some(line), Some(column)) => print!(" at line {line}, col {column}"),
+        (Some(line), None) => print!(" at line {line}"),
+        _ => (),
+    }
+    println!(" (from {})", link.origin());
+}
+
+fn print_github_actions_href_list<'a>(
+    command: &'static str,
     message: &'static str,
     filepath: &Path,
-    hrefs: &BTreeSet<(Option<usize>, String)>,
+    group_lines: bool,
+    hrefs: impl Iterator<Item = (Option<usize>, &'a str)>,
 ) -> Result<(), Error> {
     let mut prev_lineno = None;
-    for (i, (lineno, href)) in hrefs.iter().enumerate() {
-        if prev_lineno != *lineno || i == 0 {
+    for (i, (lineno, href)) in hrefs.enumerate() {
+        if i == 0 || (!group_lines && prev_lineno != lineno) {
             print!(
-                "\n::error file={},line={}::{}:",
+                "\n::{} file={},line={}::{}:",
+                command,
                 filepath.canonicalize()?.display(),
                 lineno.unwrap_or(1),
                 message,
             );
         }
-        prev_lineno = *lineno;
+        prev_lineno = lineno;
 
         // %0A -- escaped newline
         //
         // https://github.community/t/what-is-the-correct-character-escaping-for-workflow-command-values-e-g-echo-xxxx/118465/5
         print!("%0A  {}", href);
+        // With a single annotation, its line only fits the first href.
+        if let (true, Some(lineno)) = (group_lines, lineno) {
+            print!(" (line {lineno})");
+        }
     }
 
     println!();
@@ -370,7 +1958,10 @@ fn dump_paragraphs(path: PathBuf) -> Result<(), Error> {
         Some(x) if HTML_FILES.contains(&x) => {
             let document = Document::new(Path::new(""), &path);
             document
-                .links::<DebugParagraphWalker<ParagraphHasher>>(&mut doc_buf, false)?
+                .links::<DebugParagraphWalker<ParagraphHasher>>(
+                    &mut doc_buf,
+                    &ParserOptions::default(),
+                )?
                 .filter_map(|link| Some((link.into_paragraph()?, None)))
                 .collect()
         }
@@ -388,10 +1979,37 @@ fn dump_paragraphs(path: PathBuf) -> Result<(), Error> {
     Ok(())
 }
 
-fn dump_external_links(base_path: PathBuf) -> Result<(), Error> {
+fn dump_external_links(
+    base_path: PathBuf,
+    exclude_external_domains: Vec<String>,
+) -> Result<(), Error> {
+    let excluded_domains: Vec<&str> = exclude_external_domains
+        .iter()
+        .flat_map(|domains| domains.split(','))
+        .map(str::trim)
+        .filter(|domain| !domain.is_empty())
+        .collect();
+    let is_excluded = |href: &str| {
+        external_link_host(href).is_some_and(|host| {
+            excluded_domains
+                .iter()
+                .any(|domain| host_matches_domain(host, domain))
+        })
+    };
+
     println!("Reading files");
-    let html_result =
-        extract_html_links::<UsedLinkCollector<_>, NoopParagraphWalker>(&base_path, true)?;
+    let html_result = extract_html_links::<UsedLinkCollector<_>, NoopParagraphWalker>(
+        &base_path,
+        &base_path,
+        &ParserOptions {
+            check_anchors: true,
+            ..Default::default()
+        },
+        None,
+        None,
+        false,
+        false,
+    )?;
 
     println!(
         "Checking {} links from {} files ({} documents)",
@@ -400,35 +2018,494 @@ fn dump_external_links(base_path: PathBuf) -> Result<(), Error> {
         html_result.documents_count,
     );
 
-    let used_links = html_result.collector.used_links.iter().peekable();
+    let mut reference_counts = BTreeMap::new();
 
-    for used_link in used_links {
-        if is_external_link(used_link.href.as_bytes()) {
+    for used_link in &html_result.collector.used_links {
+        if is_external_link(used_link.href.as_bytes()) && !is_excluded(&used_link.href) {
             println!("{}", used_link.href);
+            *reference_counts
+                .entry(used_link.href.as_str())
+                .or_insert(0usize) += 1;
+        }
+    }
+
+    // Most referenced links first, ties are broken alphabetically (BTreeMap order + stable sort)
+    let mut reference_counts: Vec<_> = reference_counts.into_iter().collect();
+    reference_counts.sort_by_key(|&(_, count)| cmp::Reverse(count));
+
+    println!();
+    println!("References per external link:");
+    for (href, count) in reference_counts {
+        println!("  {count} {href}");
+    }
+
+    mem::forget(html_result);
+
+    Ok(())
+}
+
+fn list_schemes(base_path: PathBuf) -> Result<(), Error> {
+    println!("Reading files");
+    let html_result = extract_html_links::<UsedLinkCollector<_>, NoopParagraphWalker>(
+        &base_path,
+        &base_path,
+        &ParserOptions::default(),
+        None,
+        None,
+        false,
+        false,
+    )?;
+
+    let mut scheme_counts = BTreeMap::new();
+    for used_link in &html_result.collector.used_links {
+        // Schemes are case-insensitive.
+        let scheme = if used_link.raw_href.starts_with("//") {
+            "// (protocol-relative)".to_owned()
+        } else if let Some(scheme) = url_scheme(used_link.raw_href.as_bytes()) {
+            std::str::from_utf8(scheme).unwrap().to_ascii_lowercase()
+        } else {
+            continue;
+        };
+        *scheme_counts.entry(scheme).or_insert(0usize) += 1;
+    }
+
+    // Most used schemes first, ties are broken alphabetically (BTreeMap order + stable sort)
+    let mut scheme_counts: Vec<_> = scheme_counts.into_iter().collect();
+    scheme_counts.sort_by_key(|&(_, count)| cmp::Reverse(count));
+
+    println!();
+    println!("Links per scheme:");
+    for (scheme, count) in scheme_counts {
+        println!("  {count} {scheme}");
+    }
+
+    mem::forget(html_result);
+
+    Ok(())
+}
+
+fn list_protocol_relative(base_path: PathBuf) -> Result<(), Error> {
+    let html_result = extract_html_links::<UsedLinkCollector<_>, NoopParagraphWalker>(
+        &base_path,
+        &base_path,
+        &ParserOptions::default(),
+        None,
+        None,
+        false,
+        false,
+    )?;
+
+    // Sorted by file, every link is only listed once per file.
+    let mut conversions = BTreeSet::new();
+    for used_link in &html_result.collector.used_links {
+        if used_link.raw_href.starts_with("//") {
+            conversions.insert((used_link.path.as_path(), used_link.raw_href.as_str()));
+        }
+    }
+
+    for (path, raw_href) in conversions {
+        println!("{}: {} -> https:{}", path.display(), raw_href, raw_href);
+    }
+
+    mem::forget(html_result);
+
+    Ok(())
+}
+
+fn check_sitemap(base_path: PathBuf, report_unlisted_pages: bool) -> Result<(), Error> {
+    let sitemap_path = Arc::new(base_path.join("sitemap.xml"));
+    let contents = fs::read_to_string(&*sitemap_path)
+        .with_context(|| format!("Failed to read {}", sitemap_path.display()))?;
+
+    println!("Reading files");
+    let parser_options = ParserOptions::default();
+    let mut html_result =
+        extract_html_links::<LocalLinksOnly<BrokenLinkCollector<_>>, NoopParagraphWalker>(
+            &base_path,
+            &base_path,
+            &parser_options,
+            None,
+            None,
+            false,
+            false,
+        )?;
+
+    // URLs in the sitemap are resolved like links from the root index.
+    let arena = bumpalo::Bump::new();
+    let root = Document::new(&base_path, &base_path.join("index.html"));
+    let mut listed_pages = BTreeSet::new();
+    for loc in sitemap_locs(&contents) {
+        let loc = arena.alloc_str(&loc);
+        let href = root.join(&arena, &parser_options, strip_origin(loc));
+        listed_pages.insert(href.0.to_owned());
+        html_result.collector.collector.ingest(Link::Uses(UsedLink {
+            href,
+            raw_href: loc,
+            path: sitemap_path.clone(),
+            paragraph: None,
+            element: "<loc>",
+            media: None,
+            referrer: None,
+            absolute: false,
+            position: None,
+        }));
+    }
+
+    let check_options = CheckOptions {
+        redirects: Redirects::read(&base_path)?,
+        ..Default::default()
+    };
+
+    let mut missing_count = 0;
+    for broken_link in html_result
+        .collector
+        .collector
+        .get_broken_links(&check_options)
+        .filter(|broken_link| broken_link.link.path == sitemap_path)
+    {
+        missing_count += 1;
+        println!(
+            "  error: sitemap lists missing page {}",
+            broken_link.link.raw_href
+        );
+    }
+
+    if report_unlisted_pages {
+        let mut unlisted_pages = BTreeSet::new();
+        for entry in walk_files(&base_path, false).collect::<Vec<_>>() {
+            let path = entry?.path();
+            let is_html = path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| HTML_FILES.contains(&extension));
+            if is_html {
+                let href = Document::new(&base_path, &path).href().0.to_owned();
+                if !listed_pages.contains(&href) {
+                    unlisted_pages.insert(href);
+                }
+            }
+        }
+
+        for href in unlisted_pages {
+            println!("  warning: page /{href} is not in the sitemap");
         }
     }
 
+    println!("Found {missing_count} missing pages in the sitemap");
+
+    mem::forget(html_result);
+
+    if missing_count > 0 {
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
+fn emit_inventory(
+    base_path: PathBuf,
+    check_anchors: bool,
+    format: InventoryFormat,
+) -> Result<(), Error> {
+    let html_result = extract_html_links::<InventoryCollector<_>, NoopParagraphWalker>(
+        &base_path,
+        &base_path,
+        &ParserOptions {
+            check_anchors,
+            ..Default::default()
+        },
+        None,
+        None,
+        false,
+        false,
+    )?;
+
+    let broken_links: BTreeSet<_> = html_result
+        .collector
+        .local_links
+        .collector
+        .get_broken_links(&CheckOptions {
+            check_anchors,
+            ..Default::default()
+        })
+        .map(|broken_link| (broken_link.link.path, broken_link.link.raw_href))
+        .collect();
+
+    // Links of a document stay in order, documents are sorted by path.
+    let mut used_links: Vec<_> = html_result.collector.used_links.used_links.iter().collect();
+    used_links.sort_by_key(|used_link| used_link.path.clone());
+
+    let entries: Vec<_> = used_links
+        .into_iter()
+        .map(|used_link| {
+            let external = is_external_link(used_link.raw_href.as_bytes());
+            InventoryEntry {
+                source: used_link.path.display().to_string(),
+                href: used_link.raw_href.clone(),
+                target: if external {
+                    used_link.raw_href.clone()
+                } else {
+                    format!("/{}", used_link.href)
+                },
+                external,
+                broken: if external {
+                    None
+                } else {
+                    Some(
+                        broken_links
+                            .contains(&(used_link.path.clone(), used_link.raw_href.clone())),
+                    )
+                },
+            }
+        })
+        .collect();
+
+    match format {
+        InventoryFormat::Json => println!("{}", serde_json::to_string_pretty(&entries)?),
+        InventoryFormat::Csv => print!("{}", inventory_to_csv(&entries)),
+    }
+
     mem::forget(html_result);
 
     Ok(())
 }
 
+fn normalize_links(
+    base_path: PathBuf,
+    trailing_slash: Option<TrailingSlash>,
+    write: bool,
+) -> Result<(), Error> {
+    let html_result = extract_html_links::<UsedLinkCollector<_>, NoopParagraphWalker>(
+        &base_path,
+        &base_path,
+        // Only the offsets of the links are used, to rewrite them where they are written.
+        &ParserOptions {
+            html_tab_size: Some(1),
+            ..Default::default()
+        },
+        None,
+        None,
+        false,
+        false,
+    )?;
+
+    // Per file and per href as written and its canonical form: the elements it is found in and
+    // where their attributes start.
+    let mut changes: BTreeMap<&Path, BTreeMap<(&str, String), Vec<_>>> = BTreeMap::new();
+
+    for used_link in &html_result.collector.used_links {
+        let extension = used_link.path.extension().and_then(|x| x.to_str());
+        if !extension.is_some_and(|extension| HTML_FILES.contains(&extension)) {
+            continue;
+        }
+
+        // Links in <object> are relative to its codebase, their canonical form is unknown.
+        if used_link.element.starts_with("<object ") {
+            continue;
+        }
+
+        let file = match used_link
+            .path
+            .strip_prefix(&base_path)
+            .ok()
+            .and_then(Path::to_str)
+        {
+            Some(file) => file,
+            None => continue,
+        };
+
+        if let Some(normalized) =
+            normalize_href(&base_path, file, &used_link.raw_href, trailing_slash)
+        {
+            changes
+                .entry(used_link.path.as_path())
+                .or_default()
+                .entry((used_link.raw_href.as_str(), normalized))
+                .or_default()
+                .push((
+                    used_link.element,
+                    used_link.position.map(|position| position.offset),
+                ));
+        }
+    }
+
+    let mut count = 0;
+    let mut changed_files = 0;
+    let mut skipped = 0;
+    for (path, changes) in &changes {
+        println!("{}", path.display());
+
+        let mut contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read file {}", path.display()))?;
+        let mut replacements = Vec::new();
+
+        for ((raw_href, normalized), usages) in changes {
+            let mut replaced = 0;
+            let mut written_differently = false;
+            let mut cannot_rewrite = BTreeSet::new();
+
+            for (element, offset) in usages {
+                let attribute = match link_attribute(element) {
+                    Some(attribute) => attribute,
+                    None => {
+                        cannot_rewrite.insert(*element);
+                        continue;
+                    }
+                };
+
+                // The value is only rewritten if it is written exactly like the href, and not with
+                // HTML entities or surrounding whitespace.
+                match offset
+                    .and_then(|offset| attribute_value_range(&contents, offset, attribute))
+                    .filter(|range| contents[range.clone()] == **raw_href)
+                {
+                    Some(range) => {
+                        replacements.push((range, normalized.as_str()));
+                        replaced += 1;
+                    }
+                    None => written_differently = true,
+                }
+            }
+
+            if replaced > 0 {
+                println!("  {raw_href} -> {normalized}");
+            }
+            if written_differently {
+                println!("  {raw_href} -> {normalized} (skipped, written differently in the file)");
+                skipped += 1;
+            }
+            for element in cannot_rewrite {
+                println!("  {raw_href} -> {normalized} (skipped, cannot rewrite {element})");
+                skipped += 1;
+            }
+        }
+
+        // Replace from the end of the file so that the remaining ranges stay valid.
+        replacements.sort_by_key(|(range, _)| range.start);
+        replacements.dedup_by_key(|(range, _)| range.start);
+        for (range, normalized) in replacements.iter().rev() {
+            contents.replace_range(range.clone(), normalized);
+        }
+
+        let file_count = replacements.len();
+        if file_count > 0 {
+            count += file_count;
+            changed_files += 1;
+            if write {
+                fs::write(path, contents)
+                    .with_context(|| format!("Failed to write file {}", path.display()))?;
+            }
+        }
+
+        println!();
+    }
+
+    if write {
+        println!("Normalized {count} links in {changed_files} files");
+    } else {
+        println!(
+            "Found {count} links to normalize in {changed_files} files, pass --write to rewrite them"
+        );
+    }
+
+    if skipped > 0 {
+        println!("Skipped {skipped} links that have to be changed by hand");
+    }
+
+    Ok(())
+}
+
+fn resolve_url(base_path: PathBuf, file: PathBuf, href: String) -> Result<(), Error> {
+    if !file.starts_with(&base_path) {
+        return Err(anyhow!(
+            "{} is not inside of {}",
+            file.display(),
+            base_path.display()
+        ));
+    }
+
+    let arena = bumpalo::Bump::new();
+    let document = Document::new(&base_path, &file);
+
+    println!("document: /{}", document.href());
+
+    if is_external_link(href.as_bytes()) {
+        println!("external link, not checked: {href}");
+        return Ok(());
+    }
+
+    let without_anchors = document.join(&arena, &ParserOptions::default(), &href);
+    println!("without anchors: /{without_anchors}");
+
+    let with_anchors = document.join(
+        &arena,
+        &ParserOptions {
+            check_anchors: true,
+            ..Default::default()
+        },
+        &href,
+    );
+    println!("with anchors: /{with_anchors}");
+
+    Ok(())
+}
+
 struct HtmlResult<C> {
     collector: C,
     documents_count: usize,
     file_count: usize,
+    /// Files that could not be read, only with `collect_read_errors`.
+    read_errors: Vec<FileError>,
+}
+
+impl<C> HtmlResult<C> {
+    fn new(collector: C) -> Self {
+        HtmlResult {
+            collector,
+            documents_count: 0,
+            file_count: 0,
+            read_errors: Vec::new(),
+        }
+    }
+
+    /// Record that the file at `path` could not be read, or abort if `collect_read_errors` is not
+    /// set.
+    fn add_read_error(
+        &mut self,
+        path: &Path,
+        error: Error,
+        collect_read_errors: bool,
+    ) -> Result<(), Error> {
+        if !collect_read_errors {
+            return Err(error.context(format!("Failed to read file {}", path.display())));
+        }
+
+        self.read_errors.push(FileError {
+            kind: FileErrorKind::ReadError,
+            path: path.to_owned(),
+            message: format!("{error:#}"),
+        });
+        Ok(())
+    }
 }
 
+/// All files in `base_path`, and with `include_directories` also all directories.
 fn walk_files(
     base_path: &Path,
+    include_directories: bool,
 ) -> impl ParallelIterator<Item = Result<jwalk::DirEntry<((), bool)>, jwalk::Error>> {
     WalkDirGeneric::<((), bool)>::new(base_path)
         .sort(true) // helps branch predictor (?)
         .skip_hidden(false)
-        .process_read_dir(|_, _, _, children| {
+        .process_read_dir(move |_, _, _, children| {
             for dir_entry_result in children.iter_mut() {
                 if let Ok(dir_entry) = dir_entry_result {
-                    dir_entry.client_state = dir_entry.file_type().is_file();
+                    let file_type = dir_entry.file_type();
+                    // Symlinks are not followed, but a symlink to a file (or to nothing) is served
+                    // like a file.
+                    dir_entry.client_state = file_type.is_file()
+                        || (file_type.is_symlink() && !dir_entry.path().is_dir())
+                        || (include_directories && file_type.is_dir());
                 }
             }
         })
@@ -451,98 +2528,355 @@ fn walk_files(
         })
 }
 
+/// Read NUL-separated paths, like the output of `git diff -z --name-only`.
+fn read_paths_null(mut reader: impl io::Read) -> Result<BTreeSet<PathBuf>, Error> {
+    let mut buf = Vec::new();
+    reader
+        .read_to_end(&mut buf)
+        .context("Failed to read paths from stdin")?;
+
+    buf.split(|&b| b == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| {
+            let path = std::str::from_utf8(path).context("Invalid unicode in path on stdin")?;
+            Ok(normalize_path(Path::new(path)))
+        })
+        .collect()
+}
+
+/// Drop `.` components, so that `./public/index.html` and `public/index.html` compare equal.
+fn normalize_path(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| *component != std::path::Component::CurDir)
+        .collect()
+}
+
+#[test]
+fn test_read_paths_null() {
+    let paths = read_paths_null(&b"./public/index.html\0public/my docs/a\nb.html\0\0"[..]).unwrap();
+    assert_eq!(
+        paths.into_iter().collect::<Vec<_>>(),
+        [
+            PathBuf::from("public/index.html"),
+            PathBuf::from("public/my docs/a\nb.html"),
+        ]
+    );
+}
+
+/// Whether `entry` is a symlink whose target does not exist.
+fn is_dangling_symlink(entry: &jwalk::DirEntry<((), bool)>) -> bool {
+    entry.file_type().is_symlink() && fs::metadata(entry.path()).is_err()
+}
+
+/// Collect links from all files in `root_path`. Only documents inside of `check_path` contribute
+/// used links and warnings, the others only define link targets. Files are read from `tar_files`
+/// instead of from disk if given. If `checked_files` is given, only those documents contribute
+/// used links. Files that cannot be read abort the run, unless `collect_read_errors` is set.
 fn extract_html_links<C: LinkCollector<P::Paragraph>, P: ParagraphWalker>(
-    base_path: &Path,
-    check_anchors: bool,
+    root_path: &Path,
+    check_path: &Path,
+    parser_options: &ParserOptions,
+    tar_files: Option<&TarFiles>,
+    checked_files: Option<&BTreeSet<PathBuf>>,
+    collect_read_errors: bool,
+    emit_events: bool,
 ) -> Result<HtmlResult<C>, Error> {
-    let result: Result<_, Error> = walk_files(base_path)
+    let files = match tar_files {
+        Some(tar_files) => Either::Left(tar_files.files_in(root_path).par_bridge().map(
+            |(path, contents)| {
+                let size = contents.len() as u64;
+                Ok((
+                    path.clone(),
+                    Some(contents.clone()),
+                    Some(size),
+                    false,
+                    false,
+                ))
+            },
+        )),
+        None => Either::Right(
+            walk_files(root_path, parser_options.allow_directory_listings).map(
+                |entry| -> Result<_, Error> {
+                    let entry = entry?;
+                    let is_dangling = is_dangling_symlink(&entry);
+                    let is_directory = entry.file_type().is_dir();
+                    // Only stat files when their size matters.
+                    let size = match parser_options.max_file_size {
+                        Some(_) if !is_dangling => Some(entry.metadata()?.len()),
+                        _ => None,
+                    };
+                    Ok((entry.path(), None, size, is_dangling, is_directory))
+                },
+            ),
+        ),
+    };
+
+    let result: Result<_, Error> = files
         .try_fold(
-            || (DocumentBuffers::default(), C::new(), 0, 0),
-            |(mut doc_buf, mut collector, mut documents_count, mut file_count), entry| {
-                let entry = entry?;
-                let path = entry.path();
-                let document = Document::new(base_path, &path);
-
-                collector.ingest(Link::Defines(DefinedLink {
-                    href: document.href(),
-                }));
-                file_count += 1;
-
-                if !document
-                    .path
-                    .extension()
-                    .and_then(|extension| Some(HTML_FILES.contains(&extension.to_str()?)))
-                    .unwrap_or(false)
-                {
-                    return Ok((doc_buf, collector, documents_count, file_count));
+            || (DocumentBuffers::default(), HtmlResult::new(C::new())),
+            |(mut doc_buf, mut result), file| {
+                let (path, contents, size, is_dangling, is_directory) = file?;
+                if is_dangling && parser_options.resolve_symlinked_targets {
+                    return Ok((doc_buf, result));
                 }
 
-                for link in document
-                    .links::<P>(&mut doc_buf, check_anchors)
-                    .with_context(|| format!("Failed to read file {}", document.path.display()))?
-                {
-                    collector.ingest(link);
+                // With --allow-directory-listings, the server lists directories without index.
+                if is_directory {
+                    let mut href = Document::new(root_path, &path).href().0.to_owned();
+                    if parser_options.strict_trailing_slash && !href.is_empty() {
+                        href.push('/');
+                    }
+                    result.collector.ingest(Link::Defines(DefinedLink {
+                        href: Href(&href),
+                        unexpected_element: None,
+                        raw_anchor: None,
+                    }));
+                    return Ok((doc_buf, result));
+                }
+
+                let mut document = Document::new(root_path, &path);
+                if parser_options.directory_index == DirectoryIndex::None {
+                    document = document.without_directory_index();
+                }
+                if let Some(contents) = contents {
+                    document = document.with_contents(contents);
                 }
 
+                let href = if parser_options.strict_trailing_slash {
+                    document.href_with_trailing_slash()
+                } else {
+                    document.href().0.into()
+                };
+                let extension = document.path.extension().and_then(|x| x.to_str());
+                let is_html = document.is_brotli
+                    || document.is_xml
+                    || extension.is_some_and(|extension| HTML_FILES.contains(&extension));
+
+                // With --follow-meta-refresh-chain, a page that redirects is not a link target
+                // itself, like the source of a rule in _redirects. We only know after reading it.
+                let defer_definition =
+                    parser_options.follow_meta_refresh_chain && is_html && !is_dangling;
+                if !defer_definition {
+                    result.collector.ingest(Link::Defines(DefinedLink {
+                        href: Href(&href),
+                        unexpected_element: None,
+                        raw_anchor: None,
+                    }));
+                }
+                result.file_count += 1;
+
+                // There is nothing to read from a dangling symlink.
+                if is_dangling {
+                    return Ok((doc_buf, result));
+                }
+
+                let is_checked = path.starts_with(check_path)
+                    && checked_files.is_none_or(|files| files.contains(&normalize_path(&path)));
+
+                let is_parsed =
+                    is_html || (parser_options.check_css_imports && extension == Some("css"));
+                let too_large = size.filter(|size| {
+                    is_parsed && parser_options.max_file_size.is_some_and(|max| *size > max)
+                });
+                if let Some(size) = too_large {
+                    if is_checked {
+                        result.collector.ingest(Link::Warns(Warning {
+                            path: document.path.clone(),
+                            message: format!(
+                                "skipped file of {size} bytes, larger than --max-file-size"
+                            ),
+                        }));
+                    }
+                    if defer_definition {
+                        result.collector.ingest(Link::Defines(DefinedLink {
+                            href: Href(&href),
+                            unexpected_element: None,
+                            raw_anchor: None,
+                        }));
+                    }
+                    return Ok((doc_buf, result));
+                }
+
+                if parser_options.check_css_imports && is_checked && extension == Some("css") {
+                    match document.css_links(&mut doc_buf, parser_options) {
+                        Ok(links) => {
+                            for link in links {
+                                result.collector.ingest(link);
+                            }
+                            if emit_events {
+                                Event::FileScanned {
+                                    path: &document.path,
+                                }
+                                .emit()?;
+                            }
+                        }
+                        Err(e) => result.add_read_error(&document.path, e, collect_read_errors)?,
+                    }
+
+                    doc_buf.reset();
+                    return Ok((doc_buf, result));
+                }
+
+                if !is_html {
+                    return Ok((doc_buf, result));
+                }
+
+                // Outside of check_path, we only care about anchors defined in the document.
+                if !is_checked && !parser_options.check_anchors && !defer_definition {
+                    return Ok((doc_buf, result));
+                }
+
+                let mut is_redirect = false;
+                let is_read = match document.links::<P>(&mut doc_buf, parser_options) {
+                    Ok(links) => {
+                        for link in links {
+                            is_redirect |= matches!(link, Link::Redirects(_));
+                            if is_checked || matches!(link, Link::Defines(_) | Link::Redirects(_)) {
+                                result.collector.ingest(link);
+                            }
+                        }
+                        true
+                    }
+                    Err(e) => {
+                        result.add_read_error(&document.path, e, collect_read_errors)?;
+                        false
+                    }
+                };
+
                 doc_buf.reset();
 
-                documents_count += 1;
+                if defer_definition && !is_redirect {
+                    result.collector.ingest(Link::Defines(DefinedLink {
+                        href: Href(&href),
+                        unexpected_element: None,
+                        raw_anchor: None,
+                    }));
+                }
+
+                if is_read {
+                    result.documents_count += 1;
+                    if emit_events {
+                        Event::FileScanned {
+                            path: &document.path,
+                        }
+                        .emit()?;
+                    }
+                }
 
-                Ok((doc_buf, collector, documents_count, file_count))
+                Ok((doc_buf, result))
             },
         )
-        .map(|result| {
-            result.map(|(_, collector, documents_count, file_count)| {
-                (collector, documents_count, file_count)
-            })
-        })
+        .map(|result| result.map(|(_, result)| result))
         .try_reduce(
-            || (C::new(), 0, 0),
-            |(mut collector, mut documents_count, mut file_count),
-             (collector2, documents_count2, file_count2)| {
-                collector.merge(collector2);
-                documents_count += documents_count2;
-                file_count += file_count2;
-                Ok((collector, documents_count, file_count))
+            || HtmlResult::new(C::new()),
+            |mut result, result2| {
+                result.collector.merge(result2.collector);
+                result.documents_count += result2.documents_count;
+                result.file_count += result2.file_count;
+                result.read_errors.extend(result2.read_errors);
+                Ok(result)
             },
         );
 
-    let (collector, documents_count, file_count) = result?;
+    let mut result = result?;
+    result.read_errors.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(result)
+}
+
+type MarkdownResult<P> = BTreeMap<P, Vec<(DocumentSource, Option<usize>)>>;
+
+/// How to parse source files, see `--sources-format`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum SourcesFormat {
+    Markdown,
+    Html,
+}
 
-    Ok(HtmlResult {
-        collector,
-        documents_count,
-        file_count,
-    })
+impl SourcesFormat {
+    fn from_extension(extension: &str) -> Option<Self> {
+        if MARKDOWN_FILES.contains(&extension) {
+            Some(SourcesFormat::Markdown)
+        } else if HTML_FILES.contains(&extension) {
+            Some(SourcesFormat::Html)
+        } else {
+            None
+        }
+    }
 }
 
-type MarkdownResult<P> = BTreeMap<P, Vec<(DocumentSource, usize)>>;
+impl std::str::FromStr for SourcesFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "markdown" => Ok(SourcesFormat::Markdown),
+            "html" => Ok(SourcesFormat::Html),
+            _ => Err(format!(
+                "unknown sources format {s:?}, expected markdown or html"
+            )),
+        }
+    }
+}
 
+/// Collect paragraphs from all source files in `sources_path`. Files are parsed according to
+/// `sources_format`, or by their extension if it is not given. With `sources_glob`, files whose
+/// path relative to `sources_path` does not match are skipped.
 fn extract_markdown_paragraphs<P: ParagraphWalker>(
     sources_path: &Path,
+    sources_format: Option<SourcesFormat>,
+    sources_glob: Option<&Glob>,
 ) -> Result<MarkdownResult<P::Paragraph>, Error> {
-    let results: Vec<Result<_, Error>> = walk_files(sources_path)
-        .try_fold(Vec::new, |mut paragraphs, entry| {
-            let entry = entry?;
-            let source = DocumentSource::new(entry.path());
-
-            if !source
-                .path
-                .extension()
-                .and_then(|extension| Some(MARKDOWN_FILES.contains(&extension.to_str()?)))
-                .unwrap_or(false)
-            {
-                return Ok(paragraphs);
-            }
+    let results: Vec<Result<_, Error>> = walk_files(sources_path, false)
+        .try_fold(
+            || (DocumentBuffers::default(), Vec::new()),
+            |(mut doc_buf, mut paragraphs), entry| {
+                let entry = entry?;
+                if is_dangling_symlink(&entry) {
+                    return Ok((doc_buf, paragraphs));
+                }
+                let path = entry.path();
+                if let Some(glob) = sources_glob {
+                    let relative_path = path.strip_prefix(sources_path).unwrap_or(&path);
+                    if !glob.matches(relative_path) {
+                        return Ok((doc_buf, paragraphs));
+                    }
+                }
+                let source = DocumentSource::new(path);
+                let format = sources_format.or_else(|| {
+                    source
+                        .path
+                        .extension()
+                        .and_then(|extension| extension.to_str())
+                        .and_then(SourcesFormat::from_extension)
+                });
+
+                if format == Some(SourcesFormat::Markdown) {
+                    for (paragraph, lineno) in source
+                        .paragraphs::<P>()
+                        .with_context(|| format!("Failed to read file {}", source.path.display()))?
+                    {
+                        paragraphs.push((source.clone(), (paragraph, Some(lineno))));
+                    }
+                } else if format == Some(SourcesFormat::Html) {
+                    // HTML partials are hashed exactly like the HTML output, so their paragraphs
+                    // match up. We cannot determine line numbers for them though.
+                    let document = Document::new(sources_path, &source.path);
+                    let html_paragraphs: BTreeSet<_> = document
+                        .links::<P>(&mut doc_buf, &ParserOptions::default())
+                        .with_context(|| format!("Failed to read file {}", source.path.display()))?
+                        .filter_map(|link| link.into_paragraph())
+                        .collect();
+                    doc_buf.reset();
+
+                    for paragraph in html_paragraphs {
+                        paragraphs.push((source.clone(), (paragraph, None)));
+                    }
+                }
 
-            for paragraph_and_lineno in source
-                .paragraphs::<P>()
-                .with_context(|| format!("Failed to read file {}", source.path.display()))?
-            {
-                paragraphs.push((source.clone(), paragraph_and_lineno));
-            }
-            Ok(paragraphs)
-        })
+                Ok((doc_buf, paragraphs))
+            },
+        )
+        .map(|result| result.map(|(_, paragraphs)| paragraphs))
         .collect();
 
     let mut paragraps_to_sourcefile = BTreeMap::new();
@@ -562,11 +2896,21 @@ fn extract_markdown_paragraphs<P: ParagraphWalker>(
 fn match_all_paragraphs(base_path: PathBuf, sources_path: PathBuf) -> Result<(), Error> {
     println!("Reading files");
     let html_result = extract_html_links::<LocalLinksOnly<UsedLinkCollector<_>>, ParagraphHasher>(
-        &base_path, true,
+        &base_path,
+        &base_path,
+        &ParserOptions {
+            check_anchors: true,
+            ..Default::default()
+        },
+        None,
+        None,
+        false,
+        false,
     )?;
 
     println!("Reading source files");
-    let paragraps_to_sourcefile = extract_markdown_paragraphs::<ParagraphHasher>(&sources_path)?;
+    let paragraps_to_sourcefile =
+        extract_markdown_paragraphs::<ParagraphHasher>(&sources_path, None, None)?;
 
     println!("Calculating");
     let mut total_links = 0;
@@ -67,10 +67,8 @@ impl DocumentSource {
                     }
                     in_paragraph = false;
                 }
-                Event::Text(text) | Event::Code(text) => {
-                    if in_paragraph {
-                        walker.update(text.as_bytes());
-                    }
+                Event::Text(text) | Event::Code(text) if in_paragraph => {
+                    walker.update(text.as_bytes());
                 }
                 _ => {}
             }
@@ -0,0 +1,274 @@
+//! The `normalize` subcommand: Rewrite links to the canonical form of their target, such as
+//! `../docs/./guide.html` to `guide.html`.
+
+use std::ops::Range;
+use std::path::Path;
+use std::str;
+
+use bumpalo::collections::String as BumpString;
+
+use crate::html::push_and_canonicalize;
+use crate::urls::is_external_link;
+
+/// Whether to add or remove trailing slashes of links to directories, see `--trailing-slash`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TrailingSlash {
+    Add,
+    Remove,
+}
+
+impl str::FromStr for TrailingSlash {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "add" => Ok(TrailingSlash::Add),
+            "remove" => Ok(TrailingSlash::Remove),
+            _ => Err(format!(
+                "unknown trailing slash policy {s:?}, expected add or remove"
+            )),
+        }
+    }
+}
+
+/// The canonical form of `raw_href`, a link in the document at `file` relative to `root_path`, or
+/// `None` if it is already canonical. Absolute links stay absolute, relative links stay relative,
+/// query strings and fragments are kept as written.
+pub fn normalize_href(
+    root_path: &Path,
+    file: &str,
+    raw_href: &str,
+    trailing_slash: Option<TrailingSlash>,
+) -> Option<String> {
+    let qs_start = raw_href.find(&['?', '#'][..]).unwrap_or(raw_href.len());
+    let (path, rest) = raw_href.split_at(qs_start);
+    if path.is_empty() || is_external_link(raw_href.as_bytes()) {
+        return None;
+    }
+
+    let arena = bumpalo::Bump::new();
+    let mut target = BumpString::from_str_in(file, &arena);
+    push_and_canonicalize(&mut target, &lowercase_escapes(path), false, true);
+
+    let is_directory = target.is_empty() || target.ends_with('/');
+    let target = target.trim_end_matches('/');
+    let add_slash = match trailing_slash {
+        Some(TrailingSlash::Add) => {
+            is_directory || (!target.is_empty() && root_path.join(target).is_dir())
+        }
+        Some(TrailingSlash::Remove) => false,
+        None => is_directory,
+    };
+
+    let mut normalized = if path.starts_with('/') {
+        format!("/{target}")
+    } else {
+        relative_href(file, target, add_slash)
+    };
+
+    if normalized.is_empty() {
+        normalized.push_str("./");
+    } else if add_slash && !normalized.ends_with('/') {
+        normalized.push('/');
+    }
+    normalized.push_str(rest);
+
+    if normalized == raw_href {
+        None
+    } else {
+        Some(normalized)
+    }
+}
+
+/// The href that points from the document at `file` to `target`, both relative to the root.
+/// Without a trailing slash, a link to a parent directory has to name it, like `../docs`.
+fn relative_href(file: &str, target: &str, trailing_slash: bool) -> String {
+    let directory: Vec<&str> = match file.rfind('/') {
+        Some(i) => file[..i].split('/').collect(),
+        None => Vec::new(),
+    };
+    let target: Vec<&str> = target.split('/').filter(|s| !s.is_empty()).collect();
+
+    let mut common = directory
+        .iter()
+        .zip(&target)
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common == target.len() && common > 0 && !trailing_slash {
+        common -= 1;
+    }
+
+    let mut href = "../".repeat(directory.len() - common);
+    href.push_str(&target[common..].join("/"));
+    href
+}
+
+/// Lowercase the hex digits of percent-encoded characters, so that links to the same file are
+/// written the same way.
+fn lowercase_escapes(path: &str) -> String {
+    let mut rv = String::with_capacity(path.len());
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        rv.push(c);
+        if c == '%' {
+            for c in chars.by_ref().take(2) {
+                rv.push(c.to_ascii_lowercase());
+            }
+        }
+    }
+    rv
+}
+
+/// The attribute that a link found in `element`, such as `<a href>`, is written in. `None` if the
+/// link cannot be rewritten on its own: `srcset`, `ping` and `<meta content>` hold more than the
+/// link, and links in `<object>` are relative to its `codebase` instead of the document.
+pub fn link_attribute(element: &str) -> Option<&str> {
+    if element == "<a download>" {
+        return Some("href");
+    }
+
+    let (tag, attribute) = element
+        .strip_prefix('<')?
+        .strip_suffix('>')?
+        .split_once(' ')?;
+    let is_attribute_name = attribute
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == ':');
+    if !is_attribute_name
+        || matches!(tag, "meta" | "object")
+        || matches!(attribute, "srcset" | "ping")
+    {
+        return None;
+    }
+
+    Some(attribute)
+}
+
+/// The byte range of the value of `attribute` in `contents`, where the parser found the attribute
+/// to start at `offset`. `None` if a different attribute starts there.
+pub fn attribute_value_range(
+    contents: &str,
+    offset: usize,
+    attribute: &str,
+) -> Option<Range<usize>> {
+    let name = contents.get(offset..offset + attribute.len())?;
+    if !name.eq_ignore_ascii_case(attribute) {
+        return None;
+    }
+
+    let skip_whitespace = |i: usize| {
+        contents[i..]
+            .find(|c: char| !c.is_ascii_whitespace())
+            .map_or(contents.len(), |j| i + j)
+    };
+    let mut i = skip_whitespace(offset + attribute.len());
+    if !contents[i..].starts_with('=') {
+        return None;
+    }
+    i = skip_whitespace(i + 1);
+
+    let (start, end) = match contents[i..].chars().next()? {
+        quote @ ('"' | '\'') => (i + 1, contents[i + 1..].find(quote)? + i + 1),
+        _ => (
+            i,
+            contents[i..]
+                .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                .map_or(contents.len(), |j| i + j),
+        ),
+    };
+    Some(start..end)
+}
+
+#[test]
+fn test_normalize_href() {
+    let root = Path::new("/nonexistent");
+    let normalize = |file, raw_href| normalize_href(root, file, raw_href, None);
+
+    assert_eq!(
+        normalize("docs/intro.html", "../docs/./guide.html"),
+        Some("guide.html".to_owned())
+    );
+    assert_eq!(
+        normalize("docs/intro.html", "..//docs/guide.html?x=1#setup"),
+        Some("guide.html?x=1#setup".to_owned())
+    );
+    assert_eq!(
+        normalize("docs/intro.html", "/docs/../about/"),
+        Some("/about/".to_owned())
+    );
+    assert_eq!(
+        normalize("docs/intro.html", "../docs"),
+        None,
+        "a link to the parent directory has to name it"
+    );
+    assert_eq!(
+        normalize("docs/intro.html", "./"),
+        None,
+        "a link to the own directory is ./"
+    );
+    assert_eq!(
+        normalize("docs/intro.html", "../docs/"),
+        Some("./".to_owned())
+    );
+    assert_eq!(
+        normalize("index.html", "../../blog/"),
+        Some("blog/".to_owned())
+    );
+    assert_eq!(
+        normalize("docs/intro.html", "caf%C3%A9.html"),
+        Some("caf%c3%a9.html".to_owned())
+    );
+    assert_eq!(normalize("docs/intro.html", "guide.html"), None);
+    assert_eq!(normalize("docs/intro.html", "#setup"), None);
+    assert_eq!(
+        normalize("docs/intro.html", "https://example.com/a/../b"),
+        None
+    );
+
+    let remove = |raw_href| {
+        normalize_href(
+            root,
+            "docs/intro.html",
+            raw_href,
+            Some(TrailingSlash::Remove),
+        )
+    };
+    assert_eq!(remove("../blog/"), Some("../blog".to_owned()));
+    assert_eq!(remove("/blog/#top"), Some("/blog#top".to_owned()));
+    assert_eq!(remove("../blog"), None);
+}
+
+#[test]
+fn test_attribute_value_range() {
+    let contents =
+        r#"<a href="a.html"> <a HREF = 'b.html'> <a href=c.html> <a href=d.html/> <a href>"#;
+    let value = |offset, attribute| {
+        attribute_value_range(contents, offset, attribute).map(|range| &contents[range])
+    };
+
+    assert_eq!(value(3, "href"), Some("a.html"));
+    assert_eq!(value(21, "href"), Some("b.html"));
+    assert_eq!(value(41, "href"), Some("c.html"));
+    assert_eq!(value(57, "href"), Some("d.html/"));
+    assert_eq!(value(74, "href"), None, "an attribute without a value");
+    assert_eq!(value(3, "src"), None);
+    assert_eq!(value(3, "hre"), None);
+}
+
+#[test]
+fn test_link_attribute() {
+    assert_eq!(link_attribute("<a href>"), Some("href"));
+    assert_eq!(link_attribute("<a download>"), Some("href"));
+    assert_eq!(link_attribute("<a xlink:href>"), Some("xlink:href"));
+    assert_eq!(link_attribute("<button formaction>"), Some("formaction"));
+    assert_eq!(link_attribute("<img srcset>"), None);
+    assert_eq!(link_attribute("<source srcset>"), None);
+    assert_eq!(
+        link_attribute("<source srcset media=\"(min-width: 800px)\">"),
+        None
+    );
+    assert_eq!(link_attribute("<meta http-equiv=refresh>"), None);
+    assert_eq!(link_attribute("<a ping>"), None);
+    assert_eq!(link_attribute("<object data>"), None);
+    assert_eq!(link_attribute("url()"), None);
+}
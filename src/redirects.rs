@@ -0,0 +1,241 @@
+//! Support for `_redirects` files as used by Netlify and Cloudflare Pages. Links to the source of
+//! a redirect are not broken, as the host redirects them to the target.
+//!
+//! https://docs.netlify.com/routing/redirects/
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use anyhow::{Context, Error};
+
+use crate::urls::is_external_link;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Redirect {
+    /// Path segments of the source, which may contain `:placeholder` segments and end with `*`.
+    from: Vec<String>,
+    to: String,
+    /// Whether the host actually redirects, as opposed to serving the target under the source URL
+    /// (status 200).
+    is_redirect: bool,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Redirects {
+    redirects: Vec<Redirect>,
+}
+
+/// How a link is resolved by a `_redirects` rule.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RedirectMatch<'a> {
+    pub to: &'a str,
+    pub is_redirect: bool,
+}
+
+impl Redirects {
+    /// Read `_redirects` in the root of the site, if there is one.
+    pub fn read(root_path: &Path) -> Result<Self, Error> {
+        let path = root_path.join("_redirects");
+        match fs::read_to_string(&path) {
+            Ok(contents) => Ok(Self::parse(&contents)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    pub fn parse(contents: &str) -> Self {
+        let redirects = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                // Conditions like `id=:id` or `Country=us` are ignored, we do not know the query
+                // string or who is visiting.
+                let mut parts = line
+                    .split_whitespace()
+                    .filter(|part| !part.contains('=') || part.contains('/'));
+                let from = parts.next()?;
+                let to = parts.next()?;
+                // Rules with 4xx statuses serve custom error pages, the link is still broken.
+                let is_redirect = match parts.next() {
+                    None => true,
+                    Some(status) if status.starts_with('3') => true,
+                    Some(status) if status.starts_with('2') => false,
+                    Some(_) => return None,
+                };
+
+                Some(Redirect {
+                    from: from
+                        .split('/')
+                        .filter(|segment| !segment.is_empty())
+                        .map(str::to_owned)
+                        .collect(),
+                    to: to.to_owned(),
+                    is_redirect,
+                })
+            })
+            .collect();
+
+        Redirects { redirects }
+    }
+
+    /// Find the rule that applies to `href`, a canonicalized href without anchor. Like on Netlify,
+    /// the first matching rule wins.
+    pub fn resolve(&self, href: &str) -> Option<RedirectMatch<'_>> {
+        let segments: Vec<&str> = href.split('/').filter(|s| !s.is_empty()).collect();
+
+        self.redirects
+            .iter()
+            .find(|redirect| matches_segments(&redirect.from, &segments))
+            .map(|redirect| RedirectMatch {
+                to: &redirect.to,
+                is_redirect: redirect.is_redirect,
+            })
+    }
+
+    /// The URL that `href` is redirected to, with placeholders and splats filled in. `None` if no
+    /// rule applies or the host serves the target under the same URL (status 200).
+    pub fn redirect_target(&self, href: &str) -> Option<String> {
+        let segments: Vec<&str> = href.split('/').filter(|s| !s.is_empty()).collect();
+        let redirect = self
+            .redirects
+            .iter()
+            .find(|redirect| matches_segments(&redirect.from, &segments))?;
+        if !redirect.is_redirect {
+            return None;
+        }
+
+        let mut placeholders = Vec::new();
+        for (i, pattern) in redirect.from.iter().enumerate() {
+            if pattern == "*" {
+                placeholders.push(("splat", segments[i.min(segments.len())..].join("/")));
+                break;
+            } else if let Some(name) = pattern.strip_prefix(':') {
+                placeholders.push((name, segments[i].to_owned()));
+            }
+        }
+
+        let target = redirect
+            .to
+            .split('/')
+            .map(|segment| {
+                segment
+                    .strip_prefix(':')
+                    .and_then(|name| placeholders.iter().find(|(n, _)| *n == name))
+                    .map_or(segment, |(_, value)| value.as_str())
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        Some(target)
+    }
+
+    /// The source and target of every rule that redirects to a fixed local URL, without
+    /// placeholders.
+    pub fn local_targets(&self) -> impl Iterator<Item = (String, &str)> {
+        self.redirects
+            .iter()
+            .filter(|redirect| {
+                !is_external_link(redirect.to.as_bytes())
+                    && !redirect
+                        .to
+                        .split('/')
+                        .any(|segment| segment.starts_with(':'))
+            })
+            .map(|redirect| {
+                (
+                    format!("/{}", redirect.from.join("/")),
+                    redirect.to.as_str(),
+                )
+            })
+    }
+}
+
+fn matches_segments(pattern: &[String], segments: &[&str]) -> bool {
+    match (pattern.split_first(), segments.split_first()) {
+        (Some((first, _)), _) if first == "*" => true,
+        (Some((first, pattern)), Some((segment, segments))) => {
+            (first.starts_with(':') || first == segment) && matches_segments(pattern, segments)
+        }
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+#[test]
+fn test_redirects() {
+    let redirects = Redirects::parse(
+        "
+        # comment
+        /old-page     /new-page
+        /docs/        /guide/ 301
+        /blog/*       /news/:splat
+        /users/:id/   /profiles/:id 302
+        /app/*        /index.html 200
+        /search  q=:q  /find 301
+        /broken
+        /*            /404.html 404
+        ",
+    );
+
+    let resolve = |href| redirects.resolve(href).map(|m| (m.to, m.is_redirect));
+
+    assert_eq!(resolve("old-page"), Some(("/new-page", true)));
+    assert_eq!(resolve("docs"), Some(("/guide/", true)));
+    assert_eq!(resolve("blog"), Some(("/news/:splat", true)));
+    assert_eq!(resolve("blog/2020/post.html"), Some(("/news/:splat", true)));
+    assert_eq!(resolve("users/alice"), Some(("/profiles/:id", true)));
+    assert_eq!(resolve("users/alice/settings"), None);
+    assert_eq!(resolve("app/settings"), Some(("/index.html", false)));
+    assert_eq!(resolve("search"), Some(("/find", true)));
+    assert_eq!(resolve("broken"), None);
+    assert_eq!(resolve("new-page"), None);
+}
+
+#[test]
+fn test_redirect_target() {
+    let redirects = Redirects::parse(
+        "
+        /old-page     /new-page
+        /blog/*       /news/:splat
+        /users/:id/   /profiles/:id 302
+        /app/*        /index.html 200
+        ",
+    );
+
+    assert_eq!(
+        redirects.redirect_target("old-page"),
+        Some("/new-page".to_owned())
+    );
+    assert_eq!(
+        redirects.redirect_target("blog/2020/post.html"),
+        Some("/news/2020/post.html".to_owned())
+    );
+    assert_eq!(redirects.redirect_target("blog"), Some("/news/".to_owned()));
+    assert_eq!(
+        redirects.redirect_target("users/alice"),
+        Some("/profiles/alice".to_owned())
+    );
+    assert_eq!(redirects.redirect_target("app/settings"), None);
+    assert_eq!(redirects.redirect_target("new-page"), None);
+}
+
+#[test]
+fn test_local_targets() {
+    let redirects = Redirects::parse(
+        "
+        /old-page     /new-page
+        /blog/*       /news/:splat
+        /app/*        /index.html 200
+        /external     https://example.com/
+        ",
+    );
+
+    assert_eq!(
+        redirects.local_targets().collect::<Vec<_>>(),
+        [
+            ("/old-page".to_owned(), "/new-page"),
+            ("/app/*".to_owned(), "/index.html"),
+        ]
+    );
+}
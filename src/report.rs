@@ -0,0 +1,637 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::str;
+use std::time::Instant;
+
+use schemars::JsonSchema;
+use serde::{Serialize, Serializer};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    /// One JSON event per line, see `Event`.
+    Ndjson,
+}
+
+impl str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            _ => Err(format!(
+                "unknown format {s:?}, expected text, json or ndjson"
+            )),
+        }
+    }
+}
+
+/// The result of checking a site, as printed by `--format json`.
+#[derive(Debug, Default, Serialize, JsonSchema)]
+pub struct Report {
+    /// All files that contain broken links or warnings. If --sources is set, source files are
+    /// listed before HTML files.
+    pub files: Vec<FileReport>,
+    /// Anchors that are defined but never linked to, only present with --report-unused-anchors.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unused_anchors: Option<Vec<String>>,
+    pub bad_links: usize,
+    /// Number of distinct targets of bad links, only present with --dedupe-across-files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unique_bad_links: Option<usize>,
+    pub bad_anchors: usize,
+    pub warnings: usize,
+    /// Files that could not be read, only present if there are any. Like bad links, they make
+    /// the check fail.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FileError>,
+    /// Entries of the --baseline file whose links are not broken anymore and can be removed from
+    /// it, only present with --baseline.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stale_baseline_entries: Option<Vec<StaleBaselineEntry>>,
+    /// Whether the check passed, which is also when hyperlink exits with status 0. Always present,
+    /// so that a clean run can be told apart from a crash.
+    pub success: bool,
+}
+
+impl Report {
+    /// Render broken links and anchors as a GitHub-flavored Markdown table, for
+    /// `$GITHUB_STEP_SUMMARY`.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("## hyperlink\n\n");
+
+        if self.bad_links == 0 && self.bad_anchors == 0 {
+            out.push_str("No bad links found.\n");
+            return out;
+        }
+
+        writeln!(
+            out,
+            "Found {} bad links and {} bad anchors.\n",
+            self.bad_links, self.bad_anchors
+        )
+        .unwrap();
+        out.push_str("| File | Problem | Link | Count |\n");
+        out.push_str("| --- | --- | --- | --- |\n");
+
+        for file in &self.files {
+            let problems = file
+                .bad_links
+                .iter()
+                .map(|link| (link.0.problem(), link))
+                .chain(file.bad_anchors.iter().map(|link| ("bad anchor", link)));
+
+            for (problem, (link, occurrences)) in problems {
+                let path = match link.line {
+                    Some(line) => format!("{}:{}", file.path.display(), line),
+                    None => file.path.display().to_string(),
+                };
+                writeln!(
+                    out,
+                    "| `{}` | {} | `/{}` | {} |",
+                    escape_table_cell(&path),
+                    problem,
+                    escape_table_cell(&link.href),
+                    occurrences.count
+                )
+                .unwrap();
+            }
+        }
+
+        out
+    }
+
+    /// Print the report as `--format ndjson` events, after the `file_scanned` events.
+    pub fn emit_events(&self) -> Result<(), serde_json::Error> {
+        for file in &self.files {
+            let links = file
+                .bad_links
+                .iter()
+                .map(|link| ("link", link))
+                .chain(file.bad_anchors.iter().map(|link| ("anchor", link)));
+            for (kind, (link, occurrences)) in links {
+                Event::BrokenLink {
+                    path: &file.path,
+                    kind,
+                    link,
+                    count: occurrences.count,
+                    written_as: &occurrences.written_as,
+                }
+                .emit()?;
+            }
+
+            for (warning, count) in &file.warnings {
+                Event::Warning {
+                    path: &file.path,
+                    warning,
+                    count: *count,
+                }
+                .emit()?;
+            }
+        }
+
+        for href in self.unused_anchors.iter().flatten() {
+            Event::UnusedAnchor { href }.emit()?;
+        }
+
+        for entry in self.stale_baseline_entries.iter().flatten() {
+            Event::StaleBaselineEntry(entry).emit()?;
+        }
+
+        for error in &self.errors {
+            Event::Error(error).emit()?;
+        }
+
+        Event::Done {
+            summary: self.summary(),
+        }
+        .emit()
+    }
+
+    pub fn summary(&self) -> Summary {
+        Summary {
+            bad_links: self.bad_links,
+            unique_bad_links: self.unique_bad_links,
+            bad_anchors: self.bad_anchors,
+            warnings: self.warnings,
+            errors: self.errors.len(),
+            success: self.success,
+        }
+    }
+}
+
+/// Pipes end a table cell even inside of code spans.
+fn escape_table_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+/// All problems found in a single file, either a HTML file or a source file.
+#[derive(Debug, Default, Serialize, JsonSchema)]
+pub struct FileReport {
+    pub path: PathBuf,
+    /// Broken links and how often each of them occurs in the file.
+    #[serde(serialize_with = "serialize_counted")]
+    #[schemars(with = "Vec<CountedLink>")]
+    pub bad_links: BTreeMap<ReportedLink, Occurrences>,
+    #[serde(serialize_with = "serialize_counted")]
+    #[schemars(with = "Vec<CountedLink>")]
+    pub bad_anchors: BTreeMap<ReportedLink, Occurrences>,
+    /// Warnings and how often each of them occurs in the file.
+    #[serde(serialize_with = "serialize_counted_warnings")]
+    #[schemars(with = "Vec<CountedWarning>")]
+    pub warnings: BTreeMap<ReportedWarning, usize>,
+}
+
+/// A file that could not be processed. With `--format json` these are reported instead of
+/// aborting the run.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct FileError {
+    pub kind: FileErrorKind,
+    pub path: PathBuf,
+    pub message: String,
+}
+
+/// An entry of the --baseline file whose link is not broken anymore.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct StaleBaselineEntry {
+    /// Either `link` or `anchor`.
+    pub kind: &'static str,
+    /// The document containing the link, relative to the base path.
+    pub path: String,
+    pub href: String,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FileErrorKind {
+    /// The file could not be read, decompressed or decoded.
+    ReadError,
+}
+
+impl FileReport {
+    pub fn new(path: PathBuf) -> Self {
+        FileReport {
+            path,
+            ..Default::default()
+        }
+    }
+
+    /// Record a broken link, written as `raw_href` in the document. Repeated occurrences of the
+    /// same link are counted instead of listed individually.
+    pub fn add_broken_link(&mut self, hard_404: bool, link: ReportedLink, raw_href: &str) {
+        let links = if hard_404 {
+            &mut self.bad_links
+        } else {
+            &mut self.bad_anchors
+        };
+
+        let occurrences = links.entry(link).or_default();
+        occurrences.count += 1;
+        if !occurrences.written_as.contains(raw_href) {
+            occurrences.written_as.insert(raw_href.to_owned());
+        }
+    }
+    /// Record a warning. Repeated occurrences of the same warning are counted.
+    pub fn add_warning(&mut self, warning: ReportedWarning) {
+        *self.warnings.entry(warning).or_default() += 1;
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Serialize, JsonSchema)]
+pub struct ReportedLink {
+    /// The line of the source file containing the link, if known.
+    pub line: Option<usize>,
+    /// The column of the attribute containing the link on that line, with tabs expanded to
+    /// `--html-tab-size`. Only present with `--html-tab-size`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    /// The link target, relative to the root of the site and without a leading slash.
+    pub href: String,
+    /// The element and attribute the link was found in, such as `<script src>`.
+    pub element: &'static str,
+    /// The `media` condition of the `<source>` element the link was found in, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media: Option<String>,
+    /// What references the id in `href`, for links from `--check-aria-references` and
+    /// `--check-label-for`. Already part of `element`, so not serialized.
+    #[serde(skip)]
+    pub referrer: Option<&'static str>,
+    /// Whether the link starts with a single slash and is reported because of
+    /// `--filesystem-mode`. Only present if true.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub absolute: bool,
+    /// The source of the `_redirects` rule that redirects to this missing link, only present with
+    /// `--warn-on-redirect-to-404`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect_from: Option<String>,
+}
+
+impl ReportedLink {
+    /// What kind of bad link this is, as printed in the report.
+    pub fn problem(&self) -> &'static str {
+        if self.redirect_from.is_some() {
+            "broken redirect"
+        } else if self.element == "<a download>" {
+            "broken download link"
+        } else if self.absolute {
+            "absolute link"
+        } else {
+            "bad link"
+        }
+    }
+
+    /// Where the link was found, as printed in the report, such as `<img src>` or
+    /// `<source srcset media="(min-width: 800px)">`.
+    pub fn origin(&self) -> Cow<'static, str> {
+        match (&self.media, self.element.strip_suffix('>')) {
+            (Some(media), Some(element)) => Cow::Owned(format!("{element} media=\"{media}\">")),
+            _ => Cow::Borrowed(self.element),
+        }
+    }
+}
+
+/// How often a broken link occurs in a file, and how it is spelled there.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Occurrences {
+    pub count: usize,
+    pub written_as: BTreeSet<String>,
+}
+
+impl Occurrences {
+    /// Whether any spelling differs from the canonicalized `href` by more than leading and
+    /// trailing slashes, i.e. whether the spellings are worth pointing out to the user.
+    pub fn is_written_differently(&self, href: &str) -> bool {
+        let href = href.trim_end_matches('/');
+        self.written_as
+            .iter()
+            .any(|raw_href| raw_href.trim_matches('/') != href)
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+struct CountedLink {
+    #[serde(flatten)]
+    link: ReportedLink,
+    /// How often the link occurs in the file.
+    count: usize,
+    /// The hrefs as literally written in the document, before they were resolved to `href`.
+    written_as: BTreeSet<String>,
+}
+
+fn serialize_counted<S: Serializer>(
+    links: &BTreeMap<ReportedLink, Occurrences>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(links.iter().map(|(link, occurrences)| CountedLink {
+        link: link.clone(),
+        count: occurrences.count,
+        written_as: occurrences.written_as.clone(),
+    }))
+}
+
+#[derive(Serialize, JsonSchema)]
+struct CountedWarning {
+    #[serde(flatten)]
+    warning: ReportedWarning,
+    /// How often the warning occurs in the file.
+    count: usize,
+}
+
+fn serialize_counted_warnings<S: Serializer>(
+    warnings: &BTreeMap<ReportedWarning, usize>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(warnings.iter().map(|(warning, count)| CountedWarning {
+        warning: warning.clone(),
+        count: *count,
+    }))
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Serialize, JsonSchema)]
+pub struct ReportedWarning {
+    /// The line of the source file the warning refers to, if known.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+/// An event printed by `--format ndjson`, one JSON object per line. `file_scanned` events are
+/// printed while the site is read, in no particular order. All other events are printed once all
+/// links have been checked, ending with `done`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    FileScanned {
+        path: &'a Path,
+    },
+    BrokenLink {
+        path: &'a Path,
+        /// Either `link` or `anchor`.
+        kind: &'static str,
+        #[serde(flatten)]
+        link: &'a ReportedLink,
+        count: usize,
+        written_as: &'a BTreeSet<String>,
+    },
+    Warning {
+        path: &'a Path,
+        #[serde(flatten)]
+        warning: &'a ReportedWarning,
+        count: usize,
+    },
+    UnusedAnchor {
+        href: &'a str,
+    },
+    StaleBaselineEntry(&'a StaleBaselineEntry),
+    Error(&'a FileError),
+    BaselineWritten {
+        path: &'a Path,
+        entries: usize,
+    },
+    Done {
+        summary: Summary,
+    },
+}
+
+impl Event<'_> {
+    pub fn emit(&self) -> Result<(), serde_json::Error> {
+        let line = serde_json::to_string(self)?;
+        println!("{line}");
+        Ok(())
+    }
+}
+
+/// The totals of a `Report`, as printed in the `done` event.
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub bad_links: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unique_bad_links: Option<usize>,
+    pub bad_anchors: usize,
+    pub warnings: usize,
+    pub errors: usize,
+    pub success: bool,
+}
+
+/// Statistics about a run, as written by `--metrics-file`.
+#[derive(Debug, Default, Serialize)]
+pub struct Metrics {
+    pub files: usize,
+    pub documents: usize,
+    pub links: usize,
+    pub bad_links: usize,
+    pub bad_anchors: usize,
+    pub warnings: usize,
+    /// Files that could not be read, only reported with `--format json` or `ndjson`.
+    pub errors: usize,
+    /// Duration of each phase in seconds.
+    pub durations: BTreeMap<&'static str, f64>,
+}
+
+impl Metrics {
+    /// Record the time elapsed since `start` as the duration of `phase`.
+    pub fn add_duration(&mut self, phase: &'static str, start: Instant) {
+        self.durations.insert(phase, start.elapsed().as_secs_f64());
+    }
+}
+
+#[test]
+fn test_report_json() {
+    let mut file = FileReport::new(PathBuf::from("index.html"));
+    for (line, raw_href) in [(3, "bar.html"), (3, "./bar.html"), (5, "bar.html")] {
+        file.add_broken_link(
+            true,
+            ReportedLink {
+                line: Some(line),
+                column: None,
+                href: "bar.html".to_owned(),
+                element: "<a href>",
+                media: None,
+                referrer: None,
+                absolute: false,
+                redirect_from: None,
+            },
+            raw_href,
+        );
+    }
+    for _ in 0..2 {
+        file.add_warning(ReportedWarning {
+            line: None,
+            message: "srcset without sizes".to_owned(),
+        });
+    }
+
+    let report = Report {
+        files: vec![file],
+        bad_links: 3,
+        warnings: 2,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        serde_json::to_value(&report).unwrap(),
+        serde_json::json!({
+            "files": [{
+                "path": "index.html",
+                "bad_links": [
+                    {
+                        "line": 3,
+                        "href": "bar.html",
+                        "element": "<a href>",
+                        "count": 2,
+                        "written_as": ["./bar.html", "bar.html"],
+                    },
+                    {
+                        "line": 5,
+                        "href": "bar.html",
+                        "element": "<a href>",
+                        "count": 1,
+                        "written_as": ["bar.html"],
+                    },
+                ],
+                "bad_anchors": [],
+                "warnings": [
+                    {"line": null, "message": "srcset without sizes", "count": 2},
+                ],
+            }],
+            "bad_links": 3,
+            "bad_anchors": 0,
+            "warnings": 2,
+            "success": false,
+        })
+    );
+}
+
+#[test]
+fn test_event_json() {
+    let link = ReportedLink {
+        line: Some(3),
+        column: None,
+        href: "bar.html".to_owned(),
+        element: "<a href>",
+        media: None,
+        referrer: None,
+        absolute: false,
+        redirect_from: None,
+    };
+    let written_as = BTreeSet::from(["./bar.html".to_owned()]);
+    let error = FileError {
+        kind: FileErrorKind::ReadError,
+        path: PathBuf::from("style.css"),
+        message: "stream did not contain valid UTF-8".to_owned(),
+    };
+
+    assert_eq!(
+        serde_json::to_value(Event::FileScanned {
+            path: Path::new("index.html")
+        })
+        .unwrap(),
+        serde_json::json!({"event": "file_scanned", "path": "index.html"})
+    );
+    assert_eq!(
+        serde_json::to_value(Event::BrokenLink {
+            path: Path::new("index.html"),
+            kind: "link",
+            link: &link,
+            count: 2,
+            written_as: &written_as,
+        })
+        .unwrap(),
+        serde_json::json!({
+            "event": "broken_link",
+            "path": "index.html",
+            "kind": "link",
+            "line": 3,
+            "href": "bar.html",
+            "element": "<a href>",
+            "count": 2,
+            "written_as": ["./bar.html"],
+        })
+    );
+    assert_eq!(
+        serde_json::to_value(Event::Error(&error)).unwrap(),
+        serde_json::json!({
+            "event": "error",
+            "kind": "read_error",
+            "path": "style.css",
+            "message": "stream did not contain valid UTF-8",
+        })
+    );
+}
+
+#[test]
+fn test_report_markdown() {
+    let mut file = FileReport::new(PathBuf::from("index.html"));
+    for href in ["bar.html", "bar.html", "a|b.html"] {
+        file.add_broken_link(
+            true,
+            ReportedLink {
+                line: None,
+                column: None,
+                href: href.to_owned(),
+                element: "<a href>",
+                media: None,
+                referrer: None,
+                absolute: false,
+                redirect_from: None,
+            },
+            href,
+        );
+    }
+    file.add_broken_link(
+        false,
+        ReportedLink {
+            line: Some(4),
+            column: None,
+            href: "index.html#missing".to_owned(),
+            element: "<a href>",
+            media: None,
+            referrer: None,
+            absolute: false,
+            redirect_from: None,
+        },
+        "#missing",
+    );
+
+    let report = Report {
+        files: vec![file],
+        bad_links: 3,
+        bad_anchors: 1,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        report.to_markdown(),
+        "## hyperlink\n\n\
+         Found 3 bad links and 1 bad anchors.\n\n\
+         | File | Problem | Link | Count |\n\
+         | --- | --- | --- | --- |\n\
+         | `index.html` | bad link | `/a\\|b.html` | 1 |\n\
+         | `index.html` | bad link | `/bar.html` | 2 |\n\
+         | `index.html:4` | bad anchor | `/index.html#missing` | 1 |\n"
+    );
+    assert_eq!(
+        Report::default().to_markdown(),
+        "## hyperlink\n\nNo bad links found.\n"
+    );
+}
+
+#[test]
+fn test_occurrences_spellings() {
+    let occurrences = |spellings: &[&str]| Occurrences {
+        count: spellings.len(),
+        written_as: spellings.iter().map(|&raw| raw.to_owned()).collect(),
+    };
+
+    assert!(
+        !occurrences(&["platforms/go", "/platforms/go/"]).is_written_differently("platforms/go")
+    );
+    assert!(occurrences(&["/platforms/go", "../go/"]).is_written_differently("platforms/go"));
+    assert!(occurrences(&["go/?foo=bar"]).is_written_differently("go"));
+    assert!(!occurrences(&["/go/"]).is_written_differently("go/"));
+}
@@ -0,0 +1,78 @@
+//! `--rewrite-map`: Clean URLs that the server rewrites to files with other names, like content
+//! hashes in `{"/guide": "pages/guide.abcdef.html"}`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Error};
+use bumpalo::collections::String as BumpString;
+
+use crate::html::{push_and_canonicalize, try_percent_decode_path};
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RewriteMap {
+    /// Canonical hrefs of files by the canonical href they are served under.
+    rewrites: BTreeMap<String, String>,
+}
+
+impl RewriteMap {
+    pub fn read(
+        path: &Path,
+        collapse_index_html: bool,
+        strict_trailing_slash: bool,
+    ) -> Result<Self, Error> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read rewrite map {}", path.display()))?;
+        Self::parse(&contents, collapse_index_html, strict_trailing_slash)
+            .with_context(|| format!("Failed to parse rewrite map {}", path.display()))
+    }
+
+    /// Parse a JSON object mapping URL paths to file paths, both relative to the root of the site.
+    pub fn parse(
+        contents: &str,
+        collapse_index_html: bool,
+        strict_trailing_slash: bool,
+    ) -> Result<Self, Error> {
+        let raw: BTreeMap<String, String> = serde_json::from_str(contents)?;
+        let arena = bumpalo::Bump::new();
+        let canonicalize = |path: &str| {
+            let mut href = BumpString::new_in(&arena);
+            push_and_canonicalize(
+                &mut href,
+                &try_percent_decode_path(path),
+                collapse_index_html,
+                strict_trailing_slash,
+            );
+            href.to_string()
+        };
+
+        let rewrites = raw
+            .iter()
+            .map(|(from, to)| (canonicalize(from), canonicalize(to)))
+            .collect();
+
+        Ok(RewriteMap { rewrites })
+    }
+
+    /// The canonical href of the file that is served for `page`, an href without anchor.
+    pub fn resolve(&self, page: &str) -> Option<&str> {
+        self.rewrites.get(page).map(String::as_str)
+    }
+}
+
+#[test]
+fn test_rewrite_map() {
+    let map = RewriteMap::parse(
+        r#"{"/guide": "pages/guide.abcdef.html", "/docs/": "/pages/docs.123.html"}"#,
+        true,
+        false,
+    )
+    .unwrap();
+
+    assert_eq!(map.resolve("guide"), Some("pages/guide.abcdef.html"));
+    assert_eq!(map.resolve("docs"), Some("pages/docs.123.html"));
+    assert_eq!(map.resolve("pages/guide.abcdef.html"), None);
+
+    assert!(RewriteMap::parse(r#"["/guide"]"#, true, false).is_err());
+}
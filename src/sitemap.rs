@@ -0,0 +1,62 @@
+//! Support for `sitemap.xml` files, see the check-sitemap command.
+//!
+//! https://www.sitemaps.org/protocol.html
+
+use html5gum::{Token, Tokenizer};
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+/// The URLs in the `<loc>` elements of a sitemap, in order.
+pub fn sitemap_locs(contents: &str) -> Vec<String> {
+    let mut locs = Vec::new();
+    let mut current_loc: Option<Vec<u8>> = None;
+
+    for token in Tokenizer::new(contents).flatten() {
+        match token {
+            Token::StartTag(tag) if tag.name.as_slice() == b"loc" => {
+                current_loc = Some(Vec::new());
+            }
+            Token::String(text) => {
+                if let Some(ref mut loc) = current_loc {
+                    loc.extend_from_slice(&text);
+                }
+            }
+            Token::EndTag(tag) if tag.name.as_slice() == b"loc" => {
+                if let Some(loc) = current_loc.take() {
+                    let loc = String::from_utf8_lossy(&loc);
+                    if !loc.trim().is_empty() {
+                        locs.push(loc.trim().to_owned());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    locs
+}
+
+#[test]
+fn test_sitemap_locs() {
+    let locs = sitemap_locs(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+        <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+          <url>
+            <loc>https://example.com/</loc>
+            <lastmod>2024-01-01</lastmod>
+          </url>
+          <url>
+            <loc>
+              https://example.com/search?q=a&amp;b
+            </loc>
+          </url>
+          <url><loc></loc></url>
+        </urlset>"#,
+    );
+
+    assert_eq!(
+        locs,
+        ["https://example.com/", "https://example.com/search?q=a&b"]
+    );
+}
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+/// Turn the text content of a heading into an anchor the way GitHub does for Markdown headings:
+/// Lowercase everything, drop punctuation and replace spaces with dashes.
+///
+/// `text` has to be the text content only, so `<code>foo</code> bar` is passed as `foo bar`.
+pub fn github_slug(text: &str) -> String {
+    text.trim()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' | '_' => Some(c),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Like `github_slug`, the default `autoHeadingIDType` of Hugo. Headings without any letters or
+/// digits are called `heading`.
+pub fn hugo_slug(text: &str) -> String {
+    let slug = github_slug(text);
+    if slug.is_empty() {
+        "heading".to_owned()
+    } else {
+        slug
+    }
+}
+
+/// Turn the text content of a heading into an anchor the way kramdown, the Markdown converter of
+/// Jekyll, does: Drop everything up to the first ASCII letter and all characters other than ASCII
+/// letters, digits, spaces and dashes, then replace spaces with dashes and lowercase.
+pub fn jekyll_slug(text: &str) -> String {
+    let slug: String = text
+        .trim_start_matches(|c: char| !c.is_ascii_alphabetic())
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' => Some(c),
+            c if c.is_ascii_alphanumeric() => Some(c.to_ascii_lowercase()),
+            _ => None,
+        })
+        .collect();
+
+    if slug.is_empty() {
+        "section".to_owned()
+    } else {
+        slug
+    }
+}
+
+/// The static site generator whose anchors `--heading-anchors` imitates, see `--anchor-style`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AnchorStyle {
+    #[default]
+    Github,
+    Hugo,
+    Jekyll,
+    /// Docusaurus uses the same slugger as GitHub.
+    Docusaurus,
+}
+
+impl AnchorStyle {
+    pub fn slug(self, text: &str) -> String {
+        match self {
+            AnchorStyle::Github | AnchorStyle::Docusaurus => github_slug(text),
+            AnchorStyle::Hugo => hugo_slug(text),
+            AnchorStyle::Jekyll => jekyll_slug(text),
+        }
+    }
+
+    /// Make `slug` unique within a document by appending `-1`, `-2`, ... `used` maps the anchors
+    /// of the document so far to how often they were disambiguated.
+    pub fn disambiguate(self, slug: String, used: &mut HashMap<String, usize>) -> String {
+        let slug = match self {
+            // github-slugger skips suffixes that collide with other headings, so `Foo`, `Foo 1`,
+            // `Foo` produce `foo`, `foo-1`, `foo-2`.
+            AnchorStyle::Github | AnchorStyle::Docusaurus => {
+                let mut result = slug.clone();
+                while used.contains_key(&result) {
+                    let count = used.entry(slug.clone()).or_insert(0);
+                    *count += 1;
+                    result = format!("{slug}-{count}");
+                }
+                result
+            }
+            // Hugo tries every suffix starting from 1 again for each heading.
+            AnchorStyle::Hugo => {
+                let mut result = slug.clone();
+                let mut i = 1;
+                while used.contains_key(&result) {
+                    result = format!("{slug}-{i}");
+                    i += 1;
+                }
+                result
+            }
+            // kramdown only counts repetitions and does not remember suffixed anchors, so they
+            // may collide with other headings.
+            AnchorStyle::Jekyll => {
+                return match used.get_mut(&slug) {
+                    Some(count) => {
+                        *count += 1;
+                        format!("{slug}-{count}")
+                    }
+                    None => {
+                        used.insert(slug.clone(), 0);
+                        slug
+                    }
+                };
+            }
+        };
+
+        used.entry(slug.clone()).or_insert(0);
+        slug
+    }
+}
+
+impl std::str::FromStr for AnchorStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "github" => Ok(AnchorStyle::Github),
+            "hugo" => Ok(AnchorStyle::Hugo),
+            "jekyll" => Ok(AnchorStyle::Jekyll),
+            "docusaurus" => Ok(AnchorStyle::Docusaurus),
+            _ => Err(format!(
+                "unknown anchor style {s:?}, expected github, hugo, jekyll or docusaurus"
+            )),
+        }
+    }
+}
+
+#[test]
+fn test_github_slug() {
+    assert_eq!(github_slug("Hello World"), "hello-world");
+    assert_eq!(github_slug("  foo bar "), "foo-bar");
+    assert_eq!(github_slug("What's new in 1.0?"), "whats-new-in-10");
+    assert_eq!(
+        github_slug("snake_case & kebab-case"),
+        "snake_case--kebab-case"
+    );
+    assert_eq!(github_slug("Über Größe"), "über-größe");
+}
+
+#[test]
+fn test_jekyll_slug() {
+    assert_eq!(jekyll_slug("Hello World"), "hello-world");
+    assert_eq!(jekyll_slug("1. Getting started"), "getting-started");
+    assert_eq!(jekyll_slug("What's new in 1.0?"), "whats-new-in-10");
+    assert_eq!(jekyll_slug("Über Größe"), "ber-gre");
+    assert_eq!(jekyll_slug("123"), "section");
+}
+
+#[test]
+fn test_anchor_style_disambiguate() {
+    let anchors = |style: AnchorStyle, headings: &[&str]| {
+        let mut used = HashMap::new();
+        headings
+            .iter()
+            .map(|heading| style.disambiguate(style.slug(heading), &mut used))
+            .collect::<Vec<_>>()
+    };
+
+    let headings = ["Foo", "Foo", "Foo 1", "Foo", "?"];
+    assert_eq!(
+        anchors(AnchorStyle::Github, &headings),
+        ["foo", "foo-1", "foo-1-1", "foo-2", ""]
+    );
+    assert_eq!(
+        anchors(AnchorStyle::Docusaurus, &headings),
+        anchors(AnchorStyle::Github, &headings)
+    );
+    assert_eq!(
+        anchors(AnchorStyle::Hugo, &headings),
+        ["foo", "foo-1", "foo-1-1", "foo-2", "heading"]
+    );
+    assert_eq!(
+        anchors(AnchorStyle::Jekyll, &headings),
+        ["foo", "foo-1", "foo-1", "foo-2", "section"]
+    );
+
+    let headings = ["Foo 1", "Foo", "Foo"];
+    assert_eq!(
+        anchors(AnchorStyle::Github, &headings),
+        ["foo-1", "foo", "foo-2"]
+    );
+    assert_eq!(
+        anchors(AnchorStyle::Hugo, &headings),
+        ["foo-1", "foo", "foo-2"]
+    );
+    assert_eq!(
+        anchors(AnchorStyle::Jekyll, &headings),
+        ["foo-1", "foo", "foo-1"]
+    );
+}
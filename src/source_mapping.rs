@@ -0,0 +1,102 @@
+//! `--source-mapping`: Find the source file of a HTML file by transforming its path, as an
+//! alternative to matching paragraphs with `--sources`.
+
+use std::path::{Path, PathBuf};
+use std::str;
+
+/// A mapping like `{}/index.html=content/{}.md`. The placeholder matches any part of the HTML
+/// file's path relative to the base path, and is substituted into the source path.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SourceMapping {
+    output: (String, String),
+    source: (String, String),
+}
+
+impl str::FromStr for SourceMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let split_placeholder = |pattern: &str| {
+            let (prefix, suffix) = pattern.split_once("{}")?;
+            if suffix.contains("{}") {
+                return None;
+            }
+            Some((prefix.to_owned(), suffix.to_owned()))
+        };
+
+        let (output, source) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected OUTPUT=SOURCE, got {s:?}"))?;
+
+        match (split_placeholder(output), split_placeholder(source)) {
+            (Some(output), Some(source)) => Ok(SourceMapping { output, source }),
+            _ => Err(format!(
+                "both sides of {s:?} need to contain exactly one {{}} placeholder"
+            )),
+        }
+    }
+}
+
+impl SourceMapping {
+    /// Return the source file for `path`, a HTML file relative to the base path.
+    pub fn source_path(&self, path: &Path) -> Option<PathBuf> {
+        let path = path.to_str()?.replace('\\', "/");
+        let (prefix, suffix) = &self.output;
+        let placeholder = path
+            .strip_prefix(prefix.as_str())?
+            .strip_suffix(suffix.as_str())?;
+        let (prefix, suffix) = &self.source;
+        Some(PathBuf::from(format!("{prefix}{placeholder}{suffix}")))
+    }
+}
+
+/// Guess the line of `source` that contains the broken link `href`. We only know the canonicalized
+/// href, so we look for its longest trailing part that appears in the source, such as `go` for
+/// `platforms/go`. Returns a 1-based line number.
+pub fn find_href_line(source: &str, href: &str) -> Option<usize> {
+    let (page, anchor) = href.split_at(href.find('#').unwrap_or(href.len()));
+    let page = page.trim_end_matches('/');
+
+    let mut needles: Vec<String> = std::iter::once(0)
+        .chain(page.match_indices('/').map(|(i, _)| i + 1))
+        .map(|i| format!("{}{anchor}", &page[i..]))
+        .collect();
+
+    if !anchor.is_empty() {
+        needles.push(anchor.to_owned());
+    }
+
+    needles
+        .iter()
+        .filter(|needle| !needle.is_empty())
+        .find_map(|needle| {
+            source
+                .lines()
+                .position(|line| line.contains(needle.as_str()))
+        })
+        .map(|i| i + 1)
+}
+
+#[test]
+fn test_source_path() {
+    let mapping: SourceMapping = "{}/index.html=content/{}.md".parse().unwrap();
+    assert_eq!(
+        mapping.source_path(Path::new("docs/intro/index.html")),
+        Some(PathBuf::from("content/docs/intro.md"))
+    );
+    assert_eq!(mapping.source_path(Path::new("docs/intro.html")), None);
+
+    assert!("{}/index.html".parse::<SourceMapping>().is_err());
+    assert!("index.html=content/{}.md".parse::<SourceMapping>().is_err());
+    assert!("{}/{}.html=content/{}.md".parse::<SourceMapping>().is_err());
+}
+
+#[test]
+fn test_find_href_line() {
+    let source =
+        "# Platforms\n\nSee [Go](../go/) and [Python](../python/#setup).\n\n[Rust](rust)\n";
+    assert_eq!(find_href_line(source, "platforms/go"), Some(3));
+    assert_eq!(find_href_line(source, "platforms/python#setup"), Some(3));
+    assert_eq!(find_href_line(source, "platforms/rust"), Some(5));
+    assert_eq!(find_href_line(source, "platforms/java"), None);
+}
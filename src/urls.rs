@@ -1,24 +1,21 @@
 #[inline]
 pub fn is_external_link(url: &[u8]) -> bool {
-    // check if url is empty
-    let first_char = match url.first() {
-        Some(x) => x,
-        None => return false,
-    };
-
     // protocol-relative URL
-    if url.starts_with(b"//") {
-        return true;
-    }
+    url.starts_with(b"//") || url_scheme(url).is_some()
+}
 
+/// The scheme of `url`, such as `https` or `mailto`, as written. `None` for relative URLs,
+/// including protocol-relative ones.
+#[inline]
+pub fn url_scheme(url: &[u8]) -> Option<&[u8]> {
     // check if string before first : is a valid URL scheme
     // see RFC 2396, Appendix A for what constitutes a valid scheme
 
-    if !first_char.is_ascii_alphabetic() {
-        return false;
+    if !url.first()?.is_ascii_alphabetic() {
+        return None;
     }
 
-    for c in &url[1..] {
+    for (i, c) in url.iter().enumerate().skip(1) {
         match c {
             b'a'..=b'z' => (),
             b'A'..=b'Z' => (),
@@ -26,12 +23,107 @@ pub fn is_external_link(url: &[u8]) -> bool {
             b'+' => (),
             b'-' => (),
             b'.' => (),
-            b':' => return true,
-            _ => return false,
+            b':' => return Some(&url[..i]),
+            _ => return None,
         }
     }
 
-    false
+    None
+}
+
+/// Schemes accepted by `--strict-scheme`. Anything else is likely a typo like `htps`.
+static KNOWN_SCHEMES: &[&str] = &[
+    "about",
+    "blob",
+    "callto",
+    "data",
+    "facetime",
+    "file",
+    "ftp",
+    "geo",
+    "git",
+    "http",
+    "https",
+    "irc",
+    "ircs",
+    "javascript",
+    "magnet",
+    "mailto",
+    "news",
+    "sftp",
+    "sip",
+    "skype",
+    "sms",
+    "ssh",
+    "tel",
+    "webcal",
+    "ws",
+    "wss",
+    "xmpp",
+];
+
+/// Whether `scheme` is in the allowlist of `--strict-scheme`, ignoring case.
+pub fn is_known_scheme(scheme: &[u8]) -> bool {
+    KNOWN_SCHEMES
+        .iter()
+        .any(|known| known.as_bytes().eq_ignore_ascii_case(scheme))
+}
+
+/// The host of an external link such as `https://user@cdn.example.com:8080/path`, or `None` for
+/// links without one, such as `mailto:` links.
+pub fn external_link_host(url: &str) -> Option<&str> {
+    let rest = match url.strip_prefix("//") {
+        Some(rest) => rest,
+        None => url[url.find(':')? + 1..].strip_prefix("//")?,
+    };
+
+    let authority = &rest[..rest.find(&['/', '?', '#'][..]).unwrap_or(rest.len())];
+    let host_and_port = authority.rsplit('@').next()?;
+    let host = if host_and_port.starts_with('[') {
+        // IPv6 address
+        &host_and_port[..host_and_port
+            .find(']')
+            .map_or(host_and_port.len(), |i| i + 1)]
+    } else {
+        host_and_port.split(':').next()?
+    };
+
+    Some(host).filter(|host| !host.is_empty())
+}
+
+/// The path, query string and anchor of an absolute URL like `https://example.com/about/`, so that
+/// it can be resolved like a link from the root of the site. Other URLs are returned unchanged.
+pub fn strip_origin(url: &str) -> &str {
+    let rest = match url.strip_prefix("//") {
+        Some(rest) => rest,
+        None => match url_scheme(url.as_bytes())
+            .and_then(|scheme| url[scheme.len() + 1..].strip_prefix("//"))
+        {
+            Some(rest) => rest,
+            None => return url,
+        },
+    };
+
+    &rest[rest.find(&['/', '?', '#'][..]).unwrap_or(rest.len())..]
+}
+
+/// Whether `host` is `domain` or one of its subdomains. A `www.` prefix is ignored on both, so
+/// `www.example.com` and `example.com` are interchangeable.
+pub fn host_matches_domain(host: &str, domain: &str) -> bool {
+    let strip_www = |name: &str| -> String {
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+        match name.strip_prefix("www.") {
+            Some(name) => name.to_owned(),
+            None => name,
+        }
+    };
+
+    let host = strip_www(host);
+    let domain = strip_www(domain);
+    host == domain
+        || host
+            .strip_suffix(domain.as_str())
+            .is_some_and(|subdomain| subdomain.ends_with('.'))
 }
 
 #[test]
@@ -43,3 +135,63 @@ fn test_is_bad_schema() {
     assert!(is_external_link(b"http:/"));
     assert!(!is_external_link(b"http/"));
 }
+
+#[test]
+fn test_url_scheme() {
+    assert_eq!(url_scheme(b"https://example.com"), Some(&b"https"[..]));
+    assert_eq!(url_scheme(b"MailTo:foo@example.com"), Some(&b"MailTo"[..]));
+    assert_eq!(url_scheme(b"htttp:example.com"), Some(&b"htttp"[..]));
+    assert_eq!(url_scheme(b"javascript:void(0)"), Some(&b"javascript"[..]));
+    assert_eq!(url_scheme(b"//example.com"), None);
+    assert_eq!(url_scheme(b"foo/bar:baz"), None);
+    assert_eq!(url_scheme(b"1password:"), None);
+    assert_eq!(url_scheme(b""), None);
+}
+
+#[test]
+fn test_is_known_scheme() {
+    assert!(is_known_scheme(b"https"));
+    assert!(is_known_scheme(b"MailTo"));
+    assert!(!is_known_scheme(b"htps"));
+    assert!(!is_known_scheme(b"mailto "));
+}
+
+#[test]
+fn test_external_link_host() {
+    assert_eq!(
+        external_link_host("https://example.com/foo"),
+        Some("example.com")
+    );
+    assert_eq!(
+        external_link_host("https://user@cdn.example.com:8080?q=1"),
+        Some("cdn.example.com")
+    );
+    assert_eq!(external_link_host("//example.com#top"), Some("example.com"));
+    assert_eq!(external_link_host("http://[::1]:80/"), Some("[::1]"));
+    assert_eq!(external_link_host("mailto:foo@example.com"), None);
+    assert_eq!(external_link_host("file:///etc/passwd"), None);
+}
+
+#[test]
+fn test_strip_origin() {
+    assert_eq!(strip_origin("https://example.com/about/"), "/about/");
+    assert_eq!(strip_origin("https://example.com"), "");
+    assert_eq!(strip_origin("http://example.com:8080?q=1"), "?q=1");
+    assert_eq!(strip_origin("//example.com/a.html#top"), "/a.html#top");
+    assert_eq!(strip_origin("/about/"), "/about/");
+    assert_eq!(
+        strip_origin("mailto:foo@example.com"),
+        "mailto:foo@example.com"
+    );
+}
+
+#[test]
+fn test_host_matches_domain() {
+    assert!(host_matches_domain("example.com", "example.com"));
+    assert!(host_matches_domain("cdn.example.com", "example.com"));
+    assert!(host_matches_domain("www.example.com", "example.com"));
+    assert!(host_matches_domain("example.com", "www.example.com"));
+    assert!(host_matches_domain("CDN.Example.com.", "example.com"));
+    assert!(!host_matches_domain("badexample.com", "example.com"));
+    assert!(!host_matches_domain("example.com", "cdn.example.com"));
+}
@@ -16,7 +16,7 @@ fn test_dead_link() {
             r#"^Reading files
 Checking 1 links from 1 files \(1 documents\)
 \..index\.html
-  error: bad link /bar.html
+  error: bad link /bar.html \(from <a href>\)
 
 Found 1 bad links
 "#,
@@ -26,6 +26,218 @@ Found 1 bad links
     site.close().unwrap();
 }
 
+#[test]
+fn test_max_open_files() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=a.html>a</a><a href=b.html>b</a>")
+        .unwrap();
+    site.child("a.html")
+        .write_str("<a href=b.html>b</a>")
+        .unwrap();
+    site.child("b.html")
+        .write_str("<a href=missing.html>x</a>")
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--max-open-files")
+        .arg("1");
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains("error: bad link /missing.html"));
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--max-open-files")
+        .arg("0");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--max-open-files must be at least 1",
+    ));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_reserved_characters_in_filename() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=c%23.html#intro>a</a><a href=c%23.html#missing>b</a>")
+        .unwrap();
+    site.child("c#.html")
+        .write_str("<h1 id=intro>C#</h1>")
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".").arg("--check-anchors");
+    cmd.assert().failure().code(2).stdout(
+        predicate::str::contains("error: bad link /c%23.html#missing")
+            .and(predicate::str::contains("#intro").not()),
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_dedupe_across_files() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=bar.html> <a href=baz.html>")
+        .unwrap();
+    site.child("other.html")
+        .write_str("<a href=bar.html> <a href=/bar.html#foo>")
+        .unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--dedupe-across-files");
+
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "Found 4 bad links (2 unique targets)",
+        ));
+    site.close().unwrap();
+}
+
+#[test]
+fn test_message_template() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=bar.html>")
+        .unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--message-template")
+        .arg("{kind} in {path}: {href}");
+
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::is_match(r"\nbad link in \..index\.html: /bar\.html\n").unwrap());
+    site.close().unwrap();
+}
+
+#[test]
+fn test_html_tab_size() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(
+            "<ul>\n\t<li><a href=bar.html>bar</a>\n<!-- <a href=bar.html> -->\n\
+             <li><a href=bar.html>again</a>\n</ul>",
+        )
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--html-tab-size")
+        .arg("4");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("error: bad link /bar.html at line 2, col 12 (from <a href>)")
+            .and(predicate::str::contains(
+                "error: bad link /bar.html at line 4, col 8 (from <a href>)",
+            ))
+            .and(predicate::str::contains("line 3").not()),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--html-tab-size")
+        .arg("8")
+        .arg("--message-template")
+        .arg("{line}:{column}: {href}");
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains("\n2:16: /bar.html\n"));
+    site.close().unwrap();
+}
+
+#[test]
+fn test_message_template_unknown_placeholder() {
+    let site = assert_fs::TempDir::new().unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--message-template")
+        .arg("{file}: {href}");
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown placeholder {file}"));
+    site.close().unwrap();
+}
+
+#[test]
+fn test_dead_link_repeated() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=go/>1</a> <a href=go/?foo=bar>2</a> <a href=go/#x>3</a> <img src=go/>")
+        .unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains(
+            "  error: bad link /go (written as \"go/\", \"go/#x\", \"go/?foo=bar\") (×3) (from <a href>)\n",
+        )
+            .and(predicate::str::contains(
+                "  error: bad link /go (from <img src>)\n",
+            ))
+            .and(predicate::str::contains("Found 4 bad links")),
+    );
+    site.close().unwrap();
+}
+
+#[test]
+fn test_relative_links_from_root_index() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=sub/page.html> <a href=./sub/> <a href=sub/page.html#top>")
+        .unwrap();
+    site.child("sub/index.html").write_str("").unwrap();
+    site.child("sub/page.html")
+        .write_str("<h1 id=top>Top</h1>")
+        .unwrap();
+
+    for base_path in [".", "./"] {
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(base_path)
+            .arg("--check-anchors");
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("Found 0 bad links"));
+    }
+    site.close().unwrap();
+}
+
+#[test]
+fn test_dead_download_link() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=report.pdf download>report</a> <a href=bar.html>bar</a>")
+        .unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("  error: broken download link /report.pdf (from <a download>)\n")
+            .and(predicate::str::contains(
+                "  error: bad link /bar.html (from <a href>)\n",
+            ))
+            .and(predicate::str::contains("Found 2 bad links")),
+    );
+    site.close().unwrap();
+}
+
 #[test]
 fn test_dead_anchor() {
     let site = assert_fs::TempDir::new().unwrap();
@@ -41,7 +253,7 @@ fn test_dead_anchor() {
             r#"^Reading files
 Checking 1 links from 2 files \(2 documents\)
 \..index\.html
-  error: bad link /bar.html#goo
+  error: bad link /bar.html#goo \(from <a href>\)
 
 Found 0 bad links
 Found 1 bad anchors
@@ -52,6 +264,24 @@ $"#,
     site.close().unwrap();
 }
 
+#[test]
+fn test_footnote_anchors() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(
+            r##"<p>Claim<sup id="fnref1"><a href="#fn1">1</a></sup></p>
+            <ol><li id="fn1">Source <a href="#fnref1">↩</a></li></ol>"##,
+        )
+        .unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".").arg("--check-anchors");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Found 0 bad anchors"));
+    site.close().unwrap();
+}
+
 #[test]
 fn test_bad_dir() {
     let mut cmd = Command::cargo_bin("hyperlink").unwrap();
@@ -65,3 +295,2559 @@ fn test_bad_dir() {
             "Error: IO error for operation on non_existing_dir:",
         ));
 }
+
+#[test]
+fn test_baseline() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=bar.html>")
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--baseline=baseline.txt")
+        .arg("--write-baseline");
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Wrote 1 broken links to baseline baseline.txt",
+    ));
+
+    site.child("baseline.txt")
+        .assert(predicate::str::contains("link\tindex.html\tbar.html\n"));
+
+    // known broken links are ignored, new ones are still reported
+    site.child("index.html")
+        .write_str("<a href=bar.html><a href=baz.html>")
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--baseline=baseline.txt");
+
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("error: bad link /baz.html")
+            .and(predicate::str::contains("/bar.html").not())
+            .and(predicate::str::contains(
+                "Ignored 1 known broken links from baseline",
+            ))
+            .and(predicate::str::contains("Found 1 bad links")),
+    );
+
+    // fixed links are reported as stale baseline entries
+    site.child("index.html")
+        .write_str("<a href=bar.html>")
+        .unwrap();
+    site.child("bar.html").touch().unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--baseline=baseline.txt");
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Baseline entries that are no longer broken, remove them from the baseline:
+  link index.html /bar.html
+",
+    ));
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--baseline=baseline.txt")
+        .arg("--format")
+        .arg("json");
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(
+        report["stale_baseline_entries"],
+        serde_json::json!([{"kind": "link", "path": "index.html", "href": "bar.html"}])
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--baseline=baseline.txt")
+        .arg("--format")
+        .arg("ndjson");
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        r#"{"event":"stale_baseline_entry","kind":"link","path":"index.html","href":"bar.html"}"#,
+    ));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_anchor_attribute() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<h2 data-anchor-id=setup>Setup</h2> <a href=#setup>setup</a>")
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".").arg("--check-anchors");
+    cmd.assert()
+        .failure()
+        .code(2)
+        .stdout(predicate::str::contains("error: bad link /#setup"));
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-anchors")
+        .arg("--anchor-attribute")
+        .arg("data-anchor-id");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--anchor-attribute")
+        .arg("data-anchor-id");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--anchor-attribute requires --check-anchors to be set",
+    ));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_fragment_only_link_in_partial() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<nav id=nav></nav> <a href=#nav>nav</a>")
+        .unwrap();
+    site.child("partials/header.html")
+        .write_str("<a href=#nav>nav</a>")
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".").arg("--check-anchors");
+    cmd.assert().failure().code(2).stdout(
+        predicate::str::is_match(
+            r"partials/header\.html\n  error: bad link /partials/header\.html#nav ",
+        )
+        .unwrap()
+        .and(predicate::str::contains("index.html").not())
+        .and(predicate::str::contains("Found 1 bad anchors")),
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_ignore_fragments() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=#missing>top</a> <a href=bar.html#missing>bar</a>")
+        .unwrap();
+    site.child("bar.html").touch().unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--ignore-fragments");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--ignore-fragments")
+        .arg("--check-anchors");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--ignore-fragments cannot be combined with --check-anchors or --strict",
+    ));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_anchor_case() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(r##"<h2 id="intro">Intro</h2> <a href="#Intro">intro</a>"##)
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-anchors")
+        .arg("--ignore-anchor-case");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-anchors")
+        .arg("--ignore-anchor-case")
+        .arg("--unicode-anchor-case");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--ignore-anchor-case cannot be combined with --unicode-anchor-case",
+    ));
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--ignore-anchor-case");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--ignore-anchor-case requires --check-anchors to be set",
+    ));
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--unicode-anchor-case");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--unicode-anchor-case requires --check-anchors to be set",
+    ));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_dead_embed() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(r#"<embed src="manual.pdf" type="application/pdf">"#)
+        .unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains("error: bad link /manual.pdf"));
+    site.close().unwrap();
+}
+
+#[test]
+fn test_dead_image_button() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<form><input type=image src=missing.png alt=Submit></form>")
+        .unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "error: bad link /missing.png (from <input src>)",
+        ));
+    site.close().unwrap();
+}
+
+#[test]
+fn test_parse_comments() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(r#"<!--[if IE]><link rel="stylesheet" href="ie.css"><![endif]-->"#)
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--parse-comments");
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "error: bad link /ie.css (from <link href>)",
+        ));
+    site.close().unwrap();
+}
+
+#[test]
+fn test_report_mixed_separators() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(r#"<a href="docs\guide.html">"#)
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--report-mixed-separators");
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains(
+            r"  warning: backslash in href: docs\guide.html",
+        ));
+    site.close().unwrap();
+}
+
+#[test]
+fn test_report_empty_anchors() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(r##"<h2 id="">Intro</h2> <a href="#">top</a>"##)
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-anchors")
+        .arg("--report-empty-anchors");
+    cmd.assert().success().stdout(
+        predicate::str::contains("  warning: empty id attribute\n")
+            .and(predicate::str::contains("Found 1 warnings")),
+    );
+    site.close().unwrap();
+}
+
+#[test]
+fn test_repeated_warnings() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(r#"<h2 id="">Intro</h2> <h2 id="">Usage</h2>"#)
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--report-empty-anchors");
+    cmd.assert().success().stdout(
+        predicate::str::contains("  warning: empty id attribute (×2)\n")
+            .and(predicate::str::contains("Found 2 warnings")),
+    );
+    site.close().unwrap();
+}
+
+#[test]
+fn test_strict_scheme() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(r#"<a href="htps://example.com">x</a> <a href="tel:+1234">call</a>"#)
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("warning").not());
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".").arg("--strict-scheme");
+    cmd.assert().success().stdout(
+        predicate::str::contains("  warning: unknown URL scheme \"htps\"\n")
+            .and(predicate::str::contains("Found 1 warnings")),
+    );
+    site.close().unwrap();
+}
+
+#[test]
+fn test_warn_duplicate_links() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(
+            r#"<a href="guide.html">Guide</a> <a href="faq.html">Read more</a>
+            <a href="guide.html">Read more</a>"#,
+        )
+        .unwrap();
+    site.child("guide.html").touch().unwrap();
+    site.child("faq.html").touch().unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("warning").not());
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--warn-duplicate-links");
+    cmd.assert().success().stdout(
+        predicate::str::contains("  warning: duplicate link to guide.html\n")
+            .and(predicate::str::contains(
+                "  warning: links with the same text \"Read more\" point to faq.html and guide.html\n",
+            ))
+            .and(predicate::str::contains("Found 2 warnings")),
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_allow_directory_listings() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=files/>files</a> <a href=missing/>missing</a>")
+        .unwrap();
+    site.child("files/report.pdf").touch().unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("error: bad link /files")
+            .and(predicate::str::contains("Found 2 bad links")),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--allow-directory-listings");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("error: bad link /missing")
+            .and(predicate::str::contains("/files").not())
+            .and(predicate::str::contains("Found 1 bad links")),
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_rewrite_map() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(
+            "<a href=/guide#setup>guide</a> <a href=/guide#nope>nope</a> <a href=/faq>faq</a>",
+        )
+        .unwrap();
+    site.child("pages/guide.abcdef.html")
+        .write_str("<h2 id=setup>Setup</h2>")
+        .unwrap();
+    site.child("routes.json")
+        .write_str(r#"{"/guide": "pages/guide.abcdef.html", "/faq": "pages/faq.123456.html"}"#)
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".").arg("--check-anchors");
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains("Found 3 bad links"));
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-anchors")
+        .arg("--rewrite-map")
+        .arg("routes.json");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("error: bad link /faq")
+            .and(predicate::str::contains("error: bad link /guide#nope"))
+            .and(predicate::str::contains("/guide#setup").not())
+            .and(predicate::str::contains("Found 1 bad links"))
+            .and(predicate::str::contains("Found 1 bad anchors")),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--rewrite-map")
+        .arg("missing.json");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Failed to read rewrite map missing.json",
+    ));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_max_file_size() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=dump.html>dump</a>")
+        .unwrap();
+    site.child("dump.html")
+        .write_str(&"<a href=missing.html>x</a>".repeat(100))
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains("error: bad link /missing.html"));
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--max-file-size")
+        .arg("1000");
+    cmd.assert().success().stdout(
+        predicate::str::contains(
+            "  warning: skipped file of 2600 bytes, larger than --max-file-size\n",
+        )
+        .and(predicate::str::contains("missing.html").not())
+        .and(predicate::str::contains("Found 1 warnings")),
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_symlinked_targets() {
+    use std::os::unix::fs::symlink;
+
+    let site = assert_fs::TempDir::new().unwrap();
+    let outside = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=latest.html> <a href=external.html> <a href=dangling.html>")
+        .unwrap();
+    site.child("v2.3.html").write_str("").unwrap();
+    outside.child("page.html").write_str("").unwrap();
+    symlink("v2.3.html", site.child("latest.html").path()).unwrap();
+    symlink(
+        outside.child("page.html").path(),
+        site.child("external.html").path(),
+    )
+    .unwrap();
+    symlink("missing.html", site.child("dangling.html").path()).unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Found 0 bad links"));
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--resolve-symlinked-targets");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("error: bad link /dangling.html")
+            .and(predicate::str::contains("Found 1 bad links")),
+    );
+    site.close().unwrap();
+    outside.close().unwrap();
+}
+
+#[test]
+fn test_anchor_element_policy() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("guide.html")
+        .write_str("<a href=index.html#setup>")
+        .unwrap();
+    site.child("index.html")
+        .write_str(
+            "<a href=#setup> <a href=#install> <span id=setup></span> \
+             <h2 id=install>Install</h2> <span id=unused></span>",
+        )
+        .unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-anchors")
+        .arg("--anchor-element-policy")
+        .arg("headings,landmarks");
+
+    cmd.assert().success().stdout(
+        predicate::str::contains("  warning: linked anchor #setup is defined on <span>")
+            .and(predicate::str::contains("#install").not())
+            .and(predicate::str::contains("#unused").not())
+            .and(predicate::str::contains("Found 1 warnings")),
+    );
+    site.close().unwrap();
+}
+
+#[test]
+fn test_valid_routes() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("routes.txt")
+        .write_str("# settings\n/app.html?page=settings\n/app.html?page=profile\n")
+        .unwrap();
+    site.child("index.html")
+        .write_str(
+            "<a href=app.html?page=settings> <a href=app.html?page=setings> \
+             <a href=app.html> <a href=about.html?ref=home>",
+        )
+        .unwrap();
+    site.child("app.html").touch().unwrap();
+    site.child("about.html").touch().unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-anchors")
+        .arg("--valid-routes")
+        .arg("routes.txt");
+    cmd.assert().failure().code(2).stdout(
+        predicate::str::contains("/app.html?page=setings")
+            .and(predicate::str::contains("page=settings").not())
+            .and(predicate::str::contains("about.html").not())
+            .and(predicate::str::contains("Found 0 bad links")),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--valid-routes")
+        .arg("routes.txt");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--valid-routes requires --check-anchors to be set",
+    ));
+    site.close().unwrap();
+}
+
+#[test]
+fn test_check_label_for() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(
+            r#"<form><label for=name>Name</label> <input id=name>
+            <label for=email>Email</label> <input name=email></form>"#,
+        )
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".").arg("--check-anchors");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-anchors")
+        .arg("--check-label-for");
+    cmd.assert().failure().code(2).stdout(
+        predicate::str::contains("  error: label references missing id email")
+            .and(predicate::str::contains("(from <label for>)"))
+            .and(predicate::str::contains("missing id name").not())
+            .and(predicate::str::contains("Found 1 bad anchors")),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-label-for");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--check-label-for requires --check-anchors to be set",
+    ));
+    site.close().unwrap();
+}
+
+#[test]
+fn test_warn_anchor_encoding_mismatch() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(r#"<a href="guide.html#deploy-til-testmilj%C3%B8">deploy</a> <a href="guide.html#intro">intro</a>"#)
+        .unwrap();
+    site.child("guide.html")
+        .write_str(r#"<h2 id="deploy-til-testmiljø">Deploy</h2> <h2 id="intro">Intro</h2>"#)
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".").arg("--check-anchors");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("warning").not());
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-anchors")
+        .arg("--warn-anchor-encoding-mismatch");
+    cmd.assert().success().stdout(
+        predicate::str::contains(
+            "index.html\n  warning: anchor #deploy-til-testmilj%C3%B8 uses percent-encoding but target id is literal \"deploy-til-testmiljø\"\n",
+        )
+        .and(predicate::str::contains("Found 1 warnings")),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--warn-anchor-encoding-mismatch");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--warn-anchor-encoding-mismatch requires --check-anchors to be set",
+    ));
+    site.close().unwrap();
+}
+
+#[test]
+fn test_anchor_prefix() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=guide.html#setup> <a href=guide.html#faq> <a href=guide.html#nope>")
+        .unwrap();
+    site.child("guide.html")
+        .write_str("<h2 id=user-content-setup>Setup</h2> <h2 id=faq>FAQ</h2>")
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".").arg("--check-anchors");
+    cmd.assert()
+        .failure()
+        .code(2)
+        .stdout(predicate::str::contains("Found 2 bad anchors"));
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-anchors")
+        .arg("--anchor-prefix")
+        .arg("user-content-");
+    cmd.assert().failure().code(2).stdout(
+        predicate::str::contains("error: bad link /guide.html#nope")
+            .and(predicate::str::contains("#setup").not())
+            .and(predicate::str::contains("Found 1 bad anchors")),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--anchor-prefix")
+        .arg("user-content-");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--anchor-prefix requires --check-anchors to be set",
+    ));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_hashbang_routes() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(
+            "<a href=app.html#!/section> <a href=missing.html#!/section> <a href=app.html#top>",
+        )
+        .unwrap();
+    site.child("app.html").write_str("<p>app</p>").unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".").arg("--check-anchors");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("error: bad link /app.html#!/section")
+            .and(predicate::str::contains("Found 1 bad links"))
+            .and(predicate::str::contains("Found 2 bad anchors")),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-anchors")
+        .arg("--hashbang-routes");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains(
+            "error: bad link /missing.html (written as \"missing.html#!/section\")",
+        )
+        .and(predicate::str::contains("/app.html#!").not())
+        .and(predicate::str::contains("error: bad link /app.html#top"))
+        .and(predicate::str::contains("Found 1 bad links"))
+        .and(predicate::str::contains("Found 1 bad anchors")),
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_check_aria_references() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(
+            r#"<p id=desc1>Description</p>
+            <button aria-describedby="desc1 desc2" aria-labelledby="about-label">x</button>"#,
+        )
+        .unwrap();
+    site.child("about.html")
+        .write_str("<span id=about-label>About</span>")
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".").arg("--check-anchors");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-anchors")
+        .arg("--check-aria-references");
+    cmd.assert().failure().code(2).stdout(
+        predicate::str::contains("  error: aria-describedby references missing id desc2")
+            .and(predicate::str::contains(
+                "  error: aria-labelledby references missing id about-label",
+            ))
+            .and(predicate::str::contains("(from <* aria-describedby>)"))
+            .and(predicate::str::contains("desc1").not())
+            .and(predicate::str::contains("Found 2 bad anchors")),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-aria-references");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--check-aria-references requires --check-anchors to be set",
+    ));
+    site.close().unwrap();
+}
+
+#[test]
+fn test_anchor_synonyms() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("synonyms.txt")
+        .write_str("# renamed headings\n#install = #installation\n/faq.html#old = #new\n")
+        .unwrap();
+    site.child("index.html")
+        .write_str(
+            "<a href=guide.html#install> <a href=guide.html#old> <a href=faq.html#old> \
+             <a href=faq.html#install>",
+        )
+        .unwrap();
+    site.child("guide.html")
+        .write_str("<h2 id=installation>Installation</h2> <h2 id=new>New</h2>")
+        .unwrap();
+    site.child("faq.html").write_str("<p id=new>").unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-anchors")
+        .arg("--anchor-synonyms")
+        .arg("synonyms.txt");
+    cmd.assert().failure().code(2).stdout(
+        predicate::str::contains("error: bad link /guide.html#old")
+            .and(predicate::str::contains("error: bad link /faq.html#install"))
+            .and(predicate::str::contains(
+                "  warning: link /guide.html#install uses an old anchor, link to /guide.html#installation instead\n",
+            ))
+            .and(predicate::str::contains(
+                "  warning: link /faq.html#old uses an old anchor, link to /faq.html#new instead\n",
+            ))
+            .and(predicate::str::contains("Found 2 bad anchors")),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--anchor-synonyms")
+        .arg("synonyms.txt");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--anchor-synonyms requires --check-anchors to be set",
+    ));
+    site.close().unwrap();
+}
+
+#[test]
+fn test_anchor_style() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(
+            "<h2>1. Über uns</h2> <h2>Foo</h2> <h2>Foo</h2> \
+             <a href=#ber-uns> <a href=#foo-1>",
+        )
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-anchors")
+        .arg("--heading-anchors")
+        .arg("--anchor-style")
+        .arg("jekyll");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Found 0 bad links"));
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-anchors")
+        .arg("--heading-anchors");
+    cmd.assert()
+        .failure()
+        .code(2)
+        .stdout(predicate::str::contains("#ber-uns").and(predicate::str::contains("#foo-1").not()));
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-anchors")
+        .arg("--anchor-style")
+        .arg("hugo");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--anchor-style requires --heading-anchors to be set",
+    ));
+    site.close().unwrap();
+}
+
+#[test]
+fn test_unused_anchors() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=bar.html#used><h2 id=self>Self</h2><a href=#self>")
+        .unwrap();
+    site.child("bar.html")
+        .write_str("<h1 id=used>Used</h1><h2 id=unused>Unused</h2>")
+        .unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-anchors")
+        .arg("--report-unused-anchors");
+
+    cmd.assert().success().stdout(
+        predicate::str::contains(
+            "Unused anchors:
+  /bar.html #unused
+
+",
+        )
+        .and(predicate::str::contains("Found 1 unused anchors")),
+    );
+    site.close().unwrap();
+}
+
+#[test]
+fn test_html_sources() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("public/index.html")
+        .write_str("<p>Check out <a href=bar.html>this page</a></p>")
+        .unwrap();
+    site.child("src/partial.html")
+        .write_str("<div>\n<p>Check out <a href=bar.html>this page</a></p>\n</div>")
+        .unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("public/")
+        .arg("--sources")
+        .arg("src/");
+
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::is_match(
+            r#"partial\.html
+  error: bad link /bar\.html \(from <a href>\)
+"#,
+        )
+        .unwrap(),
+    );
+    site.close().unwrap();
+}
+
+#[test]
+fn test_sources_format() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("public/index.html")
+        .write_str("<p>Check out <a href=bar.html>this page</a></p>")
+        .unwrap();
+    site.child("src/index.md.tmpl")
+        .write_str("# Home\n\nCheck out [this page](bar.html)\n")
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("public/")
+        .arg("--sources")
+        .arg("src/");
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains("index.md.tmpl").not());
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("public/")
+        .arg("--sources")
+        .arg("src/")
+        .arg("--sources-format")
+        .arg("markdown");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::is_match(
+            r"index\.md\.tmpl\n  error: bad link /bar\.html .*\(from <a href>\)\n",
+        )
+        .unwrap(),
+    );
+    site.close().unwrap();
+}
+
+#[test]
+fn test_sources_glob() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("public/index.html")
+        .write_str("<p>Check out <a href=bar.html>this page</a></p>")
+        .unwrap();
+    site.child("src/docs/index.md")
+        .write_str("# Home\n\nCheck out [this page](bar.html)\n")
+        .unwrap();
+    site.child("src/drafts/index.md")
+        .write_str("# Home\n\nCheck out [this page](bar.html)\n")
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("public/")
+        .arg("--sources")
+        .arg("src/")
+        .arg("--sources-glob")
+        .arg("docs/**/*.md");
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains("docs/index.md"))
+        .stdout(predicate::str::contains("drafts/index.md").not());
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("public/")
+        .arg("--sources-glob")
+        .arg("docs/**/*.md");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--sources-glob requires --sources to be set",
+    ));
+    site.close().unwrap();
+}
+
+#[test]
+fn test_source_mapping() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("public/docs/index.html")
+        .write_str("<p>Check out <a href=../bar/>this page</a></p>")
+        .unwrap();
+    site.child("public/index.html")
+        .write_str("<a href=/baz/>baz</a>")
+        .unwrap();
+    site.child("content/docs.md")
+        .write_str("# Docs\n\nCheck out [this page](../bar/)\n")
+        .unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("public/")
+        .arg("--source-mapping")
+        .arg("{}/index.html=content/{}.md");
+
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains(
+            "content/docs.md\n  error: bad link /bar (written as \"../bar/\") at line 3 (from <a href>)\n",
+        )
+        .and(predicate::str::contains(
+            "index.html\n  error: bad link /baz (from <a href>)\n",
+        ))
+        .and(predicate::str::contains("Found 2 bad links")),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("public/")
+        .arg("--sources")
+        .arg("content/")
+        .arg("--source-mapping")
+        .arg("{}/index.html=content/{}.md");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--source-mapping cannot be combined with --sources",
+    ));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_group_by_source() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("public/docs/index.html")
+        .write_str("<a href=../guide/#nope>guide</a> <a href=../missing/>missing</a>")
+        .unwrap();
+    site.child("public/guide/index.html")
+        .write_str("<h1>Guide</h1>")
+        .unwrap();
+    site.child("content/docs.md")
+        .write_str("# Docs\n\nSee [the guide](../guide/#nope).\n\nAnd [this](../missing/).\n")
+        .unwrap();
+
+    let anchor =
+        "  error: bad link /guide#nope (written as \"../guide/#nope\") at line 3 (from <a href>)\n";
+    let link =
+        "  error: bad link /missing (written as \"../missing/\") at line 5 (from <a href>)\n";
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("public/")
+        .arg("--check-anchors")
+        .arg("--source-mapping")
+        .arg("{}/index.html=content/{}.md");
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains(format!(
+            "content/docs.md\n{link}{anchor}"
+        )));
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("public/")
+        .arg("--check-anchors")
+        .arg("--source-mapping")
+        .arg("{}/index.html=content/{}.md")
+        .arg("--group-by-source");
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains(format!(
+            "content/docs.md\n{anchor}{link}"
+        )));
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("public/")
+        .arg("--group-by-source");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--group-by-source requires --sources or --source-mapping to be set",
+    ));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_directory_index_mode() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=/foo/>foo</a> <a href=/bar/index.html>bar</a>")
+        .unwrap();
+    site.child("foo.html").touch().unwrap();
+    site.child("bar/index.html").touch().unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("error: bad link /foo (from <a href>)\n")
+            .and(predicate::str::contains("bar/index.html").not()),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--directory-index-mode")
+        .arg("same-name-html");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--directory-index-mode")
+        .arg("none");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("error: bad link /foo (from <a href>)\n")
+            .and(predicate::str::contains("Found 1 bad links")),
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_filesystem_mode() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=/about.html>a</a> <a href=about.html>b</a> <a href=//example.com>c</a>")
+        .unwrap();
+    site.child("about.html").touch().unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--filesystem-mode");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("  error: absolute link /about.html (from <a href>)\n")
+            .and(predicate::str::contains("Found 1 bad links")),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--filesystem-mode")
+        .arg("--message-template")
+        .arg("{kind}: {href}");
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains("\nabsolute link: /about.html\n"));
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--filesystem-mode")
+        .arg("--format")
+        .arg("json");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains(r#""href": "about.html""#)
+            .and(predicate::str::contains(r#""absolute": true"#))
+            .and(predicate::str::contains("//about.html").not()),
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_strict_trailing_slash() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=/foo/>foo</a> <a href=/bar>bar</a> <a href=/baz.html/>baz</a>")
+        .unwrap();
+    site.child("foo/index.html")
+        .write_str("<a href=../>home</a>")
+        .unwrap();
+    site.child("bar/index.html").touch().unwrap();
+    site.child("baz.html").touch().unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--strict-trailing-slash");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("error: bad link /bar (from <a href>)\n")
+            .and(predicate::str::contains(
+                "error: bad link /baz.html/ (from <a href>)\n",
+            ))
+            .and(predicate::str::contains("/foo/").not())
+            .and(predicate::str::contains("Found 2 bad links")),
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_brotli() {
+    use std::io::Write;
+
+    fn compress(html: &str) -> Vec<u8> {
+        let mut writer = brotli::CompressorWriter::new(Vec::new(), 4096, 11, 22);
+        writer.write_all(html.as_bytes()).unwrap();
+        writer.into_inner()
+    }
+
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html.br")
+        .write_binary(&compress(
+            "<a href=/foo.html>foo</a> <a href=/bar.html>bar</a>",
+        ))
+        .unwrap();
+    site.child("foo.html.br")
+        .write_binary(&compress("<a href=/>home</a>"))
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("index.html.br\n  error: bad link /bar.html (from <a href>)\n")
+            .and(predicate::str::contains("/foo.html").not())
+            .and(predicate::str::contains("Found 1 bad links")),
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_dump_external_links() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=https://example.com/b></a><a href=https://example.com/a></a>")
+        .unwrap();
+    site.child("other.html")
+        .write_str("<a href=https://example.com/b></a><a href=https://example.com/b></a>")
+        .unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("dump-external-links")
+        .arg("--base-path")
+        .arg(".");
+
+    cmd.assert().success().stdout(predicate::str::ends_with(
+        r#"
+References per external link:
+  3 https://example.com/b
+  1 https://example.com/a
+"#,
+    ));
+    site.close().unwrap();
+}
+
+#[test]
+fn test_list_schemes() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(
+            "<a href=https://example.com/></a><a href=HTTPS://example.com/a></a>\
+             <a href=mailto:foo@example.com></a><a href=htttp://example.com></a>\
+             <a href=//cdn.example.com/a.js></a><a href=other.html></a>",
+        )
+        .unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("list-schemes")
+        .arg("--base-path")
+        .arg(".");
+
+    cmd.assert().success().stdout(predicate::str::ends_with(
+        r#"
+Links per scheme:
+  2 https
+  1 // (protocol-relative)
+  1 htttp
+  1 mailto
+"#,
+    ));
+    site.close().unwrap();
+}
+
+#[test]
+fn test_list_protocol_relative() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(
+            "<script src=//cdn.example.com/a.js></script>\
+             <a href=//example.com/b></a><a href=//example.com/b></a>\
+             <a href=https://example.com/c></a><a href=other.html></a>",
+        )
+        .unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("list-protocol-relative")
+        .arg("--base-path")
+        .arg(".");
+
+    cmd.assert().success().stdout(
+        predicate::str::contains(
+            "index.html: //cdn.example.com/a.js -> https://cdn.example.com/a.js\n",
+        )
+        .and(predicate::str::contains(
+            "index.html: //example.com/b -> https://example.com/b\n",
+        ))
+        .and(predicate::str::contains("//example.com/b ->").count(1))
+        .and(predicate::str::contains("example.com/c").not())
+        .and(predicate::str::contains("other.html").not()),
+    );
+    site.close().unwrap();
+}
+
+#[test]
+fn test_check_sitemap() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("sitemap.xml")
+        .write_str(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+              <url><loc>https://example.com/</loc></url>
+              <url><loc>https://example.com/about/</loc></url>
+              <url><loc>https://example.com/missing.html</loc></url>
+            </urlset>"#,
+        )
+        .unwrap();
+    site.child("index.html").touch().unwrap();
+    site.child("about/index.html").touch().unwrap();
+    site.child("unlisted.html").touch().unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("check-sitemap")
+        .arg("--base-path")
+        .arg(".");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains(
+            "  error: sitemap lists missing page https://example.com/missing.html\n",
+        )
+        .and(predicate::str::contains("about").not())
+        .and(predicate::str::contains("unlisted.html").not())
+        .and(predicate::str::contains(
+            "Found 1 missing pages in the sitemap",
+        )),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("check-sitemap")
+        .arg("--base-path")
+        .arg(".")
+        .arg("--report-unlisted-pages");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("  warning: page /unlisted.html is not in the sitemap\n")
+            .and(predicate::str::contains("page /about ").not())
+            .and(predicate::str::contains("page / ").not()),
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_emit_inventory() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(
+            "<a href=a.html>a</a><a href=missing.html>b</a><a href=https://example.com>c</a>",
+        )
+        .unwrap();
+    site.child("a.html")
+        .write_str("<a href=index.html>home</a>")
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("emit-inventory")
+        .arg("--base-path")
+        .arg(".")
+        .arg("--format")
+        .arg("csv");
+
+    cmd.assert().success().stdout(
+        predicate::str::starts_with("source,href,target,external,broken\n")
+            .and(predicate::str::contains("a.html,index.html,"))
+            .and(predicate::str::contains(
+                "index.html,a.html,/a.html,false,false\n",
+            ))
+            .and(predicate::str::contains(
+                "index.html,missing.html,/missing.html,false,true\n",
+            ))
+            .and(predicate::str::contains(
+                "index.html,https://example.com,https://example.com,true,\n",
+            )),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("emit-inventory")
+        .arg("--base-path")
+        .arg(".");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let entries: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(entries.as_array().unwrap().len(), 4);
+    assert_eq!(entries[0]["broken"], false);
+    assert_eq!(entries[1]["href"], "a.html");
+    assert_eq!(entries[1]["broken"], false);
+    assert_eq!(entries[3]["external"], true);
+    assert!(entries[3]["broken"].is_null());
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_dump_external_links_exclude_domains() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(
+            "<a href=https://cdn.example.com/a.js></a>\
+             <a href=https://www.analytics.test/></a>\
+             <a href=https://third-party.org/page></a>\
+             <a href=https://notexample.com/></a>",
+        )
+        .unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("dump-external-links")
+        .arg("--base-path")
+        .arg(".")
+        .arg("--exclude-external-domains")
+        .arg("example.com,analytics.test");
+
+    cmd.assert().success().stdout(predicate::str::ends_with(
+        r#"
+References per external link:
+  1 https://notexample.com/
+  1 https://third-party.org/page
+"#,
+    ));
+    site.close().unwrap();
+}
+
+#[test]
+fn test_root() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("public/index.html")
+        .write_str("<a href=/missing.html>")
+        .unwrap();
+    site.child("public/blog/index.html")
+        .write_str("<a href=/index.html>home</a> <a href=/blog/missing.html>")
+        .unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("public/blog/")
+        .arg("--root")
+        .arg("public/");
+
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("error: bad link /blog/missing.html")
+            .and(predicate::str::contains("Found 1 bad links")),
+    );
+    site.close().unwrap();
+}
+
+#[test]
+fn test_from_tar() {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, contents) in [
+        (
+            "./public/index.html",
+            "<a href=foo.html>foo</a> <a href=bar.html>bar</a>",
+        ),
+        ("./public/foo.html", "<a href=/>home</a>"),
+    ] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, path, contents.as_bytes())
+            .unwrap();
+    }
+    let archive = builder.into_inner().unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.arg("--from-tar").arg("public/").write_stdin(archive);
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains(
+            "public/index.html\n  error: bad link /bar.html (from <a href>)\n",
+        )
+        .and(predicate::str::contains(
+            "Checking 3 links from 2 files (2 documents)",
+        ))
+        .and(predicate::str::contains("Found 1 bad links")),
+    );
+}
+
+#[test]
+fn test_stdin_paths_null() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("public/index.html")
+        .write_str("<a href=missing.html>missing</a>")
+        .unwrap();
+    site.child("public/my docs/page.html")
+        .write_str("<a href=../gone.html>gone</a> <a href=../index.html>home</a>")
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("public/")
+        .arg("--stdin-paths-null")
+        .write_stdin("./public/my docs/page.html\0public/deleted.html\0");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("error: bad link /gone.html")
+            .and(predicate::str::contains("missing.html").not())
+            .and(predicate::str::contains("Found 1 bad links")),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("public/")
+        .arg("--stdin-paths-null")
+        .arg("--from-tar");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--stdin-paths-null cannot be combined with --from-tar",
+    ));
+    site.close().unwrap();
+}
+
+#[test]
+fn test_format_json_success() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=bar.html>")
+        .unwrap();
+    site.child("bar.html").touch().unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--format")
+        .arg("json");
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(
+        report,
+        serde_json::json!({
+            "files": [],
+            "bad_links": 0,
+            "bad_anchors": 0,
+            "warnings": 0,
+            "success": true,
+        })
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_format_json() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=bar.html>")
+        .unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--format")
+        .arg("json");
+
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::starts_with("{")
+            .and(predicate::str::contains(r#""href": "bar.html""#))
+            .and(predicate::str::contains(r#""bad_links": 1"#))
+            .and(predicate::str::contains("Reading files").not()),
+    );
+    site.close().unwrap();
+}
+
+#[test]
+fn test_json_compact() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=bar.html>")
+        .unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--format")
+        .arg("json")
+        .arg("--json-compact");
+
+    let output = cmd.assert().failure().code(1).get_output().stdout.clone();
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(output.lines().count(), 1, "{}", output);
+    assert!(output.contains(r#""href":"bar.html""#), "{}", output);
+    assert!(output.contains(r#""bad_links":1"#), "{}", output);
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".").arg("--json-compact");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--json-compact requires --format json",
+    ));
+    site.close().unwrap();
+}
+
+#[test]
+fn test_format_ndjson() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=bar.html>")
+        .unwrap();
+    site.child("about.html").touch().unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--format")
+        .arg("ndjson");
+
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::starts_with(r#"{"event":"file_scanned","path":"#)
+            .and(predicate::str::contains(r#"index.html"}"#))
+            .and(predicate::str::contains(r#"about.html"}"#))
+            .and(predicate::str::contains(
+                r#""kind":"link","line":null,"href":"bar.html","element":"<a href>","count":1,"written_as":["bar.html"]}"#,
+            ))
+            .and(predicate::str::ends_with(
+                r#"{"event":"done","summary":{"bad_links":1,"bad_anchors":0,"warnings":0,"errors":0,"success":false}}"#.to_owned() + "\n",
+            ))
+            .and(predicate::str::contains("Reading files").not()),
+    );
+    site.close().unwrap();
+}
+
+#[test]
+fn test_format_json_read_errors() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<link rel=stylesheet href=theme.css>")
+        .unwrap();
+    site.child("theme.css")
+        .write_binary(b"body { background: url(\xff.png); }")
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-css-imports");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to read file"));
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-css-imports")
+        .arg("--format")
+        .arg("json");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains(r#""kind": "read_error""#)
+            .and(predicate::str::contains("theme.css"))
+            .and(predicate::str::contains(r#""bad_links": 0"#))
+            .and(predicate::str::contains(r#""success": false"#)),
+    );
+    site.close().unwrap();
+}
+
+#[test]
+fn test_interactive_requires_text_output() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html").touch().unwrap();
+
+    // Without the interactive feature, this fails for a different reason, but it fails either way.
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--interactive")
+        .arg("--format")
+        .arg("json");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--interactive"));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_print_json_schema() {
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.arg("--print-json-schema");
+
+    cmd.assert().success().stdout(
+        predicate::str::contains(r#""title": "Report""#)
+            .and(predicate::str::contains(r#""CountedLink""#))
+            .and(predicate::str::contains(r#""StaleBaselineEntry""#)),
+    );
+}
+
+#[test]
+fn test_check_css_imports() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<link rel=stylesheet href=css/theme.css>")
+        .unwrap();
+    site.child("css/theme.css")
+        .write_str("@import 'fonts.css';\nbody { background: url(../img/bg.png); }")
+        .unwrap();
+    site.child("css/fonts.css").touch().unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-css-imports");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains(
+            "theme.css\n  error: bad link /img/bg.png (written as \"../img/bg.png\") (from url())\n",
+        )
+            .and(predicate::str::contains("Found 1 bad links")),
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_require_html_extension() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=about>about</a> <a href=guide/>guide</a> <a href=about.html#team>team</a> <a href=logo.png>logo</a>")
+        .unwrap();
+    site.child("about.html").touch().unwrap();
+    site.child("guide/index.html").touch().unwrap();
+    site.child("logo.png").touch().unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--directory-index-mode")
+        .arg("same-name-html");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--directory-index-mode")
+        .arg("same-name-html")
+        .arg("--require-html-extension");
+    cmd.assert().success().stdout(
+        predicate::str::contains("  warning: link about has no .html extension\n")
+            .and(predicate::str::contains(
+                "  warning: link guide/ has no .html extension\n",
+            ))
+            .and(predicate::str::contains("about.html#team").not())
+            .and(predicate::str::contains("logo.png").not())
+            .and(predicate::str::contains("Found 2 warnings")),
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_picture_sources() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(
+            r#"<picture>
+            <source srcset="wide.avif" media="(min-width: 800px)">
+            <source srcset="narrow.avif">
+            <img src="fallback.png">
+            </picture>"#,
+        )
+        .unwrap();
+    site.child("narrow.avif").touch().unwrap();
+    site.child("fallback.png").touch().unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains(
+            r#"error: bad link /wide.avif (from <source srcset media="(min-width: 800px)">)"#,
+        )
+        .and(predicate::str::contains("Found 1 bad links")),
+    );
+    site.close().unwrap();
+}
+
+#[test]
+fn test_redirects() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("_redirects")
+        .write_str("/old  /new.html  301\n/app/*  /app.html  200\n")
+        .unwrap();
+    site.child("index.html")
+        .write_str("<a href=old>old</a> <a href=app/settings>app</a> <a href=missing>missing</a>")
+        .unwrap();
+    site.child("new.html").touch().unwrap();
+    site.child("app.html").touch().unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("error: bad link /missing (from <a href>)\n")
+            .and(predicate::str::contains("/old").not())
+            .and(predicate::str::contains("/app/settings").not())
+            .and(predicate::str::contains("Found 1 bad links")),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--warn-redirect-links");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains(
+            "  warning: link /old redirects to /new.html, link to the target directly\n",
+        )
+        .and(predicate::str::contains("/app/settings").not())
+        .and(predicate::str::contains("Found 1 warnings")),
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_report_long_redirect_chains() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("_redirects")
+        .write_str("/2019/*  /2020/:splat\n/2020/*  /posts/:splat\n/about  /team.html\n")
+        .unwrap();
+    site.child("index.html")
+        .write_str("<a href=2019/hello.html>hello</a> <a href=about>about</a>")
+        .unwrap();
+    site.child("posts/hello.html").touch().unwrap();
+    site.child("team.html").touch().unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--report-long-redirect-chains")
+        .arg("1");
+    cmd.assert().success().stdout(
+        predicate::str::contains(
+            "  warning: link /2019/hello.html is redirected 2 times: \
+             /2020/hello.html -> /posts/hello.html\n",
+        )
+        .and(predicate::str::contains("/about").not())
+        .and(predicate::str::contains("Found 1 warnings")),
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_warn_on_redirect_to_404() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("_redirects")
+        .write_str(
+            "/about  /team.html
+/old  /docs/index.html
+/guide  /docs/
+/broken  /missing-page.html
+/blog/*  /news/:splat
+",
+        )
+        .unwrap();
+    site.child("index.html")
+        .write_str("<a href=about>about</a>")
+        .unwrap();
+    site.child("team.html").touch().unwrap();
+    site.child("docs/index.html").touch().unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--warn-on-redirect-to-404");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains(
+            "  error: redirect /broken targets missing file /missing-page.html\n",
+        )
+        .and(predicate::str::contains("/team.html").not())
+        .and(predicate::str::contains("/docs").not())
+        .and(predicate::str::contains("Found 1 bad links")),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--warn-on-redirect-to-404")
+        .arg("--strict-trailing-slash");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("/docs")
+            .not()
+            .and(predicate::str::contains("Found 1 bad links")),
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_follow_meta_refresh_chain() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=a.html>a</a> <a href=c.html>c</a>")
+        .unwrap();
+    site.child("a.html")
+        .write_str(r#"<meta http-equiv="refresh" content="0; url=b.html">"#)
+        .unwrap();
+    site.child("b.html")
+        .write_str(r#"<meta http-equiv="refresh" content="0; url=c.html">"#)
+        .unwrap();
+    site.child("c.html").touch().unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--warn-redirect-links")
+        .arg("--report-long-redirect-chains")
+        .arg("1");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("warning").not());
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--warn-redirect-links")
+        .arg("--report-long-redirect-chains")
+        .arg("1")
+        .arg("--follow-meta-refresh-chain");
+    cmd.assert().success().stdout(
+        predicate::str::contains(
+            "  warning: link /a.html redirects to /b.html, link to the target directly\n",
+        )
+        .and(predicate::str::contains(
+            "  warning: link /b.html redirects to /c.html, link to the target directly\n",
+        ))
+        .and(predicate::str::contains(
+            "  warning: link /a.html is redirected 2 times: /b.html -> /c.html\n",
+        ))
+        .and(predicate::str::contains("Found 3 warnings")),
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_link_attribute() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=foo.html>foo</a> <img src=logo.png> <div data-href=bar.html></div>")
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--link-attribute")
+        .arg("div:data-href");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("error: bad link /bar.html (from <div data-href>)\n")
+            .and(predicate::str::contains(
+                "error: bad link /foo.html (from <a href>)\n",
+            ))
+            .and(predicate::str::contains("Found 3 bad links")),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--no-default-tags")
+        .arg("--link-attribute")
+        .arg("a:href");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("error: bad link /foo.html (from <a href>)\n")
+            .and(predicate::str::contains("logo.png").not())
+            .and(predicate::str::contains("bar.html").not())
+            .and(predicate::str::contains("Found 1 bad links")),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--no-default-tags");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--no-default-tags requires --link-attribute to be set",
+    ));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_ignore_selector() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(
+            r#"<nav><a href="missing-nav.html">Nav</a></nav>
+            <footer class="site-footer"><a href="missing-footer.html">Footer</a></footer>
+            <a href="missing-main.html">Main</a>"#,
+        )
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--ignore-selector")
+        .arg("nav")
+        .arg("--ignore-selector")
+        .arg(".site-footer");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("error: bad link /missing-main.html")
+            .and(predicate::str::contains("missing-nav.html").not())
+            .and(predicate::str::contains("missing-footer.html").not())
+            .and(predicate::str::contains("Found 1 bad links")),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--ignore-selector")
+        .arg("nav > a");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "unsupported selector \"nav > a\", expected a tag, .class or #id",
+    ));
+    site.close().unwrap();
+}
+
+#[test]
+fn test_strict() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<img src=a.png srcset='a.png 300w, b.png 600w'>")
+        .unwrap();
+    site.child("a.png").touch().unwrap();
+    site.child("b.png").touch().unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--strict")
+        .arg("1");
+    cmd.assert().failure().code(2).stdout(
+        predicate::str::contains("warning: srcset with width descriptors")
+            .and(predicate::str::contains("Found 0 bad anchors")),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--strict")
+        .arg("3");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown --strict version 3"));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_count_only() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=bar.html></a><a href=baz.html></a><a href=index.html#missing></a>")
+        .unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-anchors")
+        .arg("--count-only");
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout("bad_links=2 bad_anchors=1\n");
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--count-only")
+        .arg("--format")
+        .arg("json");
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout("{\"bad_anchors\":0,\"bad_links\":2,\"success\":false}\n");
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_quiet() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=index.html></a><a href=other.html></a>")
+        .unwrap();
+    site.child("other.html").write_str("").unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert().success().stderr(
+        predicate::str::is_match(
+            r"^Checked 2 links across 2 files in \d+\.\d\d seconds \(\d+ files/s\)\n$",
+        )
+        .unwrap(),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".").arg("--quiet");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Reading files").not())
+        .stderr("");
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_github_step_summary() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=bar.html></a><a href=bar.html></a>")
+        .unwrap();
+    let summary = site.child("summary.md");
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .env("GITHUB_STEP_SUMMARY", summary.path())
+        .arg(".")
+        .arg("--github-actions");
+    cmd.assert().failure().code(1);
+
+    summary.assert(
+        predicate::str::is_match(
+            r#"^## hyperlink
+
+Found 2 bad links and 0 bad anchors\.
+
+\| File \| Problem \| Link \| Count \|
+\| --- \| --- \| --- \| --- \|
+\| `\..index\.html` \| bad link \| `/bar\.html` \| 2 \|
+$"#,
+        )
+        .unwrap(),
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_group_consecutive_lines() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=a.html></a>\n<a href=b.html></a>\n<a href=c.html></a>\n")
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--github-actions")
+        .arg("--html-tab-size")
+        .arg("4");
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains("::error file=").count(3));
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--github-actions")
+        .arg("--html-tab-size")
+        .arg("4")
+        .arg("--group-consecutive-lines");
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(
+            predicate::str::contains("::error file=")
+                .count(1)
+                .and(predicate::str::contains(
+                ",line=1::bad links:%0A  a.html (line 1)%0A  b.html (line 2)%0A  c.html (line 3)\n",
+            )),
+        );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--group-consecutive-lines");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--group-consecutive-lines requires --github-actions to be set",
+    ));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_check_tracks() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(
+            "<video src=a.mp4>\
+             <track src=en.vtt srclang=en default>\
+             <track src=de.vtt srclang=de default>\
+             </video>",
+        )
+        .unwrap();
+    site.child("a.mp4").touch().unwrap();
+    site.child("en.vtt").touch().unwrap();
+    site.child("de.vtt").touch().unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("  warning:").not());
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".").arg("--check-tracks");
+    cmd.assert().success().stdout(
+        predicate::str::contains(
+            "  warning: <video> has 2 default subtitles or captions tracks, at most one is allowed\n",
+        )
+        .and(predicate::str::contains("Found 1 warnings")),
+    );
+
+    std::fs::remove_file(site.child("de.vtt").path()).unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("  error: bad link /de.vtt")
+            .and(predicate::str::contains("(from <track src>)"))
+            .and(predicate::str::contains("Found 1 bad links")),
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_unrendered_template() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href='{{ url }}'></a><a href=index.html></a>")
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("unrendered template")
+            .not()
+            .and(predicate::str::contains("Found 1 bad links")),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--warn-unrendered-templates");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("  warning: href looks like unrendered template: {{ url }}\n")
+            .and(predicate::str::contains("Found 1 bad links"))
+            .and(predicate::str::contains("Found 1 warnings")),
+    );
+
+    // Not part of --strict 1, so that upgrading does not make a passing build fail.
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--strict")
+        .arg("1");
+    cmd.assert()
+        .stdout(predicate::str::contains("unrendered template").not());
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--strict")
+        .arg("2");
+    cmd.assert().stdout(predicate::str::contains(
+        "href looks like unrendered template: {{ url }}",
+    ));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_link_density_warning() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=a.html></a><a href=b.html></a><a href=c.html></a><a href=ok.html></a>")
+        .unwrap();
+    site.child("ok.html")
+        .write_str("<a href=index.html></a><a href=missing.html></a>")
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--link-density-warning")
+        .arg("50%");
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("has 3/4 broken links, likely systemic")
+            .and(predicate::str::contains("has 1/2 broken links").not())
+            .and(predicate::str::contains("Found 1 warnings")),
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_metrics_file() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=bar.html>bar</a> <a href=baz.html>baz</a>")
+        .unwrap();
+    site.child("bar.html").touch().unwrap();
+    site.child("style.css").touch().unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--metrics-file")
+        .arg("metrics.json");
+    cmd.assert().failure().code(1);
+
+    let metrics: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(site.child("metrics.json").path()).unwrap())
+            .unwrap();
+    assert_eq!(metrics["files"], 3);
+    assert_eq!(metrics["documents"], 2);
+    assert_eq!(metrics["links"], 2);
+    assert_eq!(metrics["bad_links"], 1);
+    assert_eq!(metrics["errors"], 0);
+    assert!(metrics["durations"]["read_html"].is_f64());
+    assert!(metrics["durations"]["total"].is_f64());
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_metrics_file_read_errors() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<link rel=stylesheet href=theme.css>")
+        .unwrap();
+    site.child("theme.css")
+        .write_binary(b"body { background: url(\xff.png); }")
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-css-imports")
+        .arg("--format")
+        .arg("json")
+        .arg("--metrics-file")
+        .arg("metrics.json");
+    cmd.assert().failure().code(1);
+
+    let metrics: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(site.child("metrics.json").path()).unwrap())
+            .unwrap();
+    assert_eq!(metrics["bad_links"], 0);
+    assert_eq!(metrics["errors"], 1);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_fail_fast() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=index.html#missing></a><a href=a.html></a><a href=b.html></a>")
+        .unwrap();
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-anchors")
+        .arg("--fail-fast");
+
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::contains("Stopping after the first broken link")
+            .and(predicate::str::contains("Found 1 bad links"))
+            .and(predicate::str::contains("Found 0 bad anchors")),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--fail-fast")
+        .arg("--baseline")
+        .arg("baseline.txt");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--fail-fast cannot be combined with --baseline",
+    ));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_resolve_url() {
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.arg("resolve-url")
+        .arg("--base-path")
+        .arg("public/")
+        .arg("--file")
+        .arg("public/docs/index.html")
+        .arg("../guide/index.html#Setup");
+
+    cmd.assert().success().stdout(
+        r#"document: /docs
+without anchors: /guide
+with anchors: /guide#Setup
+"#,
+    );
+}
+
+#[test]
+fn test_normalize() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("docs/intro.html")
+        .write_str(
+            "<a href=../docs/./guide.html>guide</a> <a href='/blog/../about/#team'>about</a> \
+             <a data-href=../docs/./guide.html href=guide.html>",
+        )
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("normalize")
+        .arg("--base-path")
+        .arg(".");
+    cmd.assert().success().stdout(
+        predicate::str::contains("  ../docs/./guide.html -> guide.html\n")
+            .and(predicate::str::contains(
+                "  /blog/../about/#team -> /about/#team\n",
+            ))
+            .and(predicate::str::contains(
+                "Found 2 links to normalize in 1 files",
+            )),
+    );
+    site.child("docs/intro.html")
+        .assert(predicate::str::starts_with("<a href=../docs/./guide.html>"));
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("normalize")
+        .arg("--base-path")
+        .arg(".")
+        .arg("--write");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Normalized 2 links in 1 files"));
+    site.child("docs/intro.html").assert(
+        "<a href=guide.html>guide</a> <a href='/about/#team'>about</a> \
+         <a data-href=../docs/./guide.html href=guide.html>",
+    );
+    site.close().unwrap();
+}
+
+#[test]
+fn test_normalize_script_and_comments() {
+    let site = assert_fs::TempDir::new().unwrap();
+    let html = "<script>document.write(' <a href=\"a/../b.html\">')</script>\n\
+                <!-- <a href=a/../b.html> -->\n\
+                <a HREF = 'a/../b.html'>b</a>";
+    site.child("index.html").write_str(html).unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("normalize")
+        .arg("--base-path")
+        .arg(".")
+        .arg("--write");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Normalized 1 links in 1 files"));
+    site.child("index.html").assert(
+        "<script>document.write(' <a href=\"a/../b.html\">')</script>\n\
+         <!-- <a href=a/../b.html> -->\n\
+         <a HREF = 'b.html'>b</a>",
+    );
+    site.close().unwrap();
+}
+
+#[test]
+fn test_normalize_skipped() {
+    let site = assert_fs::TempDir::new().unwrap();
+    let html = "<img srcset=\"../docs/a.png 1x, ../docs/b.png 2x\">\n\
+                <meta http-equiv=refresh content=\"0; url=../docs/./index.html\">\n\
+                <a href=\"../docs/./guide.html?a=1&amp;b=2\">x</a>\n\
+                <object codebase=\"/assets/\" data=\"../assets/./movie.swf\"></object>\n\
+                <a href=../docs/./guide.html>guide</a> <link href=../docs/./guide.html>";
+    site.child("docs/intro.html").write_str(html).unwrap();
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("normalize")
+        .arg("--base-path")
+        .arg(".");
+    cmd.assert().success().stdout(
+        predicate::str::contains("  ../docs/./guide.html -> guide.html\n")
+            .and(predicate::str::contains(
+                "  ../docs/a.png -> a.png (skipped, cannot rewrite <img srcset>)\n",
+            ))
+            .and(predicate::str::contains(
+                "  ../docs/./index.html -> index.html (skipped, cannot rewrite <meta http-equiv=refresh>)\n",
+            ))
+            .and(predicate::str::contains(
+                "  ../docs/./guide.html?a=1&b=2 -> guide.html?a=1&b=2 (skipped, written differently in the file)\n",
+            ))
+            .and(predicate::str::contains("movie.swf").not())
+            .and(predicate::str::contains(
+                "Found 2 links to normalize in 1 files",
+            ))
+            .and(predicate::str::contains(
+                "Skipped 4 links that have to be changed by hand",
+            )),
+    );
+
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.current_dir(site.path())
+        .arg("normalize")
+        .arg("--base-path")
+        .arg(".")
+        .arg("--write");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Normalized 2 links in 1 files"));
+    site.child("docs/intro.html").assert(
+        html.replace(
+            "<a href=../docs/./guide.html>guide</a> <link href=../docs/./guide.html>",
+            "<a href=guide.html>guide</a> <link href=guide.html>",
+        )
+        .as_str(),
+    );
+    site.close().unwrap();
+}
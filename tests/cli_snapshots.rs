@@ -13,25 +13,157 @@ fn test_no_args() {
     ----- stdout -----
     A command-line tool to find broken links in your static site.
 
-    Usage: [-j=ARG] (COMMAND ... | [--check-anchors] [--sources=ARG] [--github-actions] [BASE-PATH])
+    Usage: [-j=ARG] (COMMAND ... | [--check-anchors] [--report-unused-anchors] [--report-empty-anchors]
+    [--ignore-anchor-case] [--unicode-anchor-case] [--heading-anchors] [--anchor-style=STYLE] [
+    --anchor-attribute=NAME]... [--anchor-element-policy=TAGS] [--valid-routes=FILE] [--anchor-synonyms=
+    FILE] [--anchor-prefix=PREFIX] [--hashbang-routes] [--check-aria-references] [--check-label-for] [
+    --warn-anchor-encoding-mismatch] [--ignore-fragments] [--sources=ARG] [--sources-format=FORMAT] [
+    --sources-glob=GLOB] [--source-mapping=PATTERN] [--group-by-source] [--check-srcset-sizes] [
+    --check-tracks] [--report-parse-errors] [--check-css-imports] [--require-html-extension] [
+    --warn-redirect-links] [--report-long-redirect-chains=HOPS] [--warn-on-redirect-to-404] [
+    --follow-meta-refresh-chain] [--link-attribute=<TAG:ATTRIBUTE>]... [--ignore-selector=SELECTOR]... [
+    --no-default-tags] [--parse-comments] [--report-mixed-separators] [--warn-unrendered-templates] [
+    --strict-scheme] [--warn-duplicate-links] [--resolve-symlinked-targets] [--allow-directory-listings]
+    [--max-file-size=BYTES] [--max-open-files=N] [--strict=VERSION] [--directory-index-mode=MODE] [
+    --strict-trailing-slash] [--filesystem-mode] [--assume-extension=EXTENSION] [--rewrite-map=FILE] [
+    --root=PATH] [--from-tar] [--stdin-paths-null] [--github-actions] [--group-consecutive-lines] [
+    --format=FORMAT] [--json-compact] [--message-template=TEMPLATE] [--html-tab-size=N] [--interactive]
+    [--link-density-warning=THRESHOLD] [--dedupe-across-files] [--fail-fast] [--count-only] [--quiet] [
+    --metrics-file=PATH] [--print-json-schema] [--baseline=FILE] [--write-baseline] [BASE-PATH])
 
     Available positional items:
-        BASE-PATH             the static file path to check
+        BASE-PATH                 the static file path to check
 
     Available options:
-        -V, --version         print version information and exit
-        -j, --jobs=ARG        how many threads to use, default is to try and saturate CPU
-            --check-anchors   whether to check for valid anchor references
-            --sources=ARG     path to directory of markdown files to use for reporting errors
-            --github-actions  enable specialized output for GitHub actions
-        -h, --help            Prints help information
+        -V, --version             print version information and exit
+        -j, --jobs=ARG            how many threads to use, default is to try and saturate CPU
+            --check-anchors       whether to check for valid anchor references
+            --report-unused-anchors  list anchors that are defined but never linked to, requires
+                                  --check-anchors
+            --report-empty-anchors  warn about empty id and name attributes, which cannot be linked to
+            --ignore-anchor-case  compare anchors case-insensitively, only folding ASCII letters
+            --unicode-anchor-case  compare anchors case-insensitively, using Unicode lowercasing
+            --heading-anchors     headings without id define an anchor derived from their text, like on
+                                  GitHub
+            --anchor-style=STYLE  derive heading anchors like github (default), hugo, jekyll or
+                                  docusaurus
+            --anchor-attribute=NAME  also treat this attribute as an anchor definition like id, can be
+                                  repeated
+            --anchor-element-policy=TAGS  warn about linked anchors on other elements than these, like
+                                  headings,landmarks
+            --valid-routes=FILE   check links with query strings against this list of routes, one per
+                                  line, like /app?page=a
+            --anchor-synonyms=FILE  accept old anchors in place of new ones from this file, one per
+                                  line, like #old = #new
+            --anchor-prefix=PREFIX  accept anchors with or without PREFIX, for generators that prefix
+                                  generated ids
+            --hashbang-routes     treat fragments starting with #! as client-side routes instead of
+                                  anchors
+            --check-aria-references  check that aria-describedby and aria-labelledby only reference ids
+                                  of the same page
+            --check-label-for     check that <label for> only references ids of the same page
+            --warn-anchor-encoding-mismatch  warn about links that percent-encode an anchor whose id is
+                                  written literally, or vice versa
+            --ignore-fragments    only check that linked pages exist, never anchors, even on links like
+                                  #foo
+            --sources=ARG         path to directory of markdown or html files to use for reporting
+                                  errors
+            --sources-format=FORMAT  parse all files in --sources as markdown or html, instead of by
+                                  their extension
+            --sources-glob=GLOB   only use files in --sources whose relative path matches this glob,
+                                  like 'docs/**/*.md'
+            --source-mapping=PATTERN  map HTML files to source files by path instead, like
+                                  '{}/index.html=content/{}.md'
+            --group-by-source     list the broken links and anchors of each source file together, sorted
+                                  by line
+            --check-srcset-sizes  warn about <img srcset> with width descriptors but no sizes attribute
+            --check-tracks        warn about <track> elements with an unknown kind, missing srclang or
+                                  conflicting defaults
+            --report-parse-errors  warn about HTML syntax errors that may cause links to be missed
+            --check-css-imports   check links in @import rules and url() functions of CSS files
+            --require-html-extension  warn about links to pages that are written without .html, like
+                                  /about or /guide/
+            --warn-redirect-links  warn about links to the source of a redirect in _redirects instead of
+                                  to its target
+            --report-long-redirect-chains=HOPS  warn about links that _redirects redirects more than
+                                  HOPS times before reaching a file
+            --warn-on-redirect-to-404  report rules in _redirects whose target does not exist as errors
+            --follow-meta-refresh-chain  treat pages with <meta http-equiv=refresh> like redirects in
+                                  _redirects
+            --link-attribute=<TAG:ATTRIBUTE>  also extract links from this tag and attribute, like
+                                  div:data-href, can be repeated
+            --ignore-selector=SELECTOR  do not extract links from elements matching this selector, like
+                                  nav, .footer or #sidebar
+            --no-default-tags     only extract links from --link-attribute, not from built-in ones like
+                                  <a href>
+            --parse-comments      also extract links from markup in comments, like IE conditional
+                                  comments
+            --report-mixed-separators  warn about hrefs containing backslashes, like docs\guide.html
+            --warn-unrendered-templates  warn about hrefs that look like unrendered templates, like {{
+                                  url }}
+            --strict-scheme       warn about links with a scheme outside of a known list, like the typo
+                                  htps://
+            --warn-duplicate-links  warn about repeated links within a page, and links with the same
+                                  text but different targets
+            --resolve-symlinked-targets  treat symlinks whose target does not exist as missing, instead
+                                  of as existing files
+            --allow-directory-listings  treat links to directories without index.html as valid, for
+                                  servers that list them
+            --max-file-size=BYTES  skip documents larger than BYTES with a warning, instead of reading
+                                  them
+            --max-open-files=N    how many files to read at the same time, default is 128
+            --strict=VERSION      enable a versioned set of checks and lints, and fail on warnings
+            --directory-index-mode=MODE  how links to directories such as /foo/ resolve: index-html
+                                  (default), same-name-html or none
+            --strict-trailing-slash  treat /foo and /foo/ as different links, only /foo/ resolves to
+                                  foo/index.html
+            --filesystem-mode     check docs that are opened from disk, where links starting with / are
+                                  broken
+            --assume-extension=EXTENSION  try appending this extension to links without one, such as
+                                  html for href="/about"
+            --rewrite-map=FILE    JSON file mapping clean URLs to the files they are served from, like
+                                  {"/guide": "guide.abc.html"}
+            --root=PATH           the root path of your server, defaults to BASE-PATH
+            --from-tar            read the site from a tar archive on stdin, BASE-PATH is then a
+                                  directory inside of it
+            --stdin-paths-null    only check the documents whose paths are read from stdin, separated by
+                                  NUL bytes
+            --github-actions      enable specialized output for GitHub actions
+            --group-consecutive-lines  put all bad links of a file into one GitHub actions annotation
+            --format=FORMAT       output format: text (default), json or ndjson
+            --json-compact        print the report of --format json on a single line instead of indented
+            --message-template=TEMPLATE  print each bad link with this template, using {path}, {href},
+                                  {line}, {column} and {kind}
+            --html-tab-size=N     report line and column of bad links in HTML files, counting tabs as N
+                                  columns
+            --interactive         browse broken links in a terminal UI, requires the interactive feature
+            --link-density-warning=THRESHOLD  warn about pages with more than THRESHOLD broken links, a
+                                  count like 10 or a percentage like 50%
+            --dedupe-across-files  also count how many distinct targets the bad links point to across
+                                  all files
+            --fail-fast           only report the first broken link, all files are still read
+            --count-only          only print the number of bad links and bad anchors
+            --quiet               do not print progress messages and the timing summary
+            --metrics-file=PATH   write JSON metrics such as file counts and phase durations to PATH
+            --print-json-schema   print the JSON schema of the output of --format json and exit
+            --baseline=FILE       file of known broken links, which are ignored
+            --write-baseline      record all current broken links in the --baseline file and exit
+        -h, --help                Prints help information
 
     Available commands:
-        dump-paragraphs       Dump out internal data for markdown or html file.
-        match-all-paragraphs  Attempt to match up all paragraphs from the HTML folder with the Markdown
-                              folder and print
-        dump-external-links   Dump out a list and count of _external_ links.  hyperlink does not check
-                              external links,
+        dump-paragraphs           Dump out internal data for markdown or html file.
+        match-all-paragraphs      Attempt to match up all paragraphs from the HTML folder with the
+                                  Markdown folder and print
+        dump-external-links       Dump out a list and count of _external_ links.  hyperlink does not
+                                  check external links,
+        list-schemes              Count the URL schemes of all links, such as https, mailto or
+                                  javascript.
+        list-protocol-relative    List protocol-relative links and the https:// link they would become.
+        emit-inventory            Print every link of the site with its target and whether it is broken,
+                                  as JSON or CSV.
+        check-sitemap             Check that every URL in sitemap.xml points to an existing page.
+        normalize                 Rewrite links to the canonical form of their target.
+        resolve-url               Print the link target that an href in a HTML file resolves to.
 
 
     ----- stderr -----